@@ -50,8 +50,11 @@ fn main() {
                 .allowlist_type("cef_context_menu_handler_t")
                 .allowlist_type("cef_context_menu_params_t")
                 .allowlist_type("cef_display_handler_t")
+                .allowlist_type("cef_drag_handler_t")
+                .allowlist_type("cef_draggable_region_t")
                 .allowlist_type("cef_frame_t")
                 .allowlist_type("cef_menu_model_t")
+                .allowlist_type("cef_menu_id_t")
                 .allowlist_type("cef_life_span_handler_t")
                 .allowlist_type("cef_binary_value_t")
                 .allowlist_type("cef_errorcode_t")
@@ -70,6 +73,8 @@ fn main() {
                 .allowlist_type("cef_view_t")
                 .allowlist_type("cef_window_delegate_t")
                 .allowlist_type("cef_window_t")
+                .allowlist_type("cef_overlay_controller_t")
+                .allowlist_type("cef_docking_mode_t")
                 .allowlist_type("cef_main_args_t")
                 .allowlist_type("cef_settings_t")
                 .allowlist_type("cef_window_handle_t")
@@ -82,11 +87,18 @@ fn main() {
                 .allowlist_function("cef_string_list_append")
                 .allowlist_function("cef_string_list_value")
                 .allowlist_function("cef_string_list_size")
+                .allowlist_function("cef_string_list_free")
                 .allowlist_function("cef_v8value_create_string")
+                .allowlist_function("cef_v8value_create_int")
                 .allowlist_type("cef_file_dialog_mode_t")
                 .allowlist_type("cef_run_file_dialog_callback_t")
+                .allowlist_type("cef_dialog_handler_t")
+                .allowlist_type("cef_file_dialog_callback_t")
+                .allowlist_type("cef_jsdialog_handler_t")
+                .allowlist_type("cef_jsdialog_callback_t")
                 .allowlist_function("cef_register_extension")
                 .allowlist_function("cef_process_message_create")
+                .allowlist_function("cef_binary_value_create")
                 .allowlist_type("cef_process_message_t")
                 .allowlist_type("cef_request_context_t")
                 .allowlist_type("cef_browser_process_handler_t")
@@ -108,6 +120,7 @@ fn main() {
                 .allowlist_function("cef_image_create")
                 .allowlist_function("cef_request_context_get_global_context")
                 .allowlist_function("cef_string_utf8_to_utf16")
+                .allowlist_function("cef_string_utf16_clear")
                 .allowlist_function("cef_string_userfree_t")
                 .allowlist_function("cef_string_userfree_alloc")
                 .allowlist_function("cef_string_userfree_wide_alloc")
@@ -123,7 +136,52 @@ fn main() {
                 .allowlist_function("cef_browser_host_create_browser_sync")
                 .allowlist_var("cef_log_severity_t_LOGSEVERITY_INFO")
                 .allowlist_type("cef_print_handler_t")
+                .allowlist_type("cef_print_settings_t")
+                .allowlist_type("cef_print_dialog_callback_t")
+                .allowlist_type("cef_print_job_callback_t")
+                .allowlist_function("cef_print_settings_create")
+                .allowlist_type("cef_load_handler_t")
+                .allowlist_type("cef_render_handler_t")
+                .allowlist_type("cef_task_t")
+                .allowlist_function("cef_post_task")
+                .allowlist_function("cef_post_delayed_task")
                 .allowlist_type("cef_command_line_t")
+                .allowlist_type("cef_display_t")
+                .allowlist_type("cef_keyboard_handler_t")
+                .allowlist_type("cef_key_event_t")
+                .allowlist_type("cef_event_flags_t")
+                .allowlist_type("cef_mouse_event_t")
+                .allowlist_type("cef_mouse_button_type_t")
+                .allowlist_type("cef_touch_event_t")
+                .allowlist_type("cef_touch_event_type_t")
+                .allowlist_type("cef_pointer_type_t")
+                .allowlist_type("cef_range_t")
+                .allowlist_type("cef_composition_underline_t")
+                .allowlist_type("cef_point_t")
+                .allowlist_type("cef_dev_tools_message_observer_t")
+                .allowlist_type("cef_registration_t")
+                .allowlist_type("cef_rect_t")
+                .allowlist_type("cef_end_tracing_callback_t")
+                .allowlist_type("cef_download_handler_t")
+                .allowlist_type("cef_download_item_t")
+                .allowlist_type("cef_before_download_callback_t")
+                .allowlist_type("cef_download_item_callback_t")
+                .allowlist_type("cef_cookie_manager_t")
+                .allowlist_type("cef_cookie_t")
+                .allowlist_type("cef_cookie_visitor_t")
+                .allowlist_type("cef_cookie_same_site_t")
+                .allowlist_type("cef_cookie_priority_t")
+                .allowlist_type("cef_set_cookie_callback_t")
+                .allowlist_type("cef_delete_cookies_callback_t")
+                .allowlist_type("cef_completion_callback_t")
+                .allowlist_function("cef_cookie_manager_get_global_manager")
+                .allowlist_function("cef_time_to_doublet")
+                .allowlist_function("cef_time_from_doublet")
+                .allowlist_function("cef_begin_tracing")
+                .allowlist_function("cef_end_tracing")
+                .allowlist_function("cef_display_get_count")
+                .allowlist_function("cef_display_get_alls")
+                .allowlist_function("cef_display_get_matching_bounds")
                 .generate()
                 .expect("Unable to generate bindings");
             let bindings = bindings.to_string();
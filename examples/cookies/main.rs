@@ -0,0 +1,61 @@
+use cef_simple::{Browser, Cef, Cookie, SameSite, WindowOptions};
+use simplelog::*;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    CombinedLogger::init(vec![TermLogger::new(
+        LevelFilter::Info,
+        Config::default(),
+        TerminalMode::Mixed,
+    )])
+    .unwrap();
+
+    let cef = Cef::initialize(None, false)?;
+
+    // forget any previous session, then pretend a native login flow handed
+    // us a session token
+    let cookies = cef
+        .cookie_manager()
+        .expect("there is a global cookie manager");
+    cookies.delete_cookies(
+        Some("https://httpbin.org/"),
+        None,
+        Some(Box::from(|deleted: Option<usize>| {
+            log::info!("deleted {:?} old cookies", deleted)
+        })),
+    );
+    cookies.set_cookie(
+        "https://httpbin.org/",
+        &Cookie {
+            secure: true,
+            http_only: true,
+            same_site: SameSite::Lax,
+            ..Cookie::new("session", "token-from-native-login")
+        },
+        Some(Box::from(|set: bool| {
+            log::info!("session cookie set: {}", set)
+        })),
+    );
+
+    cef.open_window(WindowOptions {
+        // echoes the cookies it was sent
+        url: "https://httpbin.org/cookies".to_string(),
+        title: Some("CEF Simple—Cookies Demo".to_string()),
+        on_browser_created: Some(Box::from(|browser: Browser| {
+            let cookies = match browser.cookie_manager() {
+                Some(cookies) => cookies,
+                None => return,
+            };
+            cookies.visit_all(Box::from(|cookies: Option<Vec<Cookie>>| {
+                match serde_json::to_string_pretty(&cookies) {
+                    Ok(json) => log::info!("cookies: {}", json),
+                    Err(e) => log::error!("failed to serialize cookies: {}", e),
+                }
+            }));
+        })),
+        ..WindowOptions::default()
+    })?;
+
+    cef.run()?;
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use cef_simple::{
+    Browser, Cef, Download, DownloadDestination, DownloadHandler, PromptForDownloads,
+    SaveToDirectory, WindowOptions,
+};
+use simplelog::*;
+use std::rc::Rc;
+
+/// Logs every download, leaving where it goes to the wrapped handler
+struct LogDownloads<H: DownloadHandler>(H);
+
+impl<H: DownloadHandler> DownloadHandler for LogDownloads<H> {
+    fn destination(&self, browser: &Browser, download: &Download) -> DownloadDestination {
+        let destination = self.0.destination(browser, download);
+        log::info!("downloading `{}`: {:?}", download.url, destination);
+        destination
+    }
+
+    fn on_updated(&self, _browser: &Browser, download: &Download) {
+        log::info!(
+            "{}: {:?}, {} of {:?} bytes",
+            download.suggested_file_name,
+            download.state,
+            download.received_bytes,
+            download.total_bytes
+        );
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cef = Cef::initialize(None, false)?;
+
+    CombinedLogger::init(vec![TermLogger::new(
+        LevelFilter::Info,
+        Config::default(),
+        TerminalMode::Mixed,
+    )])
+    .unwrap();
+
+    let page = urlencoding::encode(include_str!("page.html"));
+
+    // set DOWNLOAD_DIR to save downloads there without asking
+    let download_handler: Rc<dyn DownloadHandler> = match std::env::var_os("DOWNLOAD_DIR") {
+        Some(dir) => Rc::new(LogDownloads(SaveToDirectory::new(dir))),
+        None => Rc::new(LogDownloads(PromptForDownloads)),
+    };
+
+    cef.open_window(WindowOptions {
+        url: format!("data:text/html,{}", page),
+        title: Some("CEF Simple—Downloads Demo".to_string()),
+        download_handler: Some(download_handler),
+        ..WindowOptions::default()
+    })?;
+
+    cef.run()?;
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
-use cef_simple::{Cef, WindowOptions};
+use cef_simple::{Cef, DialogHandler, SandboxedDirectory, WindowOptions};
 use simplelog::*;
+use std::rc::Rc;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cef = Cef::initialize(Some(8000), false)?;
@@ -13,6 +14,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let page = urlencoding::encode(include_str!("page.html"));
 
+    // set SANDBOX_DIR to keep file dialogs inside a single directory rather
+    // than showing the platform's dialog
+    let dialog_handler = std::env::var_os("SANDBOX_DIR")
+        .map(|dir| Rc::new(SandboxedDirectory::new(dir)) as Rc<dyn DialogHandler>);
+
     cef.open_window(WindowOptions {
         url: format!("data:text/html,{}", page),
         title: Some("CEF Simple—File Dialogs Demo".to_string()),
@@ -24,6 +30,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             env!("CARGO_MANIFEST_DIR"),
             "/icon.png"
         ))),
+        dialog_handler,
         ..WindowOptions::default()
     })?;
 
@@ -0,0 +1,45 @@
+use cef_simple::{Cef, Shortcuts, WindowOptions, WindowStateStore};
+use simplelog::*;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cef = Cef::initialize(None, true)?;
+
+    CombinedLogger::init(vec![TermLogger::new(
+        LevelFilter::Trace,
+        Config::default(),
+        TerminalMode::Mixed,
+    )])
+    .unwrap();
+
+    // remember where the window was left between runs
+    cef.set_window_state_store(WindowStateStore::new(
+        std::env::temp_dir().join("cef-simple-frameless.json"),
+    ));
+
+    let mut shortcuts = Shortcuts::new();
+    shortcuts.emit("CmdOrCtrl+W", "close-window")?;
+
+    let page = urlencoding::encode(include_str!("page.html"));
+
+    cef.open_window(WindowOptions {
+        url: format!("data:text/html,{}", page),
+        title: Some("CEF Simple—Frameless Demo".to_string()),
+        frameless: true,
+        min_size: Some((400, 300)),
+        state_key: Some("main".to_string()),
+        shortcuts,
+        window_icon: Some(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/icon.png"
+        ))),
+        window_app_icon: Some(include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/icon.png"
+        ))),
+        ..WindowOptions::default()
+    })?;
+
+    cef.run()?;
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use cef_simple::{Browser, Cef, WindowOptions};
+use serde_json::Value;
+use simplelog::*;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    CombinedLogger::init(vec![TermLogger::new(
+        LevelFilter::Info,
+        Config::default(),
+        TerminalMode::Mixed,
+    )])
+    .unwrap();
+
+    // no debug port: the DevTools protocol is spoken in-process
+    let cef = Cef::initialize(None, false)?;
+
+    cef.open_window(WindowOptions {
+        url: "https://www.rust-lang.org/".to_string(),
+        title: Some("CEF Simple—Network Log Demo".to_string()),
+        on_browser_created: Some(Box::from(|browser: Browser| {
+            let devtools = browser.devtools();
+            devtools.subscribe(
+                "Network.responseReceived",
+                Box::from(|params: &Value| {
+                    let response = &params["response"];
+                    log::info!(
+                        "{} {} ({})",
+                        response["status"],
+                        response["url"].as_str().unwrap_or_default(),
+                        response["mimeType"].as_str().unwrap_or_default()
+                    );
+                }),
+            );
+            devtools.subscribe(
+                "Network.loadingFailed",
+                Box::from(|params: &Value| {
+                    log::warn!(
+                        "request failed: {}",
+                        params["errorText"].as_str().unwrap_or_default()
+                    );
+                }),
+            );
+            devtools.call(
+                "Network.enable",
+                (),
+                Some(Box::from(|result: Result<Value, String>| {
+                    if let Err(e) = result {
+                        log::error!("failed to enable network events: {}", e);
+                    }
+                })),
+            );
+        })),
+        ..WindowOptions::default()
+    })?;
+
+    cef.run()?;
+
+    Ok(())
+}
@@ -1,8 +1,14 @@
-use cef_simple::{Cef, WindowOptions};
+use cef_simple::{Cef, Settings, SwitchPreset, WindowOptions};
 use simplelog::*;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cef = Cef::initialize(Some(8000), false)?;
+    // the touch kiosk preset keeps the on-screen keyboard working even when a
+    // USB keyboard is plugged in
+    let cef = Cef::initialize_with(
+        Settings::new()
+            .with_debug_port(8000)
+            .with_preset(SwitchPreset::TouchKiosk),
+    )?;
 
     CombinedLogger::init(vec![TermLogger::new(
         LevelFilter::Trace,
@@ -10,6 +16,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         TerminalMode::Mixed,
     )])
     .unwrap();
+    log::info!("command line: {:?}", cef.command_line());
 
     let page = urlencoding::encode(include_str!("page.html"));
 
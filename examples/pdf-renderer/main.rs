@@ -0,0 +1,51 @@
+use cef_simple::{Cef, PaperSize, PdfJob, PdfOptions};
+use simplelog::*;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cef = Cef::initialize_headless(None, true)?;
+
+    CombinedLogger::init(vec![TermLogger::new(
+        LevelFilter::Info,
+        Config::default(),
+        TerminalMode::Mixed,
+    )])
+    .unwrap();
+
+    let renderer = cef.pdf_renderer(2)?;
+    let options = PdfOptions {
+        paper_size: PaperSize::Letter,
+        ..PdfOptions::default()
+    };
+
+    for i in 0..4 {
+        let job = PdfJob {
+            options: options.clone(),
+            wait_for_ready_signal: true,
+            ..PdfJob::html(format!(
+                r#"<h1>Report #{}</h1>
+                <p id="content">loading...</p>
+                <script>
+                    setTimeout(() => {{
+                        document.getElementById("content").innerText = "Generated at " + new Date();
+                        cef.signalReady();
+                    }}, 500);
+                </script>"#,
+                i
+            ))
+        };
+        let path = format!("report-{}.pdf", i);
+        renderer.render_to_file(
+            job,
+            path.clone(),
+            Some(Box::from(move |ok| {
+                log::info!("rendered `{}`: {}", path, if ok { "ok" } else { "failed" })
+            })),
+        );
+    }
+
+    renderer.on_idle(Box::from(Cef::quit));
+
+    cef.run()?;
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use cef_simple::{Cef, WindowOptions};
+use cef_simple::{Cef, PrintToFileBackend, WindowOptions};
 use simplelog::*;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -11,6 +11,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     )])
     .unwrap();
 
+    // "print" normally by saving PDFs into the temp directory
+    cef.set_print_backend(Box::new(PrintToFileBackend::new(
+        std::env::temp_dir().join("cef-simple-prints"),
+    )));
+
     let page = urlencoding::encode(include_str!("page.html"));
 
     cef.open_window(WindowOptions {
@@ -0,0 +1,75 @@
+use std::cell::Cell;
+use std::io::Write;
+use std::rc::Rc;
+
+use cef_simple::{Cef, Frame, FrameSink, PaintElement, WindowlessOptions};
+use simplelog::*;
+
+/// Writes the page to `frame.ppm` as it's painted, quitting after a few frames
+struct PpmSink {
+    frames: Cell<usize>,
+}
+
+impl FrameSink for PpmSink {
+    fn view_size(&self) -> (i32, i32) {
+        (640, 360)
+    }
+
+    fn on_frame(&self, frame: &Frame) {
+        if frame.element != PaintElement::View {
+            return;
+        }
+        log::info!(
+            "frame {}: {}×{} with {} dirty rects",
+            self.frames.get(),
+            frame.width,
+            frame.height,
+            frame.dirty_rects.len()
+        );
+
+        // PPM is RGB with no alpha, and the buffer is BGRA
+        let mut ppm = format!("P6\n{} {}\n255\n", frame.width, frame.height).into_bytes();
+        for pixel in frame.buffer.chunks(4) {
+            ppm.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+        }
+        if let Err(e) = std::fs::File::create("frame.ppm").and_then(|mut f| f.write_all(&ppm)) {
+            log::error!("failed to write frame.ppm: {}", e);
+        }
+
+        self.frames.set(self.frames.get() + 1);
+        if self.frames.get() == 30 {
+            Cef::quit();
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cef = Cef::initialize_headless(None, true)?;
+
+    CombinedLogger::init(vec![TermLogger::new(
+        LevelFilter::Info,
+        Config::default(),
+        TerminalMode::Mixed,
+    )])
+    .unwrap();
+
+    let page = urlencoding::encode(
+        r#"<h1 id="clock" style="font-family: sans-serif"></h1>
+        <script>
+            setInterval(() => document.getElementById("clock").innerText = new Date().toISOString(), 50);
+        </script>"#,
+    );
+    cef.open_windowless(WindowlessOptions {
+        frame_rate: 10,
+        ..WindowlessOptions::new(
+            &format!("data:text/html,{}", page),
+            Rc::new(PpmSink {
+                frames: Cell::new(0),
+            }),
+        )
+    })?;
+
+    cef.run()?;
+
+    Ok(())
+}
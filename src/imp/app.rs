@@ -1,12 +1,15 @@
+use std::cell::RefCell;
 use std::mem::size_of;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::bindings::{
-    cef_app_t, cef_base_ref_counted_t, cef_browser_process_handler_t, cef_render_process_handler_t,
-    cef_string_t, cef_command_line_t,
+    cef_app_t, cef_base_ref_counted_t, cef_browser_process_handler_t, cef_command_line_t,
+    cef_render_process_handler_t, cef_string_t,
 };
 use super::browser_process_handler::{self, BrowserProcessHandler};
 use super::render_process_handler::{self, RenderProcessHandler};
+use super::settings::{ProcessType, Switch};
+use super::strings;
 
 #[repr(C)]
 pub struct App {
@@ -14,6 +17,12 @@ pub struct App {
     ref_count: AtomicUsize,
     browser_process_handler: *mut BrowserProcessHandler,
     render_process_handler: *mut RenderProcessHandler,
+    switches: Vec<Switch>,
+}
+
+// the browser process' command line is processed on its main thread
+thread_local! {
+    static COMMAND_LINE: RefCell<Option<String>> = RefCell::new(None);
 }
 
 impl App {
@@ -23,21 +32,49 @@ impl App {
 }
 
 unsafe extern "C" fn on_before_command_line_processing(
-    _slf: *mut cef_app_t,
-    _process_type: *const cef_string_t,
-    command_line: *mut cef_command_line_t
+    slf: *mut cef_app_t,
+    process_type: *const cef_string_t,
+    command_line: *mut cef_command_line_t,
 ) {
-    // append this command line switch to get the on-screen-keyboard working
-    let mut cef_disable_usb_kb_detect = cef_string_t::default();
-    let disable_usb_kb_detect = "disable-usb-keyboard-detect".as_bytes();
-    let disable_usb_kb_detect = std::ffi::CString::new(disable_usb_kb_detect).unwrap();
-    super::bindings::cef_string_utf8_to_utf16(
-        disable_usb_kb_detect.as_ptr(),
-        disable_usb_kb_detect.to_bytes().len() as u64,
-        &mut cef_disable_usb_kb_detect,
+    // the browser process has no process type
+    let process_type = strings::from_cef_string(process_type);
+    let process = ProcessType::from_switch(&process_type);
+
+    let app = slf as *mut App;
+    for switch in (*app).switches.iter().filter(|s| s.applies_to(process)) {
+        let cef_name = strings::to_cef_string(&switch.name);
+        match &switch.value {
+            Some(value) => (*command_line)
+                .append_switch_with_value
+                .expect("append_switch_with_value is a function")(
+                command_line,
+                &cef_name,
+                &strings::to_cef_string(value),
+            ),
+            None => (*command_line)
+                .append_switch
+                .expect("append_switch is a function")(command_line, &cef_name),
+        }
+    }
+
+    let final_command_line = strings::from_userfree((*command_line)
+        .get_command_line_string
+        .expect("get_command_line_string is a function")(
+        command_line
+    ));
+    log::debug!(
+        "command line for the {} process: {}",
+        process.map_or(process_type, |p| format!("{:?}", p)),
+        final_command_line
     );
+    if process == Some(ProcessType::Browser) {
+        COMMAND_LINE.with(|c| *c.borrow_mut() = Some(final_command_line));
+    }
+}
 
-    (*command_line).append_switch.expect("append_switch is a function")(command_line, &cef_disable_usb_kb_detect);
+/// The browser process' command line, once CEF has been initialized
+pub fn command_line() -> Option<String> {
+    COMMAND_LINE.with(|c| c.borrow().clone())
 }
 
 extern "C" fn get_browser_process_handler(
@@ -56,7 +93,7 @@ extern "C" fn get_render_process_handler(slf: *mut cef_app_t) -> *mut cef_render
     handler as *mut cef_render_process_handler_t
 }
 
-pub fn allocate() -> *mut App {
+pub fn allocate(switches: Vec<Switch>) -> *mut App {
     let app = App {
         app: cef_app_t {
             base: cef_base_ref_counted_t {
@@ -75,6 +112,7 @@ pub fn allocate() -> *mut App {
         ref_count: AtomicUsize::new(1),
         browser_process_handler: browser_process_handler::allocate(),
         render_process_handler: render_process_handler::allocate(),
+        switches,
     };
 
     Box::into_raw(Box::from(app))
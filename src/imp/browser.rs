@@ -1,128 +1,416 @@
 use super::bindings::{
-    cef_browser_t, cef_string_list_alloc, cef_string_list_append, cef_string_t,
-    cef_string_utf8_to_utf16,
+    cef_base_ref_counted_t, cef_browser_t, cef_string_list_alloc, cef_string_list_append,
+    cef_string_list_free, cef_string_t, cef_string_utf16_clear, cef_string_utf8_to_utf16,
 };
+use super::cookie_manager::CookieManager;
+use super::devtools::{self, DevToolsOptions};
+use super::devtools_protocol::DevToolsClient;
+use super::file_dialog::{FileDialogOptions, FileDialogResult};
+use super::pdf_options::PdfOptions;
+use super::pending::{self, Pending};
+use super::print_handler;
 use super::print_pdf_callback;
 use super::run_file_dialog_callback;
+use super::screenshot::{self, OnCaptured, ScreenshotOptions};
+use super::strings;
+use std::cell::Cell;
 use std::ffi::CString;
 
+/// A handle to a browser instance, which can be used to control the browser
+/// from Rust
+pub struct Browser {
+    browser: *mut cef_browser_t,
+}
+
+impl Browser {
+    pub(crate) unsafe fn from_raw(browser: *mut cef_browser_t) -> Browser {
+        (*browser).base.add_ref.expect("add_ref is a function")(
+            browser as *mut cef_base_ref_counted_t,
+        );
+        Browser { browser }
+    }
+
+    /// Wrap a browser CEF has already given us a reference to, such as the
+    /// one `cef_browser_host_create_browser_sync` returns, taking that
+    /// reference over instead of adding one
+    pub(crate) unsafe fn from_raw_owned(browser: *mut cef_browser_t) -> Browser {
+        Browser { browser }
+    }
+
+    pub(crate) fn as_raw(&self) -> *mut cef_browser_t {
+        self.browser
+    }
+
+    /// Whether this and `other` are handles to the same browser
+    pub fn is_same(&self, other: &Browser) -> bool {
+        unsafe {
+            (*self.browser).is_same.expect("is_same is a function")(self.browser, other.browser)
+                == 1
+        }
+    }
+
+    /// Close the browser. If `force` is false, the page gets a chance to
+    /// cancel via its `beforeunload` handler.
+    pub fn close(&self, force: bool) {
+        unsafe {
+            let host = (*self.browser).get_host.expect("get_host is a function")(self.browser);
+            (*host).close_browser.expect("close_browser is a function")(host, force as i32);
+        }
+    }
+
+    /// Start closing the browser from a window close handler, running the
+    /// page's `beforeunload` handlers first. Returns true once the browser can
+    /// actually close.
+    pub(crate) fn try_close(&self) -> bool {
+        unsafe {
+            let host = (*self.browser).get_host.expect("get_host is a function")(self.browser);
+            (*host)
+                .try_close_browser
+                .expect("try_close_browser is a function")(host)
+                == 1
+        }
+    }
+
+    /// Tell a windowless browser its view has changed size, so that it asks
+    /// its `FrameSink` for the new size and repaints
+    pub fn was_resized(&self) {
+        unsafe {
+            let host = (*self.browser).get_host.expect("get_host is a function")(self.browser);
+            (*host).was_resized.expect("was_resized is a function")(host);
+        }
+    }
+
+    /// Navigate the main frame to `url`
+    pub fn load_url(&self, url: &str) {
+        unsafe {
+            let frame = (*self.browser)
+                .get_main_frame
+                .expect("get_main_frame is a function")(self.browser);
+            let cef_url = strings::to_cef_string(url);
+            (*frame).load_url.expect("load_url is a function")(frame, &cef_url);
+        }
+    }
+
+    /// Open the browser's DevTools, or bring them to the front if they're
+    /// already open
+    pub fn show_dev_tools(&self, options: &DevToolsOptions) {
+        devtools::show(self, options)
+    }
+
+    pub fn close_dev_tools(&self) {
+        devtools::close(self)
+    }
+
+    pub fn has_dev_tools(&self) -> bool {
+        devtools::has_dev_tools(self)
+    }
+
+    /// The cookie manager of the request context the browser belongs to,
+    /// which is the global one for browsers opened with `Cef`
+    pub fn cookie_manager(&self) -> Option<CookieManager> {
+        unsafe {
+            let host = (*self.browser).get_host.expect("get_host is a function")(self.browser);
+            let context = (*host)
+                .get_request_context
+                .expect("get_request_context is a function")(host);
+            if context.is_null() {
+                return None;
+            }
+            let manager = (*context)
+                .get_cookie_manager
+                .expect("get_cookie_manager is a function")(
+                context, std::ptr::null_mut()
+            );
+            (*context).base.release.expect("release is a function")(
+                context as *mut cef_base_ref_counted_t,
+            );
+            CookieManager::from_raw(manager)
+        }
+    }
+
+    /// A client for the Chrome DevTools protocol, which talks to the browser
+    /// directly rather than through the remote debugging port
+    pub fn devtools(&self) -> DevToolsClient {
+        DevToolsClient::new(self.clone())
+    }
+
+    /// Run `code` in the page's main frame. Nothing is returned; use IPC (or
+    /// the console) to get results back out of the page.
+    pub fn execute_javascript(&self, code: &str) {
+        unsafe {
+            let frame = (*self.browser)
+                .get_main_frame
+                .expect("get_main_frame is a function")(self.browser);
+            let cef_code = strings::to_cef_string(code);
+            let cef_url = strings::to_cef_string("");
+            (*frame)
+                .execute_java_script
+                .expect("execute_java_script is a function")(
+                frame, &cef_code, &cef_url, 0
+            );
+        }
+    }
+
+    /// Print the current page using the print backend set with
+    /// `Cef::set_print_backend`
+    pub fn print(&self) {
+        unsafe {
+            let host = (*self.browser).get_host.expect("get_host is a function")(self.browser);
+            (*host).print.expect("print is a function")(host);
+        }
+    }
+
+    /// Print the current page to a PDF file at `path`. `on_done` is called
+    /// with `true` if the PDF was successfully written.
+    pub fn print_to_pdf<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        options: &PdfOptions,
+        on_done: Option<Box<dyn FnMut(bool)>>,
+    ) {
+        unsafe { print_to_pdf(self.browser, path, options, on_done) }
+    }
+
+    /// Print the current page to PDF and return the PDF's bytes rather than
+    /// writing it to a path of your choosing. `on_done` is called with `None`
+    /// if printing failed.
+    pub fn print_to_pdf_data(
+        &self,
+        options: &PdfOptions,
+        on_done: Option<Box<dyn FnMut(Option<Vec<u8>>)>>,
+    ) {
+        unsafe { print_to_pdf_data(self.browser, options, on_done) }
+    }
+
+    /// Capture a PNG screenshot of the viewport, the full page or a single
+    /// element. `on_done` is called with the PNG's bytes, or with why
+    /// capturing failed.
+    pub fn capture_screenshot(&self, options: &ScreenshotOptions, on_done: Option<OnCaptured>) {
+        screenshot::capture(self, options, on_done)
+    }
+
+    /// Capture a screenshot like `capture_screenshot`, as a `Future`
+    pub fn capture_screenshot_async(&self, options: &ScreenshotOptions) -> Pending<Vec<u8>> {
+        let (pending, resolver) = pending::pending();
+        self.capture_screenshot(
+            options,
+            Some(Box::from(move |result: Result<Vec<u8>, String>| {
+                resolver.resolve(result)
+            })),
+        );
+        pending
+    }
+
+    /// Run a file dialog. `on_done` is called with `None` if the user
+    /// cancelled the dialog.
+    pub fn run_file_dialog(
+        &self,
+        options: &FileDialogOptions,
+        on_done: Option<Box<dyn FnMut(Option<FileDialogResult>)>>,
+    ) {
+        unsafe { run_file_dialog(self.browser, options, on_done) }
+    }
+}
+
+impl Clone for Browser {
+    fn clone(&self) -> Browser {
+        unsafe { Browser::from_raw(self.browser) }
+    }
+}
+
+impl Drop for Browser {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.browser).base.release.expect("release is a function")(
+                self.browser as *mut cef_base_ref_counted_t,
+            );
+        }
+    }
+}
+
+impl std::fmt::Debug for Browser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Browser")
+            .field("browser", &self.browser)
+            .finish()
+    }
+}
+
 pub unsafe fn print_to_pdf<P: AsRef<std::path::Path>>(
     browser: *mut cef_browser_t,
     path: P,
+    options: &PdfOptions,
     on_done: Option<Box<dyn FnMut(bool)>>,
 ) {
-    log::debug!("printing PDF to path `{}`...", path.as_ref().display());
-
-    // get our browser host
-    let host = (*browser).get_host.unwrap()(browser);
+    log::debug!(
+        "printing PDF to path `{}` with options {:?}...",
+        path.as_ref().display(),
+        options
+    );
 
-    // first, convert the path to a cef string
+    // the print handler reports the paper size of the print in progress, so
+    // wait for any other PDF print to finish first
+    let browser = Browser::from_raw(browser);
     let path: String = path.as_ref().display().to_string();
-    let path = path.as_bytes();
-    let path = CString::new(path).unwrap();
-    let mut cef_path = cef_string_t::default();
-    cef_string_utf8_to_utf16(path.as_ptr(), path.to_bytes().len() as u64, &mut cef_path);
-
-    // determine the settings
-    // note: page size in microns, to get microns from inches, multiply
-    // by 25400.
-    // TODO: different paper sizes?
-    let settings = super::bindings::_cef_pdf_print_settings_t {
-        header_footer_title: cef_string_t::default(), // empty header / footer
-        header_footer_url: cef_string_t::default(),   // empty url
-        page_width: 210000,                           // 210 mm (a4 paper)
-        page_height: 297000,                          // 297 mm (a4 paper)
-        scale_factor: 100,                            // scale the page at 100% (i.e. don't.)
-        margin_top: 0.0, // margins in millimeters (actually ignored because of margin type)
-        margin_right: 0.0,
-        margin_bottom: 0.0,
-        margin_left: 0.0,
-        margin_type: super::bindings::cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_DEFAULT, // default margins as defined by chrome, ~1 inch
-        header_footer_enabled: 0, // no headers or footers
-        selection_only: 0,        // print everything
-        landscape: 0,             // portrait mode
-        backgrounds_enabled: 1,   // show background colours / graphics
-    };
+    let options = options.clone();
+    print_handler::queue_pdf_print(
+        options.paper_size.microns(),
+        Box::from(move || start_print_to_pdf(&browser, &path, &options, on_done)),
+    );
+}
 
-    // now a callback when the print is done
-    let callback = print_pdf_callback::allocate(on_done);
+fn start_print_to_pdf(
+    browser: &Browser,
+    path: &str,
+    options: &PdfOptions,
+    mut on_done: Option<Box<dyn FnMut(bool)>>,
+) {
+    unsafe {
+        // get our browser host
+        let host = (*browser.browser).get_host.unwrap()(browser.browser);
 
-    // finally, initiate the print
-    (*host).print_to_pdf.expect("print_to_pdf is a function")(
-        host,
-        &mut cef_path,
-        &settings,
-        callback as *mut super::bindings::_cef_pdf_print_callback_t,
+        // first, convert the path to a cef string
+        let path = CString::new(path.as_bytes()).unwrap();
+        let mut cef_path = cef_string_t::default();
+        cef_string_utf8_to_utf16(path.as_ptr(), path.to_bytes().len() as u64, &mut cef_path);
+
+        // determine the settings
+        // note: page size in microns, to get microns from inches, multiply
+        // by 25400.
+        let mut settings = options.to_cef_settings();
+
+        // now a callback when the print is done, which lets the next PDF
+        // print start
+        let callback = print_pdf_callback::allocate(Some(Box::from(move |ok: bool| {
+            print_handler::pdf_print_finished();
+            if let Some(on_done) = on_done.as_mut() {
+                on_done(ok);
+            }
+        })));
+
+        // finally, initiate the print
+        (*host).print_to_pdf.expect("print_to_pdf is a function")(
+            host,
+            &mut cef_path,
+            &settings,
+            callback as *mut super::bindings::_cef_pdf_print_callback_t,
+        );
+
+        // CEF copies the strings it needs
+        cef_string_utf16_clear(&mut cef_path);
+        cef_string_utf16_clear(&mut settings.header_footer_title);
+        cef_string_utf16_clear(&mut settings.header_footer_url);
+        (*host).base.release.expect("release is a function")(host as *mut cef_base_ref_counted_t);
+    }
+}
+
+/// Reserve a new, empty temporary file to print a PDF into
+fn create_temp_pdf_file() -> std::io::Result<std::path::PathBuf> {
+    thread_local! {
+        static COUNTER: Cell<usize> = Cell::new(0);
+    }
+    loop {
+        let count = COUNTER.with(|c| {
+            let count = c.get();
+            c.set(count + 1);
+            count
+        });
+        let path =
+            std::env::temp_dir().join(format!("cef-simple-{}-{}.pdf", std::process::id(), count));
+        // create_new ensures we never clobber (or follow a symlink to) an existing file
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+pub unsafe fn print_to_pdf_data(
+    browser: *mut cef_browser_t,
+    options: &PdfOptions,
+    mut on_done: Option<Box<dyn FnMut(Option<Vec<u8>>)>>,
+) {
+    let path = match create_temp_pdf_file() {
+        Ok(path) => path,
+        Err(e) => {
+            log::error!("failed to create temporary PDF file: {}", e);
+            if let Some(on_done) = &mut on_done {
+                on_done(None);
+            }
+            return;
+        }
+    };
+
+    let temp_path = path.clone();
+    print_to_pdf(
+        browser,
+        &path,
+        options,
+        Some(Box::from(move |ok: bool| {
+            let data = if ok {
+                match std::fs::read(&temp_path) {
+                    Ok(data) => Some(data),
+                    Err(e) => {
+                        log::error!("failed to read printed PDF: {}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            if let Err(e) = std::fs::remove_file(&temp_path) {
+                log::warn!(
+                    "failed to remove temporary PDF `{}`: {}",
+                    temp_path.display(),
+                    e
+                );
+            }
+            if let Some(on_done) = &mut on_done {
+                on_done(data);
+            }
+        })),
     );
 }
 
 pub unsafe fn run_file_dialog(
     browser: *mut cef_browser_t,
-    mode: super::v8_file_dialog_handler::FileDialogMode,
-    title: String,
-    initial_file_name: String,
-    filter: String,
-    on_done: Option<Box<dyn FnMut(Option<std::path::PathBuf>)>>,
+    options: &FileDialogOptions,
+    on_done: Option<Box<dyn FnMut(Option<FileDialogResult>)>>,
 ) {
-    log::debug!("launching file dialog in mode `{:?}`...", mode);
+    log::debug!("launching file dialog with options {:?}...", options);
 
     // get our browser host
     let host = (*browser).get_host.unwrap()(browser);
 
-    // convert the title to a cef string
-    let title = title.as_bytes();
-    let title = CString::new(title).unwrap();
-    let mut cef_title = cef_string_t::default();
-    cef_string_utf8_to_utf16(
-        title.as_ptr(),
-        title.to_bytes().len() as u64,
-        &mut cef_title,
-    );
-
-    // convert the initial_file_name to a cef string
-    let initial_file_name = initial_file_name.as_bytes();
-    let initial_file_name = CString::new(initial_file_name).unwrap();
-    let mut cef_initial_file_name = cef_string_t::default();
-    cef_string_utf8_to_utf16(
-        initial_file_name.as_ptr(),
-        initial_file_name.to_bytes().len() as u64,
-        &mut cef_initial_file_name,
-    );
-
-    // convert the filter to a cef string
-    let filter = filter.as_bytes();
-    let filter = CString::new(filter).unwrap();
-    let mut cef_filter = cef_string_t::default();
-    cef_string_utf8_to_utf16(
-        filter.as_ptr(),
-        filter.to_bytes().len() as u64,
-        &mut cef_filter,
-    );
+    let cef_title = strings::to_cef_string(&options.title);
+    let cef_default_file_name = strings::to_cef_string(&options.default_file_name);
 
     // build the filter list
     let filters = cef_string_list_alloc();
-    cef_string_list_append(filters, &cef_filter);
+    for filter in options.filters.iter() {
+        let cef_filter = strings::to_cef_string(&filter.to_cef_filter());
+        cef_string_list_append(filters, &cef_filter);
+    }
 
     // and a callback
-    let callback = run_file_dialog_callback::allocate(on_done);
+    let callback = run_file_dialog_callback::allocate(options.filters.clone(), on_done);
 
     // and run the dialog
     (*host)
         .run_file_dialog
         .expect("run_file_dialog is a function")(
         host,
-        match mode {
-            super::v8_file_dialog_handler::FileDialogMode::Open => {
-                super::bindings::cef_file_dialog_mode_t_FILE_DIALOG_OPEN
-            }
-            super::v8_file_dialog_handler::FileDialogMode::Save => {
-                super::bindings::cef_file_dialog_mode_t_FILE_DIALOG_SAVE
-            }
-        },
+        options.cef_mode(),
         &cef_title,
-        &cef_initial_file_name,
+        &cef_default_file_name,
         filters,
-        0,
+        options.selected_filter as i32,
         callback as *mut super::bindings::_cef_run_file_dialog_callback_t,
     );
+    cef_string_list_free(filters);
 }
@@ -2,13 +2,21 @@ use std::mem::size_of;
 use std::os::raw::c_int;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use super::bindings::{cef_base_ref_counted_t, cef_browser_view_delegate_t, cef_view_delegate_t};
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_settings_t, cef_browser_t, cef_browser_view_delegate_t,
+    cef_browser_view_t, cef_client_t, cef_view_delegate_t, cef_view_t, cef_window_create_top_level,
+    cef_window_delegate_t,
+};
+use super::browser::Browser;
+use super::windows::{self, WindowId};
+use super::{client, window_delegate};
 
-#[derive(Debug)]
 #[repr(C)]
 pub struct BrowserViewDelegate {
     browser_view_delegate: cef_browser_view_delegate_t,
     ref_count: AtomicUsize,
+    window_id: WindowId,
+    on_browser_created: Option<Box<dyn FnMut(Browser)>>,
 }
 
 impl BrowserViewDelegate {
@@ -17,7 +25,77 @@ impl BrowserViewDelegate {
     }
 }
 
-pub fn allocate() -> *mut BrowserViewDelegate {
+unsafe extern "C" fn on_browser_created(
+    slf: *mut cef_browser_view_delegate_t,
+    _browser_view: *mut cef_browser_view_t,
+    browser: *mut cef_browser_t,
+) {
+    let delegate = slf as *mut BrowserViewDelegate;
+    windows::set_browser((*delegate).window_id, Browser::from_raw(browser));
+    if let Some(on_browser_created) = &mut (*delegate).on_browser_created {
+        on_browser_created(Browser::from_raw(browser));
+    }
+}
+
+unsafe extern "C" fn get_delegate_for_popup_browser_view(
+    _slf: *mut cef_browser_view_delegate_t,
+    _browser_view: *mut cef_browser_view_t,
+    _settings: *const cef_browser_settings_t,
+    client: *mut cef_client_t,
+    _is_devtools: c_int,
+) -> *mut cef_browser_view_delegate_t {
+    // the client was swapped for one of ours in `on_before_popup`, or given to
+    // CEF along with the options for a DevTools window in `devtools::show`
+    let window_id = match client::window_id(client) {
+        Some(window_id) => window_id,
+        None => return std::ptr::null_mut(),
+    };
+
+    let mut options = match windows::take_pending_popup(window_id) {
+        Some(options) => options,
+        None => return std::ptr::null_mut(),
+    };
+    let on_browser_created = options.on_browser_created.take();
+    windows::set_pending_popup(window_id, options);
+
+    let delegate = allocate(window_id, on_browser_created);
+    (*delegate).inc_ref();
+    delegate as *mut cef_browser_view_delegate_t
+}
+
+unsafe extern "C" fn on_popup_browser_view_created(
+    _slf: *mut cef_browser_view_delegate_t,
+    _browser_view: *mut cef_browser_view_t,
+    popup_browser_view: *mut cef_browser_view_t,
+    _is_devtools: c_int,
+) -> c_int {
+    // find the popup's window through the delegate we gave it
+    let view = popup_browser_view as *mut cef_view_t;
+    let delegate = (*view).get_delegate.expect("get_delegate is a function")(view);
+    if delegate.is_null() {
+        return 0;
+    }
+    let window_id = (*(delegate as *mut BrowserViewDelegate)).window_id;
+    (*delegate).base.release.expect("release is a function")(
+        delegate as *mut cef_base_ref_counted_t,
+    );
+
+    let options = match windows::take_pending_popup(window_id) {
+        Some(options) => options,
+        None => return 0,
+    };
+    log::debug!("opening popup to `{}` in {}", options.url, window_id);
+    let window_delegate = window_delegate::allocate(options, window_id);
+    (*window_delegate).popup_browser_view = Some(popup_browser_view);
+    (*window_delegate).inc_ref();
+    cef_window_create_top_level(window_delegate as *mut cef_window_delegate_t);
+    1
+}
+
+pub fn allocate(
+    window_id: WindowId,
+    on_browser_created: Option<Box<dyn FnMut(Browser)>>,
+) -> *mut BrowserViewDelegate {
     let browser_view = BrowserViewDelegate {
         browser_view_delegate: cef_browser_view_delegate_t {
             base: cef_view_delegate_t {
@@ -37,12 +115,14 @@ pub fn allocate() -> *mut BrowserViewDelegate {
                 on_focus: None,
                 on_blur: None,
             },
-            on_browser_created: None,
+            on_browser_created: Some(on_browser_created),
             on_browser_destroyed: None,
-            get_delegate_for_popup_browser_view: None,
-            on_popup_browser_view_created: None,
+            get_delegate_for_popup_browser_view: Some(get_delegate_for_popup_browser_view),
+            on_popup_browser_view_created: Some(on_popup_browser_view_created),
         },
         ref_count: AtomicUsize::new(1),
+        window_id,
+        on_browser_created,
     };
 
     Box::into_raw(Box::from(browser_view))
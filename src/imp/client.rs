@@ -1,26 +1,92 @@
 use std::mem::size_of;
 use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::bindings::{
     cef_base_ref_counted_t, cef_browser_t, cef_client_t, cef_context_menu_handler_t,
-    cef_display_handler_t, cef_frame_t, cef_life_span_handler_t, cef_process_id_t,
-    cef_process_message_t, cef_request_handler_t, cef_string_t, cef_string_userfree_t,
-    cef_string_userfree_utf16_free, cef_window_t,
+    cef_dialog_handler_t, cef_display_handler_t, cef_download_handler_t, cef_drag_handler_t,
+    cef_frame_t, cef_jsdialog_handler_t, cef_keyboard_handler_t, cef_life_span_handler_t,
+    cef_load_handler_t, cef_process_id_t, cef_process_message_t, cef_render_handler_t,
+    cef_request_handler_t, cef_string_t, cef_string_userfree_t, cef_string_userfree_utf16_free,
+    cef_window_t,
 };
+use super::browser::Browser;
 use super::context_menu_handler::{self, ContextMenuHandler};
-use super::display_handler::{self, DisplayHandler};
+use super::dialog_handler::{self, CefDialogHandler, DialogHandler};
+use super::display_handler::{self, DisplayHandler, OnConsoleMessage};
+use super::download_handler::{self, CefDownloadHandler, DownloadHandler};
+use super::drag_handler::{self, DragHandler};
+use super::file_dialog::{FileDialogOptions, FileDialogResult};
+use super::js_dialog_handler::{self, CefJsDialogHandler, JsDialogHandler};
+use super::keyboard_handler::{self, KeyboardHandler};
 use super::life_span_handler::{self, LifeSpanHandler};
+use super::load_handler::{self, LoadHandler, OnLoaded};
+use super::offscreen::FrameSink;
+use super::pdf_options::PdfOptions;
+use super::print_handler;
+use super::print_settings::PrintSettings;
+use super::render_handler::{self, RenderHandler};
 use super::request_handler::{self, RequestHandler};
+use super::shortcuts::Shortcuts;
+use super::strings;
+use super::tracing;
+use super::window_delegate::WindowOptions;
+use super::windows::{self, WindowId};
 
 #[repr(C)]
 pub struct Client {
     client: cef_client_t,
     ref_count: AtomicUsize,
+    window_id: Option<WindowId>,
     life_span_handler: *mut LifeSpanHandler,
     context_menu_handler: *mut ContextMenuHandler,
     request_handler: *mut RequestHandler,
     display_handler: *mut DisplayHandler,
+    load_handler: *mut LoadHandler,
+    render_handler: Option<*mut RenderHandler>,
+    dialog_handler: Option<*mut CefDialogHandler>,
+    js_dialog_handler: Option<*mut CefJsDialogHandler>,
+    download_handler: Option<*mut CefDownloadHandler>,
+    drag_handler: Option<*mut DragHandler>,
+    keyboard_handler: *mut KeyboardHandler,
+    on_page_ready: Option<Box<dyn FnMut(Browser)>>,
+    pdf_directory: Option<PathBuf>,
+    on_dialog_answer: Option<Box<dyn FnOnce(bool, String)>>,
+}
+
+/// User-supplied handlers for a browser
+#[derive(Default)]
+pub struct ClientHooks {
+    pub dialog_handler: Option<Rc<dyn DialogHandler>>,
+    pub js_dialog_handler: Option<Rc<dyn JsDialogHandler>>,
+    pub download_handler: Option<Rc<dyn DownloadHandler>>,
+    /// The options popups opened by the browser inherit; popups are denied if
+    /// this isn't set
+    pub popup_options: Option<WindowOptions>,
+    /// The browser's own shortcuts, on top of the app-wide ones
+    pub shortcuts: Shortcuts,
+    /// The URLs the main frame may navigate to, if it's restricted, matched by
+    /// scheme, host, port and path segments as `kiosk::is_allowed` does
+    pub navigation_allowlist: Option<Vec<String>>,
+    /// Keep the window fullscreen even when the page leaves fullscreen
+    pub lock_fullscreen: bool,
+    /// Where a windowless browser paints
+    pub frame_sink: Option<Rc<dyn FrameSink>>,
+    /// Also pass console messages here, on top of logging them
+    pub on_console_message: Option<OnConsoleMessage>,
+    /// Called once the browser has closed
+    pub on_closed: Option<Box<dyn FnOnce()>>,
+    /// Offer "Inspect Element" in the context menu, if it's enabled with
+    /// `Cef::set_dev_tools`
+    pub dev_tools_menu: bool,
+    /// Write PDFs printed with `cef.printToPDF` into this directory instead,
+    /// keeping only the file name the page asked for
+    pub pdf_directory: Option<PathBuf>,
+    /// Called when the page answers a dialog with `cef._answerDialog`; only
+    /// set for the overlays `InPageModal` shows dialogs in
+    pub on_dialog_answer: Option<Box<dyn FnOnce(bool, String)>>,
 }
 
 impl Client {
@@ -29,6 +95,18 @@ impl Client {
     }
 }
 
+/// The window a client belongs to, if it's one of ours and was made for a
+/// window
+pub unsafe fn window_id(client: *mut cef_client_t) -> Option<WindowId> {
+    // only our clients hand out our life span handler, so this tells them
+    // apart from ones made by CEF or anyone else before casting
+    let ours = get_life_span_handler as usize;
+    if client.is_null() || (*client).get_life_span_handler.map(|f| f as usize) != Some(ours) {
+        return None;
+    }
+    (*(client as *mut Client)).window_id
+}
+
 extern "C" fn get_life_span_handler(slf: *mut cef_client_t) -> *mut cef_life_span_handler_t {
     let client = slf as *mut Client;
     let handler = unsafe { (*client).life_span_handler };
@@ -57,8 +135,77 @@ extern "C" fn get_display_handler(slf: *mut cef_client_t) -> *mut cef_display_ha
     handler as *mut cef_display_handler_t
 }
 
+extern "C" fn get_load_handler(slf: *mut cef_client_t) -> *mut cef_load_handler_t {
+    let client = slf as *mut Client;
+    let handler = unsafe { (*client).load_handler };
+    unsafe { (*handler).inc_ref() };
+    handler as *mut cef_load_handler_t
+}
+
+extern "C" fn get_keyboard_handler(slf: *mut cef_client_t) -> *mut cef_keyboard_handler_t {
+    let client = slf as *mut Client;
+    let handler = unsafe { (*client).keyboard_handler };
+    unsafe { (*handler).inc_ref() };
+    handler as *mut cef_keyboard_handler_t
+}
+
+extern "C" fn get_render_handler(slf: *mut cef_client_t) -> *mut cef_render_handler_t {
+    let client = slf as *mut Client;
+    match unsafe { (*client).render_handler } {
+        Some(handler) => {
+            unsafe { (*handler).inc_ref() };
+            handler as *mut cef_render_handler_t
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
+extern "C" fn get_dialog_handler(slf: *mut cef_client_t) -> *mut cef_dialog_handler_t {
+    let client = slf as *mut Client;
+    match unsafe { (*client).dialog_handler } {
+        Some(handler) => {
+            unsafe { (*handler).inc_ref() };
+            handler as *mut cef_dialog_handler_t
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
+extern "C" fn get_download_handler(slf: *mut cef_client_t) -> *mut cef_download_handler_t {
+    let client = slf as *mut Client;
+    match unsafe { (*client).download_handler } {
+        Some(handler) => {
+            unsafe { (*handler).inc_ref() };
+            handler as *mut cef_download_handler_t
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
+extern "C" fn get_drag_handler(slf: *mut cef_client_t) -> *mut cef_drag_handler_t {
+    let client = slf as *mut Client;
+    match unsafe { (*client).drag_handler } {
+        Some(handler) => {
+            unsafe { (*handler).inc_ref() };
+            handler as *mut cef_drag_handler_t
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
+extern "C" fn get_jsdialog_handler(slf: *mut cef_client_t) -> *mut cef_jsdialog_handler_t {
+    let client = slf as *mut Client;
+    match unsafe { (*client).js_dialog_handler } {
+        Some(handler) => {
+            unsafe { (*handler).inc_ref() };
+            handler as *mut cef_jsdialog_handler_t
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
 unsafe extern "C" fn on_process_message_received(
-    _slf: *mut cef_client_t,
+    slf: *mut cef_client_t,
     browser: *mut cef_browser_t,
     frame: *mut cef_frame_t,
     _source_process: cef_process_id_t,
@@ -75,7 +222,15 @@ unsafe extern "C" fn on_process_message_received(
     cef_string_userfree_utf16_free(cef_message_name);
 
     log::debug!("browser process recieved `{}` message", message_name);
-    if message_name == "print_to_pdf" {
+    if message_name == "page_ready" {
+        let client = slf as *mut Client;
+        if let Some(on_page_ready) = &mut (*client).on_page_ready {
+            on_page_ready(Browser::from_raw(browser));
+        } else {
+            log::debug!("page signalled it is ready, but nobody is listening");
+        }
+        1
+    } else if message_name == "print_to_pdf" {
         log::debug!("print to pdf message!");
         // get the path
         let args = ((*message)
@@ -86,14 +241,27 @@ unsafe extern "C" fn on_process_message_received(
         let chars: *mut u16 = (*cef_path).str_;
         let len: usize = (*cef_path).length as usize;
         let chars = std::slice::from_raw_parts(chars, len);
-        let path = std::char::decode_utf16(chars.iter().cloned())
-            .map(|r| r.unwrap_or(std::char::REPLACEMENT_CHARACTER))
-            .collect::<String>();
+        let mut path = PathBuf::from(
+            std::char::decode_utf16(chars.iter().cloned())
+                .map(|r| r.unwrap_or(std::char::REPLACEMENT_CHARACTER))
+                .collect::<String>(),
+        );
         cef_string_userfree_utf16_free(cef_path);
+        let client = slf as *mut Client;
+        if let Some(directory) = &(*client).pdf_directory {
+            let file_name = path
+                .file_name()
+                .map(Path::new)
+                .unwrap_or_else(|| Path::new("output.pdf"));
+            path = directory.join(file_name);
+            log::debug!("redirecting PDF to `{}`", path.display());
+        }
+        let options = PdfOptions::read_from_list(args, 1);
 
         super::browser::print_to_pdf(
             browser,
             path,
+            &options,
             Some(Box::from(move |ok| {
                 // now send an IPC message back to the renderer
                 // convert the message name to a CEF string
@@ -126,122 +294,100 @@ unsafe extern "C" fn on_process_message_received(
         );
 
         1
-    } else if message_name == "save_file_dialog" || message_name == "open_file_dialog" {
+    } else if message_name == "print_to_pdf_data" {
         let args = ((*message)
             .get_argument_list
             .expect("get_argument_list is a function"))(message);
+        let request_id = ((*args).get_int.expect("get_int is a function"))(args, 0);
+        let options = PdfOptions::read_from_list(args, 1);
 
-        let num_args = (*args).get_size.expect("get_size is a function")(args);
-        debug_assert_eq!(num_args, 3);
+        super::browser::print_to_pdf_data(
+            browser,
+            &options,
+            Some(Box::from(move |data: Option<Vec<u8>>| {
+                // send the PDF bytes back to the renderer
+                let cef_message_name = strings::to_cef_string("print_to_pdf_data_done");
+                let message = super::bindings::cef_process_message_create(&cef_message_name);
+                let args = ((*message)
+                    .get_argument_list
+                    .expect("get_argument_list is a function"))(message);
+                if let Some(data) = data {
+                    ((*args).set_size.expect("set_size is a function"))(args, 3);
+                    ((*args).set_int.expect("set_int is a function"))(args, 0, request_id);
+                    ((*args).set_bool.expect("set_bool is a function"))(args, 1, 1);
+                    let binary = super::bindings::cef_binary_value_create(
+                        data.as_ptr() as *const _,
+                        data.len() as u64,
+                    );
+                    ((*args).set_binary.expect("set_binary is a function"))(args, 2, binary);
+                } else {
+                    ((*args).set_size.expect("set_size is a function"))(args, 2);
+                    ((*args).set_int.expect("set_int is a function"))(args, 0, request_id);
+                    ((*args).set_bool.expect("set_bool is a function"))(args, 1, 0);
+                }
 
-        // get the title
-        let cef_title: cef_string_userfree_t =
-            ((*args).get_string.expect("get_string is a function"))(args, 0);
-        let title: String = if cef_title == std::ptr::null_mut() {
-            let chars: *mut u16 = (*cef_title).str_;
-            let len: usize = (*cef_title).length as usize;
-            let chars = std::slice::from_raw_parts(chars, len);
-            let title = std::char::decode_utf16(chars.iter().cloned())
-                .map(|r| r.unwrap_or(std::char::REPLACEMENT_CHARACTER))
-                .collect::<String>();
-            cef_string_userfree_utf16_free(cef_title);
-            title
-        } else {
-            "".to_owned()
-        };
+                ((*frame)
+                    .send_process_message
+                    .expect("send_process_message is a function"))(
+                    frame,
+                    super::bindings::cef_process_id_t_PID_RENDERER,
+                    message,
+                );
+            })),
+        );
 
-        // get the initial_file_name
-        let cef_initial_file_name: cef_string_userfree_t =
-            ((*args).get_string.expect("get_string is a function"))(args, 1);
-        let initial_file_name: String = if cef_initial_file_name == std::ptr::null_mut() {
-            "".to_owned()
-        } else {
-            let chars: *mut u16 = (*cef_initial_file_name).str_;
-            let len: usize = (*cef_initial_file_name).length as usize;
-            let chars = std::slice::from_raw_parts(chars, len);
-            let initial_file_name = std::char::decode_utf16(chars.iter().cloned())
-                .map(|r| r.unwrap_or(std::char::REPLACEMENT_CHARACTER))
-                .collect::<String>();
-            cef_string_userfree_utf16_free(cef_initial_file_name);
-            initial_file_name
-        };
+        1
+    } else if message_name == "print" {
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        print_handler::set_pending_settings(PrintSettings::read_from_list(args, 0));
 
-        // get the filter
-        let cef_filter: cef_string_userfree_t =
-            ((*args).get_string.expect("get_string is a function"))(args, 2);
-        let filter: String = if cef_filter != std::ptr::null_mut() {
-            let chars: *mut u16 = (*cef_filter).str_;
-            let len: usize = (*cef_filter).length as usize;
-            let chars = std::slice::from_raw_parts(chars, len);
-            let filter = std::char::decode_utf16(chars.iter().cloned())
-                .map(|r| r.unwrap_or(std::char::REPLACEMENT_CHARACTER))
-                .collect::<String>();
-            cef_string_userfree_utf16_free(cef_filter);
-            filter
-        } else {
-            "".to_owned()
-        };
-        log::debug!(
-            "{} with title: “{}”; initial_file_name “{}”; filter: “{}”",
-            message_name,
-            title,
-            initial_file_name,
-            filter
-        );
+        let host = (*browser).get_host.expect("get_host is a function")(browser);
+        ((*host).print.expect("print is a function"))(host);
+        1
+    } else if message_name == "run_file_dialog" {
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        let options = FileDialogOptions::read_from_list(args, 0);
 
-        log::debug!(
-            "title: {}, initial file name: {}, filter: {}",
-            title,
-            initial_file_name,
-            filter
-        );
         super::browser::run_file_dialog(
             browser,
-            match message_name.as_ref() {
-                "open_file_dialog" => super::v8_file_dialog_handler::FileDialogMode::Open,
-                "save_file_dialog" => super::v8_file_dialog_handler::FileDialogMode::Save,
-                _ => unreachable!(),
-            },
-            title,
-            initial_file_name,
-            filter,
-            Some(Box::from(move |path: Option<std::path::PathBuf>| {
-                log::debug!("client save callback, path: {:?}", path);
+            &options,
+            Some(Box::from(move |result: Option<FileDialogResult>| {
+                log::debug!("file dialog result: {:?}", result);
                 // now send an IPC message back to the renderer
-                // convert the message name to a CEF string
-                let mut cef_message_name = cef_string_t::default();
-                let message_name = "run_file_dialog_done".as_bytes();
-                let message_name = std::ffi::CString::new(message_name).unwrap();
-                super::bindings::cef_string_utf8_to_utf16(
-                    message_name.as_ptr(),
-                    message_name.to_bytes().len() as u64,
-                    &mut cef_message_name,
-                );
-
-                // build the message
+                let cef_message_name = strings::to_cef_string("run_file_dialog_done");
                 let message = super::bindings::cef_process_message_create(&cef_message_name);
                 let args = ((*message)
                     .get_argument_list
                     .expect("get_argument_list is a function"))(message);
-                if let Some(path) = path {
-                    ((*args).set_size.expect("set_size is a function"))(args, 1);
-
-                    let mut cef_path = cef_string_t::default();
-                    let path = path.display().to_string();
-                    let path = path.as_bytes();
-                    let path = std::ffi::CString::new(path).unwrap();
-                    super::bindings::cef_string_utf8_to_utf16(
-                        path.as_ptr(),
-                        path.to_bytes().len() as u64,
-                        &mut cef_path,
+                if let Some(result) = result {
+                    // [filter index, paths...]
+                    ((*args).set_size.expect("set_size is a function"))(
+                        args,
+                        1 + result.paths.len() as u64,
                     );
-                    ((*args).set_string.expect("set_string is a function"))(args, 0, &cef_path);
+                    ((*args).set_int.expect("set_int is a function"))(
+                        args,
+                        0,
+                        result.filter_index as i32,
+                    );
+                    for (i, path) in result.paths.iter().enumerate() {
+                        let cef_path = strings::to_cef_string(&path.display().to_string());
+                        ((*args).set_string.expect("set_string is a function"))(
+                            args,
+                            1 + i as u64,
+                            &cef_path,
+                        );
+                    }
                 } else {
                     ((*args).set_size.expect("set_size is a function"))(args, 0);
                 }
 
                 // and finally send the message
-                log::debug!("returning path to JS...");
+                log::debug!("returning paths to JS...");
                 ((*frame)
                     .send_process_message
                     .expect("send_process_message is a function"))(
@@ -252,6 +398,93 @@ unsafe extern "C" fn on_process_message_received(
             })),
         );
 
+        1
+    } else if message_name == "close_requested_listener" || message_name == "close_requested_done" {
+        let client = slf as *mut Client;
+        let window_id = match (*client).window_id {
+            Some(window_id) => window_id,
+            None => {
+                log::debug!("ignoring `{}` for a browser without a window", message_name);
+                return 1;
+            }
+        };
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        let value = ((*args).get_bool.expect("get_bool is a function"))(args, 0) == 1;
+
+        if message_name == "close_requested_listener" {
+            windows::set_page_handles_close(window_id, value);
+        } else {
+            windows::page_answered_close(window_id, value);
+        }
+        1
+    } else if message_name == "window_command" {
+        let client = slf as *mut Client;
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        let command =
+            strings::from_userfree(((*args).get_string.expect("get_string is a function"))(
+                args, 0,
+            ));
+        match (*client).window_id {
+            Some(window_id) => windows::window_command(window_id, &command),
+            None => log::debug!("ignoring `{}` for a browser without a window", command),
+        }
+        1
+    } else if message_name == "trace_command" && cfg!(debug_assertions) {
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        let get_string = |i| {
+            strings::from_userfree(((*args).get_string.expect("get_string is a function"))(
+                args, i,
+            ))
+        };
+        let (command, argument) = (get_string(0), get_string(1));
+        if command == "start" {
+            let categories: Vec<&str> = argument.split(',').filter(|c| !c.is_empty()).collect();
+            if let Err(e) = tracing::begin(&categories) {
+                log::warn!("`cef.trace.start()` failed: {}", e);
+            }
+        } else if command == "stop" {
+            let path = Some(PathBuf::from(argument)).filter(|p| !p.as_os_str().is_empty());
+            tracing::end(path, None);
+        }
+        1
+    } else if message_name == "download_command" {
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        let get_string = |i| {
+            strings::from_userfree(((*args).get_string.expect("get_string is a function"))(
+                args, i,
+            ))
+        };
+        let (command, id) = (get_string(0), get_string(1));
+        match id.parse::<u32>() {
+            Ok(id) => download_handler::page_command(&Browser::from_raw(browser), &command, id),
+            Err(_) => log::warn!("invalid download id `{}`", id),
+        }
+        1
+    } else if message_name == "js_dialog_answer" {
+        let client = slf as *mut Client;
+        let on_dialog_answer = match (*client).on_dialog_answer.take() {
+            Some(on_dialog_answer) => on_dialog_answer,
+            None => {
+                log::warn!("ignoring a dialog answer from a page that isn't showing a dialog");
+                return 1;
+            }
+        };
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        let accept = ((*args).get_bool.expect("get_bool is a function"))(args, 0) == 1;
+        let input = strings::from_userfree(
+            ((*args).get_string.expect("get_string is a function"))(args, 1),
+        );
+        on_dialog_answer(accept, input);
         1
     } else {
         log::debug!("unhandled IPC message: {}", message_name);
@@ -259,7 +492,47 @@ unsafe extern "C" fn on_process_message_received(
     }
 }
 
-pub fn allocate(window: *mut cef_window_t) -> *mut Client {
+pub fn allocate(window: *mut cef_window_t, window_id: WindowId, hooks: ClientHooks) -> *mut Client {
+    allocate_client(window, Some(window_id), None, None, None, hooks)
+}
+
+/// Allocate a client for a windowless (off-screen) browser which renders into
+/// a `width` × `height` view
+pub fn allocate_windowless(
+    width: i32,
+    height: i32,
+    on_loaded: Option<OnLoaded>,
+    on_page_ready: Option<Box<dyn FnMut(Browser)>>,
+    hooks: ClientHooks,
+) -> *mut Client {
+    allocate_client(
+        std::ptr::null_mut(),
+        None,
+        Some(render_handler::allocate(
+            width,
+            height,
+            hooks.frame_sink.clone(),
+        )),
+        on_loaded,
+        on_page_ready,
+        hooks,
+    )
+}
+
+/// Allocate a client for a browser shown as an overlay inside one of our
+/// windows, which doesn't own the window
+pub fn allocate_overlay(hooks: ClientHooks) -> *mut Client {
+    allocate_client(std::ptr::null_mut(), None, None, None, None, hooks)
+}
+
+fn allocate_client(
+    window: *mut cef_window_t,
+    window_id: Option<WindowId>,
+    render_handler: Option<*mut RenderHandler>,
+    on_loaded: Option<OnLoaded>,
+    on_page_ready: Option<Box<dyn FnMut(Browser)>>,
+    hooks: ClientHooks,
+) -> *mut Client {
     let client = Client {
         client: cef_client_t {
             base: cef_base_ref_counted_t {
@@ -270,25 +543,44 @@ pub fn allocate(window: *mut cef_window_t) -> *mut Client {
                 has_at_least_one_ref: Some(has_at_least_one_ref),
             },
             get_context_menu_handler: Some(get_context_menu_handler),
-            get_dialog_handler: None,
+            get_dialog_handler: Some(get_dialog_handler),
             get_display_handler: Some(get_display_handler),
-            get_download_handler: None,
-            get_drag_handler: None,
+            get_download_handler: Some(get_download_handler),
+            get_drag_handler: Some(get_drag_handler),
             get_find_handler: None,
             get_focus_handler: None,
-            get_jsdialog_handler: None,
-            get_keyboard_handler: None,
+            get_jsdialog_handler: Some(get_jsdialog_handler),
+            get_keyboard_handler: Some(get_keyboard_handler),
             get_life_span_handler: Some(get_life_span_handler),
-            get_load_handler: None,
-            get_render_handler: None,
+            get_load_handler: Some(get_load_handler),
+            get_render_handler: Some(get_render_handler),
             get_request_handler: Some(get_request_handler),
             on_process_message_received: Some(on_process_message_received),
         },
         ref_count: AtomicUsize::new(1),
-        life_span_handler: life_span_handler::allocate(),
-        context_menu_handler: context_menu_handler::allocate(),
-        request_handler: request_handler::allocate(),
-        display_handler: display_handler::allocate(window),
+        window_id,
+        life_span_handler: life_span_handler::allocate(
+            window_id,
+            hooks.popup_options,
+            hooks.on_closed,
+        ),
+        context_menu_handler: context_menu_handler::allocate(hooks.dev_tools_menu),
+        request_handler: request_handler::allocate(hooks.navigation_allowlist),
+        display_handler: display_handler::allocate(
+            window,
+            hooks.lock_fullscreen,
+            hooks.on_console_message,
+        ),
+        load_handler: load_handler::allocate(on_loaded),
+        render_handler,
+        dialog_handler: hooks.dialog_handler.map(dialog_handler::allocate),
+        js_dialog_handler: hooks.js_dialog_handler.map(js_dialog_handler::allocate),
+        download_handler: hooks.download_handler.map(download_handler::allocate),
+        drag_handler: window_id.map(drag_handler::allocate),
+        keyboard_handler: keyboard_handler::allocate(hooks.shortcuts),
+        on_page_ready,
+        pdf_directory: hooks.pdf_directory,
+        on_dialog_answer: hooks.on_dialog_answer,
     };
 
     Box::into_raw(Box::from(client))
@@ -4,13 +4,19 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::bindings::{
     cef_base_ref_counted_t, cef_browser_t, cef_context_menu_handler_t, cef_context_menu_params_t,
-    cef_frame_t, cef_menu_model_t,
+    cef_event_flags_t, cef_frame_t, cef_menu_id_t_MENU_ID_USER_FIRST, cef_menu_model_t,
 };
+use super::browser::Browser;
+use super::{devtools, strings};
+
+const INSPECT_ELEMENT: c_int = cef_menu_id_t_MENU_ID_USER_FIRST as c_int;
 
 #[repr(C)]
 pub struct ContextMenuHandler {
     context_menu_handler: cef_context_menu_handler_t,
     ref_count: AtomicUsize,
+    /// Whether this browser may offer "Inspect Element"
+    dev_tools_menu: bool,
 }
 
 impl ContextMenuHandler {
@@ -20,7 +26,7 @@ impl ContextMenuHandler {
 }
 
 extern "C" fn on_before_context_menu(
-    _slf: *mut cef_context_menu_handler_t,
+    slf: *mut cef_context_menu_handler_t,
     _browser: *mut cef_browser_t,
     _frame: *mut cef_frame_t,
     _params: *mut cef_context_menu_params_t,
@@ -29,10 +35,33 @@ extern "C" fn on_before_context_menu(
     unsafe {
         (*model).clear.unwrap()(model);
         //(*model).remove.unwrap()(model, super::bindings::cef_menu_id_t_MENU_ID_VIEW_SOURCE as i32);
+
+        let handler = slf as *mut ContextMenuHandler;
+        if (*handler).dev_tools_menu && devtools::context_menu_enabled() {
+            let label = strings::to_cef_string("Inspect Element");
+            (*model).add_item.unwrap()(model, INSPECT_ELEMENT, &label);
+        }
+    }
+}
+
+unsafe extern "C" fn on_context_menu_command(
+    _slf: *mut cef_context_menu_handler_t,
+    browser: *mut cef_browser_t,
+    _frame: *mut cef_frame_t,
+    params: *mut cef_context_menu_params_t,
+    command_id: c_int,
+    _event_flags: cef_event_flags_t,
+) -> c_int {
+    if command_id != INSPECT_ELEMENT {
+        return 0;
     }
+    let x = (*params).get_xcoord.expect("get_xcoord is a function")(params);
+    let y = (*params).get_ycoord.expect("get_ycoord is a function")(params);
+    devtools::inspect_element(&Browser::from_raw(browser), x, y);
+    1
 }
 
-pub fn allocate() -> *mut ContextMenuHandler {
+pub fn allocate(dev_tools_menu: bool) -> *mut ContextMenuHandler {
     let handler = ContextMenuHandler {
         context_menu_handler: cef_context_menu_handler_t {
             base: cef_base_ref_counted_t {
@@ -44,10 +73,11 @@ pub fn allocate() -> *mut ContextMenuHandler {
             },
             on_before_context_menu: Some(on_before_context_menu),
             run_context_menu: None,
-            on_context_menu_command: None,
+            on_context_menu_command: Some(on_context_menu_command),
             on_context_menu_dismissed: None,
         },
         ref_count: AtomicUsize::new(1),
+        dev_tools_menu,
     };
 
     Box::into_raw(Box::from(handler))
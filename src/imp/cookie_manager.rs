@@ -0,0 +1,703 @@
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_completion_callback_t, cef_cookie_manager_get_global_manager,
+    cef_cookie_manager_t, cef_cookie_priority_t, cef_cookie_priority_t_CEF_COOKIE_PRIORITY_HIGH,
+    cef_cookie_priority_t_CEF_COOKIE_PRIORITY_LOW,
+    cef_cookie_priority_t_CEF_COOKIE_PRIORITY_MEDIUM, cef_cookie_same_site_t,
+    cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_LAX_MODE,
+    cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_NO_RESTRICTION,
+    cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_STRICT_MODE,
+    cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_UNSPECIFIED, cef_cookie_t, cef_cookie_visitor_t,
+    cef_delete_cookies_callback_t, cef_set_cookie_callback_t, cef_time_from_doublet, cef_time_t,
+    cef_time_to_doublet,
+};
+use super::strings;
+
+/// A cookie, which serializes to camelCased JSON. Times are in seconds since
+/// the Unix epoch, like `Date.now() / 1000` in JavaScript.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// The domain the cookie belongs to, which also matches its subdomains
+    /// if it starts with a dot. Empty for a cookie which only belongs to the
+    /// host it was set for.
+    pub domain: String,
+    pub path: String,
+    /// Only send the cookie over HTTPS
+    pub secure: bool,
+    /// Hide the cookie from `document.cookie`
+    pub http_only: bool,
+    /// When the cookie expires; `None` for a session cookie
+    pub expires: Option<f64>,
+    pub same_site: SameSite,
+    pub priority: CookiePriority,
+    /// Set by CEF; ignored when setting a cookie
+    pub creation: Option<f64>,
+    /// Set by CEF; ignored when setting a cookie
+    pub last_access: Option<f64>,
+}
+
+impl Default for Cookie {
+    fn default() -> Cookie {
+        Cookie {
+            name: String::new(),
+            value: String::new(),
+            domain: String::new(),
+            path: "/".to_string(),
+            secure: false,
+            http_only: false,
+            expires: None,
+            same_site: SameSite::default(),
+            priority: CookiePriority::default(),
+            creation: None,
+            last_access: None,
+        }
+    }
+}
+
+impl Cookie {
+    /// A session cookie for the whole site, which is the same as
+    /// `Cookie::default()` with a name and value
+    pub fn new(name: &str, value: &str) -> Cookie {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Cookie::default()
+        }
+    }
+
+    unsafe fn from_cef(cookie: *const cef_cookie_t) -> Cookie {
+        Cookie {
+            name: strings::from_cef_string(&(*cookie).name),
+            value: strings::from_cef_string(&(*cookie).value),
+            domain: strings::from_cef_string(&(*cookie).domain),
+            path: strings::from_cef_string(&(*cookie).path),
+            secure: (*cookie).secure == 1,
+            http_only: (*cookie).httponly == 1,
+            expires: if (*cookie).has_expires == 1 {
+                from_cef_time(&(*cookie).expires)
+            } else {
+                None
+            },
+            same_site: SameSite::from_cef((*cookie).same_site),
+            priority: CookiePriority::from_cef((*cookie).priority),
+            creation: from_cef_time(&(*cookie).creation),
+            last_access: from_cef_time(&(*cookie).last_access),
+        }
+    }
+
+    fn to_cef(&self) -> cef_cookie_t {
+        let mut cookie = cef_cookie_t::default();
+        cookie.name = strings::to_cef_string(&self.name);
+        cookie.value = strings::to_cef_string(&self.value);
+        cookie.domain = strings::to_cef_string(&self.domain);
+        cookie.path = strings::to_cef_string(&self.path);
+        cookie.secure = self.secure as c_int;
+        cookie.httponly = self.http_only as c_int;
+        if let Some(expires) = self.expires {
+            cookie.has_expires = 1;
+            unsafe { cef_time_from_doublet(expires, &mut cookie.expires) };
+        }
+        cookie.same_site = self.same_site.to_cef();
+        cookie.priority = self.priority.to_cef();
+        cookie
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SameSite {
+    /// Let the browser decide, which is `Lax` in recent versions of Chromium
+    Unspecified,
+    /// Send the cookie with cross-site requests too; needs `secure`
+    None,
+    Lax,
+    Strict,
+}
+
+impl Default for SameSite {
+    fn default() -> SameSite {
+        SameSite::Unspecified
+    }
+}
+
+impl SameSite {
+    fn to_cef(self) -> cef_cookie_same_site_t {
+        match self {
+            SameSite::Unspecified => cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_UNSPECIFIED,
+            SameSite::None => cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_NO_RESTRICTION,
+            SameSite::Lax => cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_LAX_MODE,
+            SameSite::Strict => cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_STRICT_MODE,
+        }
+    }
+
+    #[allow(non_upper_case_globals)]
+    fn from_cef(same_site: cef_cookie_same_site_t) -> SameSite {
+        match same_site {
+            cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_NO_RESTRICTION => SameSite::None,
+            cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_LAX_MODE => SameSite::Lax,
+            cef_cookie_same_site_t_CEF_COOKIE_SAME_SITE_STRICT_MODE => SameSite::Strict,
+            _ => SameSite::Unspecified,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CookiePriority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Default for CookiePriority {
+    fn default() -> CookiePriority {
+        CookiePriority::Medium
+    }
+}
+
+impl CookiePriority {
+    fn to_cef(self) -> cef_cookie_priority_t {
+        match self {
+            CookiePriority::Low => cef_cookie_priority_t_CEF_COOKIE_PRIORITY_LOW,
+            CookiePriority::Medium => cef_cookie_priority_t_CEF_COOKIE_PRIORITY_MEDIUM,
+            CookiePriority::High => cef_cookie_priority_t_CEF_COOKIE_PRIORITY_HIGH,
+        }
+    }
+
+    #[allow(non_upper_case_globals)]
+    fn from_cef(priority: cef_cookie_priority_t) -> CookiePriority {
+        match priority {
+            cef_cookie_priority_t_CEF_COOKIE_PRIORITY_LOW => CookiePriority::Low,
+            cef_cookie_priority_t_CEF_COOKIE_PRIORITY_HIGH => CookiePriority::High,
+            _ => CookiePriority::Medium,
+        }
+    }
+}
+
+/// Seconds since the Unix epoch, or `None` for a blank time
+fn from_cef_time(time: &cef_time_t) -> Option<f64> {
+    let mut seconds = 0.0;
+    if time.year == 0 || unsafe { cef_time_to_doublet(time, &mut seconds) } != 1 {
+        return None;
+    }
+    Some(seconds)
+}
+
+/// Reads and writes the cookies of a request context. Every method is
+/// asynchronous, calling its `on_done` on the UI thread once it's finished,
+/// and must be called on the UI thread of the browser process.
+pub struct CookieManager {
+    manager: *mut cef_cookie_manager_t,
+}
+
+impl CookieManager {
+    /// Takes over a reference to `manager`
+    pub(crate) unsafe fn from_raw(manager: *mut cef_cookie_manager_t) -> Option<CookieManager> {
+        if manager.is_null() {
+            None
+        } else {
+            Some(CookieManager { manager })
+        }
+    }
+
+    /// The cookie manager of the global request context, which every browser
+    /// uses
+    pub fn global() -> Option<CookieManager> {
+        unsafe {
+            CookieManager::from_raw(cef_cookie_manager_get_global_manager(std::ptr::null_mut()))
+        }
+    }
+
+    /// Get every cookie, sorted by longest path and then by earliest
+    /// creation. `on_done` is called with `None` if the cookies can't be
+    /// read.
+    pub fn visit_all(&self, on_done: Box<dyn FnMut(Option<Vec<Cookie>>)>) {
+        let visitor = allocate_visitor(on_done);
+        unsafe {
+            (*visitor).inc_ref();
+            let visiting = (*self.manager)
+                .visit_all_cookies
+                .expect("visit_all_cookies is a function")(
+                self.manager,
+                visitor as *mut cef_cookie_visitor_t,
+            );
+            (*visitor).failed = visiting != 1;
+            visitor_release(visitor as *mut cef_base_ref_counted_t);
+        }
+    }
+
+    /// Get the cookies which would be sent to `url`, leaving out `HttpOnly`
+    /// cookies unless `include_http_only` is set
+    pub fn visit_url(
+        &self,
+        url: &str,
+        include_http_only: bool,
+        on_done: Box<dyn FnMut(Option<Vec<Cookie>>)>,
+    ) {
+        let visitor = allocate_visitor(on_done);
+        unsafe {
+            (*visitor).inc_ref();
+            let visiting = (*self.manager)
+                .visit_url_cookies
+                .expect("visit_url_cookies is a function")(
+                self.manager,
+                &strings::to_cef_string(url),
+                include_http_only as c_int,
+                visitor as *mut cef_cookie_visitor_t,
+            );
+            (*visitor).failed = visiting != 1;
+            visitor_release(visitor as *mut cef_base_ref_counted_t);
+        }
+    }
+
+    /// Set a cookie for `url`, which must match the cookie's domain and path
+    /// if it has them. `on_done` is called with whether it was set.
+    pub fn set_cookie(&self, url: &str, cookie: &Cookie, on_done: Option<Box<dyn FnMut(bool)>>) {
+        let callback = allocate_set_cookie_callback(on_done);
+        unsafe {
+            (*callback).inc_ref();
+            let setting = (*self.manager)
+                .set_cookie
+                .expect("set_cookie is a function")(
+                self.manager,
+                &strings::to_cef_string(url),
+                &cookie.to_cef(),
+                callback as *mut cef_set_cookie_callback_t,
+            );
+            if setting != 1 {
+                log::warn!("can't set cookie `{}` for `{}`", cookie.name, url);
+                if let Some(on_done) = (*callback).on_done.as_mut() {
+                    on_done(false);
+                }
+            }
+            set_cookie_release(callback as *mut cef_base_ref_counted_t);
+        }
+    }
+
+    /// Delete every cookie if `url` is `None`, the cookies set for the host
+    /// of `url` if `name` is `None`, and otherwise the cookies for `url`
+    /// called `name`. `on_done` is called with the number of cookies deleted,
+    /// or `None` if they couldn't be.
+    pub fn delete_cookies(
+        &self,
+        url: Option<&str>,
+        name: Option<&str>,
+        on_done: Option<Box<dyn FnMut(Option<usize>)>>,
+    ) {
+        let callback = allocate_delete_cookies_callback(on_done);
+        unsafe {
+            (*callback).inc_ref();
+            let deleting = (*self.manager)
+                .delete_cookies
+                .expect("delete_cookies is a function")(
+                self.manager,
+                &strings::to_cef_string(url.unwrap_or_default()),
+                &strings::to_cef_string(name.unwrap_or_default()),
+                callback as *mut cef_delete_cookies_callback_t,
+            );
+            if deleting != 1 {
+                log::warn!("can't delete cookies for {:?}", url);
+                if let Some(on_done) = (*callback).on_done.as_mut() {
+                    on_done(None);
+                }
+            }
+            delete_cookies_release(callback as *mut cef_base_ref_counted_t);
+        }
+    }
+
+    /// Write the cookies to disk, if the request context has a cache path.
+    /// `on_done` is called with whether they could be flushed.
+    pub fn flush_store(&self, on_done: Option<Box<dyn FnMut(bool)>>) {
+        let callback = allocate_completion_callback(on_done);
+        unsafe {
+            (*callback).inc_ref();
+            let flushing = (*self.manager)
+                .flush_store
+                .expect("flush_store is a function")(
+                self.manager,
+                callback as *mut cef_completion_callback_t,
+            );
+            if flushing != 1 {
+                log::warn!("can't flush the cookie store");
+                if let Some(on_done) = (*callback).on_done.as_mut() {
+                    on_done(false);
+                }
+            }
+            completion_release(callback as *mut cef_base_ref_counted_t);
+        }
+    }
+}
+
+impl Clone for CookieManager {
+    fn clone(&self) -> CookieManager {
+        unsafe {
+            (*self.manager).base.add_ref.expect("add_ref is a function")(
+                self.manager as *mut cef_base_ref_counted_t,
+            );
+        }
+        CookieManager {
+            manager: self.manager,
+        }
+    }
+}
+
+impl Drop for CookieManager {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.manager).base.release.expect("release is a function")(
+                self.manager as *mut cef_base_ref_counted_t,
+            );
+        }
+    }
+}
+
+/// Collects cookies as CEF visits them, and hands them over once CEF is done
+/// with the visitor, which is the only sign that visiting has finished
+#[repr(C)]
+struct CookieVisitor {
+    visitor: cef_cookie_visitor_t,
+    ref_count: AtomicUsize,
+    cookies: Vec<Cookie>,
+    failed: bool,
+    on_done: Box<dyn FnMut(Option<Vec<Cookie>>)>,
+}
+
+impl CookieVisitor {
+    fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Drop for CookieVisitor {
+    fn drop(&mut self) {
+        let cookies = std::mem::take(&mut self.cookies);
+        (self.on_done)(if self.failed { None } else { Some(cookies) });
+    }
+}
+
+unsafe extern "C" fn visit(
+    slf: *mut cef_cookie_visitor_t,
+    cookie: *const cef_cookie_t,
+    _count: c_int,
+    _total: c_int,
+    _delete_cookie: *mut c_int,
+) -> c_int {
+    let visitor = slf as *mut CookieVisitor;
+    (*visitor).cookies.push(Cookie::from_cef(cookie));
+    1
+}
+
+fn allocate_visitor(on_done: Box<dyn FnMut(Option<Vec<Cookie>>)>) -> *mut CookieVisitor {
+    let visitor = CookieVisitor {
+        visitor: cef_cookie_visitor_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<CookieVisitor>() as u64,
+                add_ref: Some(visitor_add_ref),
+                release: Some(visitor_release),
+                has_one_ref: Some(visitor_has_one_ref),
+                has_at_least_one_ref: Some(visitor_has_at_least_one_ref),
+            },
+            visit: Some(visit),
+        },
+        ref_count: AtomicUsize::new(1),
+        cookies: Vec::new(),
+        failed: false,
+        on_done,
+    };
+
+    Box::into_raw(Box::from(visitor))
+}
+
+extern "C" fn visitor_add_ref(base: *mut cef_base_ref_counted_t) {
+    let visitor = base as *mut CookieVisitor;
+    unsafe {
+        (*visitor).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn visitor_release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let visitor = base as *mut CookieVisitor;
+    let count = unsafe { (*visitor).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(visitor);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn visitor_has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let visitor = base as *mut CookieVisitor;
+    let count = unsafe { (*visitor).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn visitor_has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let visitor = base as *mut CookieVisitor;
+    let count = unsafe { (*visitor).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
+
+#[repr(C)]
+struct SetCookieCallback {
+    callback: cef_set_cookie_callback_t,
+    ref_count: AtomicUsize,
+    on_done: Option<Box<dyn FnMut(bool)>>,
+}
+
+impl SetCookieCallback {
+    fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+unsafe extern "C" fn on_set_cookie_complete(slf: *mut cef_set_cookie_callback_t, success: c_int) {
+    let callback = slf as *mut SetCookieCallback;
+    if let Some(on_done) = (*callback).on_done.as_mut() {
+        on_done(success == 1);
+    }
+}
+
+fn allocate_set_cookie_callback(on_done: Option<Box<dyn FnMut(bool)>>) -> *mut SetCookieCallback {
+    let callback = SetCookieCallback {
+        callback: cef_set_cookie_callback_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<SetCookieCallback>() as u64,
+                add_ref: Some(set_cookie_add_ref),
+                release: Some(set_cookie_release),
+                has_one_ref: Some(set_cookie_has_one_ref),
+                has_at_least_one_ref: Some(set_cookie_has_at_least_one_ref),
+            },
+            on_complete: Some(on_set_cookie_complete),
+        },
+        ref_count: AtomicUsize::new(1),
+        on_done,
+    };
+
+    Box::into_raw(Box::from(callback))
+}
+
+extern "C" fn set_cookie_add_ref(base: *mut cef_base_ref_counted_t) {
+    let callback = base as *mut SetCookieCallback;
+    unsafe {
+        (*callback).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn set_cookie_release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut SetCookieCallback;
+    let count = unsafe { (*callback).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(callback);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn set_cookie_has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut SetCookieCallback;
+    let count = unsafe { (*callback).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn set_cookie_has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut SetCookieCallback;
+    let count = unsafe { (*callback).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
+
+#[repr(C)]
+struct DeleteCookiesCallback {
+    callback: cef_delete_cookies_callback_t,
+    ref_count: AtomicUsize,
+    on_done: Option<Box<dyn FnMut(Option<usize>)>>,
+}
+
+impl DeleteCookiesCallback {
+    fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+unsafe extern "C" fn on_delete_cookies_complete(
+    slf: *mut cef_delete_cookies_callback_t,
+    num_deleted: c_int,
+) {
+    let callback = slf as *mut DeleteCookiesCallback;
+    if let Some(on_done) = (*callback).on_done.as_mut() {
+        on_done(Some(num_deleted.max(0) as usize));
+    }
+}
+
+fn allocate_delete_cookies_callback(
+    on_done: Option<Box<dyn FnMut(Option<usize>)>>,
+) -> *mut DeleteCookiesCallback {
+    let callback = DeleteCookiesCallback {
+        callback: cef_delete_cookies_callback_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<DeleteCookiesCallback>() as u64,
+                add_ref: Some(delete_cookies_add_ref),
+                release: Some(delete_cookies_release),
+                has_one_ref: Some(delete_cookies_has_one_ref),
+                has_at_least_one_ref: Some(delete_cookies_has_at_least_one_ref),
+            },
+            on_complete: Some(on_delete_cookies_complete),
+        },
+        ref_count: AtomicUsize::new(1),
+        on_done,
+    };
+
+    Box::into_raw(Box::from(callback))
+}
+
+extern "C" fn delete_cookies_add_ref(base: *mut cef_base_ref_counted_t) {
+    let callback = base as *mut DeleteCookiesCallback;
+    unsafe {
+        (*callback).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn delete_cookies_release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut DeleteCookiesCallback;
+    let count = unsafe { (*callback).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(callback);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn delete_cookies_has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut DeleteCookiesCallback;
+    let count = unsafe { (*callback).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn delete_cookies_has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut DeleteCookiesCallback;
+    let count = unsafe { (*callback).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
+
+#[repr(C)]
+struct CompletionCallback {
+    callback: cef_completion_callback_t,
+    ref_count: AtomicUsize,
+    on_done: Option<Box<dyn FnMut(bool)>>,
+}
+
+impl CompletionCallback {
+    fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+unsafe extern "C" fn on_complete(slf: *mut cef_completion_callback_t) {
+    let callback = slf as *mut CompletionCallback;
+    if let Some(on_done) = (*callback).on_done.as_mut() {
+        on_done(true);
+    }
+}
+
+fn allocate_completion_callback(on_done: Option<Box<dyn FnMut(bool)>>) -> *mut CompletionCallback {
+    let callback = CompletionCallback {
+        callback: cef_completion_callback_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<CompletionCallback>() as u64,
+                add_ref: Some(completion_add_ref),
+                release: Some(completion_release),
+                has_one_ref: Some(completion_has_one_ref),
+                has_at_least_one_ref: Some(completion_has_at_least_one_ref),
+            },
+            on_complete: Some(on_complete),
+        },
+        ref_count: AtomicUsize::new(1),
+        on_done,
+    };
+
+    Box::into_raw(Box::from(callback))
+}
+
+extern "C" fn completion_add_ref(base: *mut cef_base_ref_counted_t) {
+    let callback = base as *mut CompletionCallback;
+    unsafe {
+        (*callback).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn completion_release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut CompletionCallback;
+    let count = unsafe { (*callback).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(callback);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn completion_has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut CompletionCallback;
+    let count = unsafe { (*callback).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn completion_has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut CompletionCallback;
+    let count = unsafe { (*callback).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
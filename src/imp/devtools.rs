@@ -0,0 +1,297 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::rc::Rc;
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_host_t, cef_browser_settings_t, cef_browser_t,
+    cef_client_t, cef_point_t, cef_window_info_t,
+};
+use super::browser::Browser;
+use super::client::{self, ClientHooks};
+use super::shortcuts::{KeyCombo, Shortcut, ShortcutAction};
+use super::task;
+use super::window_delegate::WindowOptions;
+use super::windows::{self, WindowId};
+
+/// How to show a browser's DevTools. Browsers in windows opened with
+/// `Cef::open_window` get their DevTools in a window of their own, with this
+/// title, size and icons; windowless browsers get CEF's default window.
+#[derive(Debug, Clone)]
+pub struct DevToolsOptions {
+    pub title: Option<String>,
+    pub size: Option<(i32, i32)>,
+    pub window_icon: Option<&'static [u8]>,
+    pub window_app_icon: Option<&'static [u8]>,
+    /// Select the element at this point, in view coordinates, in the
+    /// elements panel
+    pub inspect_element_at: Option<(i32, i32)>,
+}
+
+impl Default for DevToolsOptions {
+    fn default() -> DevToolsOptions {
+        DevToolsOptions {
+            title: Some("Developer Tools".to_string()),
+            size: Some((1024, 768)),
+            window_icon: None,
+            window_app_icon: None,
+            inspect_element_at: None,
+        }
+    }
+}
+
+impl DevToolsOptions {
+    fn window_options(&self) -> WindowOptions {
+        WindowOptions {
+            url: String::new(),
+            title: self.title.clone(),
+            size: self.size,
+            window_icon: self.window_icon,
+            window_app_icon: self.window_app_icon,
+            ..WindowOptions::default()
+        }
+    }
+}
+
+/// Where the user can open DevTools from, set with `Cef::set_dev_tools`. By
+/// default everything is enabled in debug builds and disabled in release
+/// builds. `Browser::show_dev_tools` works either way.
+#[derive(Debug, Clone)]
+pub struct DevTools {
+    /// The keys which open and close DevTools in every window
+    pub shortcut: Option<KeyCombo>,
+    /// Add "Inspect Element" to the context menu
+    pub context_menu: bool,
+    /// Let pages open and close their DevTools with `cef.devTools.show()` and
+    /// `cef.devTools.close()`
+    pub from_page: bool,
+    /// How DevTools opened from any of these are shown
+    pub options: DevToolsOptions,
+}
+
+impl DevTools {
+    /// F12, the context menu and the page can all open DevTools
+    pub fn enabled() -> DevTools {
+        DevTools {
+            shortcut: Some(KeyCombo::parse("F12").expect("F12 is a valid shortcut")),
+            context_menu: true,
+            from_page: true,
+            options: DevToolsOptions::default(),
+        }
+    }
+
+    /// DevTools can only be opened from Rust
+    pub fn disabled() -> DevTools {
+        DevTools {
+            shortcut: None,
+            context_menu: false,
+            from_page: false,
+            options: DevToolsOptions::default(),
+        }
+    }
+}
+
+impl Default for DevTools {
+    fn default() -> DevTools {
+        if cfg!(debug_assertions) {
+            DevTools::enabled()
+        } else {
+            DevTools::disabled()
+        }
+    }
+}
+
+thread_local! {
+    static DEV_TOOLS: RefCell<DevTools> = RefCell::new(DevTools::default());
+    /// The DevTools windows we opened, by the id of the browser they inspect
+    static WINDOWS: RefCell<BTreeMap<c_int, WindowId>> = RefCell::new(BTreeMap::new());
+}
+
+pub fn set_dev_tools(dev_tools: DevTools) {
+    DEV_TOOLS.with(|d| *d.borrow_mut() = dev_tools);
+}
+
+/// The shortcut which toggles DevTools, if it's enabled
+pub fn shortcut() -> Option<Shortcut> {
+    let combo = DEV_TOOLS.with(|d| d.borrow().shortcut)?;
+    Some(Shortcut {
+        combo,
+        action: ShortcutAction::Run(Rc::new(toggle)),
+        swallow: true,
+    })
+}
+
+pub fn context_menu_enabled() -> bool {
+    DEV_TOOLS.with(|d| d.borrow().context_menu)
+}
+
+/// Open DevTools with the app's options, inspecting the element at `x`, `y`
+pub fn inspect_element(browser: &Browser, x: i32, y: i32) {
+    let mut options = DEV_TOOLS.with(|d| d.borrow().options.clone());
+    options.inspect_element_at = Some((x, y));
+    show(browser, &options);
+}
+
+/// Run `cef.devTools.show()` or `cef.devTools.close()` from a page
+pub fn page_command(browser: &Browser, show_dev_tools: bool) {
+    let (from_page, options) = DEV_TOOLS.with(|d| {
+        let d = d.borrow();
+        (d.from_page, d.options.clone())
+    });
+    if !from_page {
+        log::debug!("pages aren't allowed to open or close DevTools");
+    } else if show_dev_tools {
+        show(browser, &options);
+    } else {
+        close(browser);
+    }
+}
+
+/// Close DevTools if `browser` is a DevTools browser or has them open, and
+/// open them otherwise
+pub fn toggle(browser: &Browser) {
+    if let Some(window_id) = window_showing(browser) {
+        close_window(window_id);
+    } else if has_dev_tools(browser) {
+        close(browser);
+    } else {
+        let options = DEV_TOOLS.with(|d| d.borrow().options.clone());
+        show(browser, &options);
+    }
+}
+
+pub fn show(browser: &Browser, options: &DevToolsOptions) {
+    let browser = browser.as_raw();
+    unsafe {
+        let host = get_host(browser);
+        let window_info = cef_window_info_t::default();
+        let mut settings = cef_browser_settings_t::default();
+        settings.size = size_of::<cef_browser_settings_t>() as u64;
+        let inspect_element_at = options
+            .inspect_element_at
+            .map(|(x, y)| cef_point_t { x, y });
+        let inspect_element_at = inspect_element_at
+            .as_ref()
+            .map_or(std::ptr::null(), |point| point as *const cef_point_t);
+
+        // CEF ignores the client if DevTools are already open, and uses its
+        // own for windowless browsers
+        let browser_id = browser_id(browser);
+        let already_open = WINDOWS.with(|w| w.borrow().contains_key(&browser_id))
+            || (*host).has_dev_tools.expect("has_dev_tools is a function")(host) == 1;
+        let client = if already_open || !in_window(host) {
+            std::ptr::null_mut()
+        } else {
+            // the DevTools browser view comes through `browser_view_delegate`
+            // just like a popup's, which puts it in a window of ours
+            let window_id = windows::register();
+            let window_options = options.window_options();
+            let client = client::allocate(
+                std::ptr::null_mut(),
+                window_id,
+                ClientHooks {
+                    on_closed: Some(Box::from(move || closed(browser_id, window_id))),
+                    dev_tools_menu: false,
+                    ..window_options.client_hooks()
+                },
+            );
+            windows::set_pending_popup(window_id, window_options);
+            WINDOWS.with(|w| w.borrow_mut().insert(browser_id, window_id));
+            (*client).inc_ref();
+            client as *mut cef_client_t
+        };
+
+        log::debug!("showing DevTools for browser {}", browser_id);
+        (*host)
+            .show_dev_tools
+            .expect("show_dev_tools is a function")(
+            host,
+            &window_info,
+            client,
+            &settings,
+            inspect_element_at,
+        );
+    }
+}
+
+pub fn close(browser: &Browser) {
+    let window_id = WINDOWS.with(|w| w.borrow().get(&browser_id(browser.as_raw())).cloned());
+    match window_id {
+        // closing the window closes the DevTools browser in it too
+        Some(window_id) => close_window(window_id),
+        None => unsafe {
+            let host = get_host(browser.as_raw());
+            (*host)
+                .close_dev_tools
+                .expect("close_dev_tools is a function")(host);
+        },
+    }
+}
+
+pub fn has_dev_tools(browser: &Browser) -> bool {
+    unsafe {
+        let host = get_host(browser.as_raw());
+        (*host).has_dev_tools.expect("has_dev_tools is a function")(host) == 1
+    }
+}
+
+/// The DevTools window showing `browser`, if it's a DevTools browser of ours
+fn window_showing(browser: &Browser) -> Option<WindowId> {
+    let id = browser_id(browser.as_raw());
+    let window_ids: Vec<WindowId> = WINDOWS.with(|w| w.borrow().values().cloned().collect());
+    window_ids
+        .into_iter()
+        .find(|window_id| windows::browser(*window_id).map(|b| browser_id(b.as_raw())) == Some(id))
+}
+
+fn close_window(window_id: WindowId) {
+    if let Some(window) = windows::window(window_id) {
+        // goes through `can_close`, just like the user closing the window
+        unsafe { (*window).close.expect("close is a function")(window) };
+    }
+}
+
+/// The DevTools browser has closed, which CEF also does when the browser it
+/// inspects closes, so close its window if it's still open
+fn closed(browser_id: c_int, window_id: WindowId) {
+    WINDOWS.with(|w| w.borrow_mut().remove(&browser_id));
+    let window = match windows::window(window_id) {
+        Some(window) => window,
+        None => return,
+    };
+    unsafe {
+        let base = window as *mut cef_base_ref_counted_t;
+        (*base).add_ref.expect("add_ref is a function")(base);
+        task::post_task(move || {
+            if (*window).is_closed.expect("is_closed is a function")(window) != 1 {
+                (*window).close.expect("close is a function")(window);
+            }
+            (*base).release.expect("release is a function")(base);
+        });
+    }
+}
+
+/// Whether a browser is shown in one of our windows, rather than being
+/// windowless
+unsafe fn in_window(host: *mut cef_browser_host_t) -> bool {
+    let client = (*host).get_client.expect("get_client is a function")(host);
+    if client.is_null() {
+        return false;
+    }
+    let window_id = client::window_id(client);
+    (*client).base.release.expect("release is a function")(client as *mut cef_base_ref_counted_t);
+    window_id.is_some()
+}
+
+unsafe fn get_host(browser: *mut cef_browser_t) -> *mut cef_browser_host_t {
+    (*browser).get_host.expect("get_host is a function")(browser)
+}
+
+fn browser_id(browser: *mut cef_browser_t) -> c_int {
+    unsafe {
+        (*browser)
+            .get_identifier
+            .expect("get_identifier is a function")(browser)
+    }
+}
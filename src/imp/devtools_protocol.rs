@@ -0,0 +1,407 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::mem::size_of;
+use std::os::raw::{c_int, c_void};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_host_t, cef_browser_t, cef_dev_tools_message_observer_t,
+    cef_registration_t, cef_string_t, size_t,
+};
+use super::browser::Browser;
+use super::pending::{self, Pending};
+use super::strings;
+
+/// Called with a DevTools protocol method's result, or its error message
+pub type OnResult = Box<dyn FnOnce(Result<Value, String>)>;
+
+/// Called with the parameters of each DevTools protocol event subscribed to
+pub type OnEvent = Box<dyn FnMut(&Value)>;
+
+/// Identifies a subscription made with `DevToolsClient::subscribe`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SubscriptionId(u64);
+
+struct Subscription {
+    browser_id: c_int,
+    event: String,
+    on_event: Rc<RefCell<OnEvent>>,
+}
+
+/// Talks the Chrome DevTools protocol to a browser, in-process, without
+/// needing a remote debugging port. Get one with `Browser::devtools`.
+///
+/// Most domains need enabling before they send events, so call
+/// `Network.enable` before subscribing to `Network.responseReceived` and so
+/// on. See https://chromedevtools.github.io/devtools-protocol/ for the
+/// methods and events there are.
+#[derive(Debug, Clone)]
+pub struct DevToolsClient {
+    browser: Browser,
+}
+
+impl DevToolsClient {
+    pub(crate) fn new(browser: Browser) -> DevToolsClient {
+        DevToolsClient { browser }
+    }
+
+    /// Call `method` (such as `Network.enable`) with `params`, calling
+    /// `on_result` with its result, or with its error message. Pass `()` for
+    /// methods without parameters.
+    pub fn call<P: Serialize>(&self, method: &str, params: P, on_result: Option<OnResult>) {
+        let params = match serde_json::to_value(params) {
+            Ok(Value::Null) => json!({}),
+            Ok(params) => params,
+            Err(e) => {
+                if let Some(on_result) = on_result {
+                    on_result(Err(format!("invalid parameters for `{}`: {}", method, e)));
+                }
+                return;
+            }
+        };
+        let on_result: OnResult = match on_result {
+            Some(on_result) => on_result,
+            None => Box::from(|_: Result<Value, String>| {}),
+        };
+        execute(&self.browser, method, params, on_result);
+    }
+
+    /// Call `method` like `call`, deserializing its result into `R`
+    pub fn call_as<P: Serialize, R: DeserializeOwned + 'static>(
+        &self,
+        method: &str,
+        params: P,
+        on_result: Box<dyn FnOnce(Result<R, String>)>,
+    ) {
+        let method_name = method.to_string();
+        self.call(
+            method,
+            params,
+            Some(Box::from(move |result: Result<Value, String>| {
+                on_result(result.and_then(|result| {
+                    serde_json::from_value(result)
+                        .map_err(|e| format!("unexpected result from `{}`: {}", method_name, e))
+                }))
+            })),
+        );
+    }
+
+    /// Call `method` like `call`, as a `Future`
+    pub fn call_async<P: Serialize>(&self, method: &str, params: P) -> Pending<Value> {
+        let (pending, resolver) = pending::pending();
+        self.call(
+            method,
+            params,
+            Some(Box::from(move |result: Result<Value, String>| {
+                resolver.resolve(result)
+            })),
+        );
+        pending
+    }
+
+    /// Call `method` like `call_as`, as a `Future`
+    pub fn call_as_async<P: Serialize, R: DeserializeOwned + 'static>(
+        &self,
+        method: &str,
+        params: P,
+    ) -> Pending<R> {
+        let (pending, resolver) = pending::pending();
+        self.call_as(
+            method,
+            params,
+            Box::from(move |result: Result<R, String>| resolver.resolve(result)),
+        );
+        pending
+    }
+
+    /// Call `on_event` with the parameters of every `event` (such as
+    /// `Network.responseReceived`) the browser sends, until `unsubscribe` is
+    /// called or the browser closes
+    pub fn subscribe(&self, event: &str, on_event: OnEvent) -> SubscriptionId {
+        let browser_id = observe(&self.browser);
+        let id = SubscriptionId(NEXT_SUBSCRIPTION_ID.with(|id| id.replace(id.get() + 1)));
+        SUBSCRIPTIONS.with(|s| {
+            s.borrow_mut().insert(
+                id,
+                Subscription {
+                    browser_id,
+                    event: event.to_string(),
+                    on_event: Rc::new(RefCell::new(on_event)),
+                },
+            )
+        });
+        id
+    }
+
+    pub fn unsubscribe(&self, id: SubscriptionId) {
+        SUBSCRIPTIONS.with(|s| s.borrow_mut().remove(&id));
+    }
+}
+
+/// Receives the results of the DevTools protocol methods we send a browser,
+/// and the events it sends
+#[repr(C)]
+pub struct DevToolsObserver {
+    observer: cef_dev_tools_message_observer_t,
+    ref_count: AtomicUsize,
+}
+
+thread_local! {
+    static NEXT_MESSAGE_ID: Cell<c_int> = Cell::new(1);
+    static NEXT_SUBSCRIPTION_ID: Cell<u64> = Cell::new(1);
+    /// The browser each sent message went to, and what to do with its result
+    static PENDING: RefCell<BTreeMap<c_int, (c_int, OnResult)>> = RefCell::new(BTreeMap::new());
+    /// Observer registrations by browser id, which unregister when released
+    static REGISTRATIONS: RefCell<BTreeMap<c_int, *mut cef_registration_t>> =
+        RefCell::new(BTreeMap::new());
+    static SUBSCRIPTIONS: RefCell<BTreeMap<SubscriptionId, Subscription>> =
+        RefCell::new(BTreeMap::new());
+}
+
+/// Start observing `browser`'s DevTools messages if we aren't already,
+/// returning its id
+fn observe(browser: &Browser) -> c_int {
+    let browser = browser.as_raw();
+    unsafe {
+        let browser_id = (*browser)
+            .get_identifier
+            .expect("get_identifier is a function")(browser);
+        if !REGISTRATIONS.with(|r| r.borrow().contains_key(&browser_id)) {
+            let host = get_host(browser);
+            let observer = allocate();
+            let registration = (*host)
+                .add_dev_tools_message_observer
+                .expect("add_dev_tools_message_observer is a function")(
+                host,
+                observer as *mut cef_dev_tools_message_observer_t,
+            );
+            REGISTRATIONS.with(|r| r.borrow_mut().insert(browser_id, registration));
+        }
+        browser_id
+    }
+}
+
+unsafe fn get_host(browser: *mut cef_browser_t) -> *mut cef_browser_host_t {
+    (*browser).get_host.expect("get_host is a function")(browser)
+}
+
+/// Call the DevTools protocol method `method` (such as
+/// `Page.captureScreenshot`) on `browser`, calling `on_result` with its result
+pub fn execute(browser: &Browser, method: &str, params: Value, on_result: OnResult) {
+    let browser_id = observe(browser);
+    unsafe {
+        let host = get_host(browser.as_raw());
+        let message_id = NEXT_MESSAGE_ID.with(|id| {
+            let message_id = id.get();
+            id.set(message_id + 1);
+            message_id
+        });
+        let message = json!({ "id": message_id, "method": method, "params": params }).to_string();
+        log::debug!("sending DevTools message {}: {}", message_id, method);
+        PENDING.with(|p| p.borrow_mut().insert(message_id, (browser_id, on_result)));
+
+        let sent = (*host)
+            .send_dev_tools_message
+            .expect("send_dev_tools_message is a function")(
+            host,
+            message.as_ptr() as *const c_void,
+            message.len() as size_t,
+        );
+        if sent != 1 {
+            if let Some((_, on_result)) = PENDING.with(|p| p.borrow_mut().remove(&message_id)) {
+                on_result(Err(format!("failed to send `{}`", method)));
+            }
+        }
+    }
+}
+
+/// Stop observing a browser which is closing, dropping its subscriptions and
+/// failing any calls it hasn't answered
+pub fn forget(browser_id: c_int) {
+    SUBSCRIPTIONS.with(|s| {
+        s.borrow_mut()
+            .retain(|_, subscription| subscription.browser_id != browser_id)
+    });
+
+    if let Some(registration) = REGISTRATIONS.with(|r| r.borrow_mut().remove(&browser_id)) {
+        unsafe {
+            (*registration).base.release.expect("release is a function")(
+                registration as *mut cef_base_ref_counted_t,
+            );
+        }
+    }
+
+    let unanswered: Vec<OnResult> = PENDING.with(|p| {
+        let mut pending = p.borrow_mut();
+        let ids: Vec<c_int> = pending
+            .iter()
+            .filter(|(_, (id, _))| *id == browser_id)
+            .map(|(message_id, _)| *message_id)
+            .collect();
+        ids.into_iter()
+            .filter_map(|message_id| pending.remove(&message_id))
+            .map(|(_, on_result)| on_result)
+            .collect()
+    });
+    for on_result in unanswered {
+        on_result(Err("the browser closed".to_string()));
+    }
+}
+
+extern "C" fn on_dev_tools_message(
+    _slf: *mut cef_dev_tools_message_observer_t,
+    _browser: *mut cef_browser_t,
+    _message: *const c_void,
+    _message_size: size_t,
+) -> c_int {
+    // let CEF split the message into method results and events
+    0
+}
+
+unsafe extern "C" fn on_dev_tools_event(
+    _slf: *mut cef_dev_tools_message_observer_t,
+    browser: *mut cef_browser_t,
+    method: *const cef_string_t,
+    params: *const c_void,
+    params_size: size_t,
+) {
+    let browser_id = (*browser)
+        .get_identifier
+        .expect("get_identifier is a function")(browser);
+    let method = strings::from_cef_string(method);
+    // clone the callbacks out, so they can subscribe and unsubscribe
+    let subscribers: Vec<Rc<RefCell<OnEvent>>> = SUBSCRIPTIONS.with(|s| {
+        s.borrow()
+            .values()
+            .filter(|s| s.browser_id == browser_id && s.event == method)
+            .map(|s| s.on_event.clone())
+            .collect()
+    });
+    if subscribers.is_empty() {
+        return;
+    }
+
+    let params: &[u8] = if params.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(params as *const u8, params_size as usize)
+    };
+    let params: Value = match serde_json::from_slice(params) {
+        Ok(params) => params,
+        Err(e) => {
+            log::warn!("invalid `{}` DevTools event: {}", method, e);
+            return;
+        }
+    };
+    for on_event in subscribers {
+        if let Ok(mut on_event) = on_event.try_borrow_mut() {
+            let on_event = &mut *on_event;
+            on_event(&params);
+        }
+    }
+}
+
+unsafe extern "C" fn on_dev_tools_method_result(
+    _slf: *mut cef_dev_tools_message_observer_t,
+    _browser: *mut cef_browser_t,
+    message_id: c_int,
+    success: c_int,
+    result: *const c_void,
+    result_size: size_t,
+) {
+    let on_result = match PENDING.with(|p| p.borrow_mut().remove(&message_id)) {
+        Some((_, on_result)) => on_result,
+        // a message we didn't send
+        None => return,
+    };
+
+    let result: &[u8] = if result.is_null() {
+        &[]
+    } else {
+        std::slice::from_raw_parts(result as *const u8, result_size as usize)
+    };
+    let result: Value = match serde_json::from_slice(result) {
+        Ok(result) => result,
+        Err(e) => {
+            on_result(Err(format!("invalid DevTools result: {}", e)));
+            return;
+        }
+    };
+    if success == 1 {
+        on_result(Ok(result));
+    } else {
+        // errors look like `{"code": -32000, "message": "..."}`
+        let message = result["message"]
+            .as_str()
+            .map(|m| m.to_string())
+            .unwrap_or_else(|| result.to_string());
+        on_result(Err(message));
+    }
+}
+
+fn allocate() -> *mut DevToolsObserver {
+    let observer = DevToolsObserver {
+        observer: cef_dev_tools_message_observer_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<DevToolsObserver>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            on_dev_tools_message: Some(on_dev_tools_message),
+            on_dev_tools_method_result: Some(on_dev_tools_method_result),
+            on_dev_tools_event: Some(on_dev_tools_event),
+            ..Default::default()
+        },
+        ref_count: AtomicUsize::new(1),
+    };
+
+    Box::into_raw(Box::from(observer))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let observer = base as *mut DevToolsObserver;
+    unsafe {
+        (*observer).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let observer = base as *mut DevToolsObserver;
+    let count = unsafe { (*observer).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(observer);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let observer = base as *mut DevToolsObserver;
+    let count = unsafe { (*observer).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let observer = base as *mut DevToolsObserver;
+    let count = unsafe { (*observer).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
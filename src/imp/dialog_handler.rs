@@ -0,0 +1,334 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_dialog_handler_t, cef_file_dialog_callback_t,
+    cef_file_dialog_mode_t, cef_file_dialog_mode_t_FILE_DIALOG_OVERWRITEPROMPT_FLAG,
+    cef_string_list_free, cef_string_list_t, cef_string_t,
+};
+use super::browser::Browser;
+use super::file_dialog::{FileDialogMode, FileDialogOptions, FileFilter};
+use super::strings;
+
+/// Intercepts file dialogs, both those started by the page (such as
+/// `<input type="file">`) and those started with `cef.runFileDialog` /
+/// `Browser::run_file_dialog`, instead of showing the platform's dialog.
+pub trait DialogHandler {
+    /// Handle a file dialog by calling `callback.select` or `callback.cancel`,
+    /// either immediately or later on; the page waits until one of them is
+    /// called. Return false to show the platform's dialog instead (in which
+    /// case `callback` must not be used).
+    fn on_file_dialog(
+        &self,
+        browser: &Browser,
+        options: &FileDialogOptions,
+        callback: FileDialogCallback,
+    ) -> bool;
+}
+
+/// Used to answer a file dialog intercepted by a `DialogHandler`
+pub struct FileDialogCallback {
+    callback: *mut cef_file_dialog_callback_t,
+}
+
+impl FileDialogCallback {
+    unsafe fn from_raw(callback: *mut cef_file_dialog_callback_t) -> Self {
+        (*callback).base.add_ref.expect("add_ref is a function")(
+            callback as *mut cef_base_ref_counted_t,
+        );
+        FileDialogCallback { callback }
+    }
+
+    /// Answer the dialog with `paths`, as though the user had chosen them
+    /// with the filter at `filter_index` selected
+    pub fn select(self, filter_index: usize, paths: Vec<PathBuf>) {
+        let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+        unsafe {
+            let list = strings::to_string_list(&paths);
+            (*self.callback).cont.expect("cont is a function")(
+                self.callback,
+                filter_index as c_int,
+                list,
+            );
+            cef_string_list_free(list);
+        }
+    }
+
+    pub fn cancel(self) {
+        unsafe { (*self.callback).cancel.expect("cancel is a function")(self.callback) };
+    }
+}
+
+impl Drop for FileDialogCallback {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.callback)
+                .base
+                .release
+                .expect("release is a function")(
+                self.callback as *mut cef_base_ref_counted_t
+            );
+        }
+    }
+}
+
+#[repr(C)]
+pub struct CefDialogHandler {
+    dialog_handler: cef_dialog_handler_t,
+    ref_count: AtomicUsize,
+    handler: Rc<dyn DialogHandler>,
+}
+
+impl CefDialogHandler {
+    pub fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+unsafe extern "C" fn on_file_dialog(
+    slf: *mut cef_dialog_handler_t,
+    browser: *mut cef_browser_t,
+    mode: cef_file_dialog_mode_t,
+    title: *const cef_string_t,
+    default_file_path: *const cef_string_t,
+    accept_filters: cef_string_list_t,
+    selected_accept_filter: c_int,
+    callback: *mut cef_file_dialog_callback_t,
+) -> c_int {
+    let handler = slf as *mut CefDialogHandler;
+    let options = FileDialogOptions {
+        mode: FileDialogMode::from_cef(mode),
+        title: strings::from_cef_string(title),
+        default_file_name: strings::from_cef_string(default_file_path),
+        filters: strings::from_string_list(accept_filters)
+            .iter()
+            .map(|f| FileFilter::parse(f))
+            .collect(),
+        selected_filter: selected_accept_filter.max(0) as usize,
+        overwrite_prompt: mode & cef_file_dialog_mode_t_FILE_DIALOG_OVERWRITEPROMPT_FLAG != 0,
+    };
+    log::debug!("intercepted file dialog: {:?}", options);
+
+    let browser = Browser::from_raw(browser);
+    let callback = FileDialogCallback::from_raw(callback);
+    let handler = (*handler).handler.clone();
+    if handler.on_file_dialog(&browser, &options, callback) {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn allocate(handler: Rc<dyn DialogHandler>) -> *mut CefDialogHandler {
+    let dialog_handler = CefDialogHandler {
+        dialog_handler: cef_dialog_handler_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<CefDialogHandler>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            on_file_dialog: Some(on_file_dialog),
+        },
+        ref_count: AtomicUsize::new(1),
+        handler,
+    };
+
+    Box::into_raw(Box::from(dialog_handler))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let dialog_handler = base as *mut CefDialogHandler;
+    unsafe {
+        (*dialog_handler).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let dialog_handler = base as *mut CefDialogHandler;
+    let count = unsafe { (*dialog_handler).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(dialog_handler);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let dialog_handler = base as *mut CefDialogHandler;
+    let count = unsafe { (*dialog_handler).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let dialog_handler = base as *mut CefDialogHandler;
+    let count = unsafe { (*dialog_handler).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Picks files for a dialog from a listing of a `SandboxedDirectory`
+pub type DirectoryChooser = Box<dyn Fn(&FileDialogOptions, Vec<PathBuf>, FileDialogCallback)>;
+
+/// A dialog handler which confines file dialogs to a single directory and
+/// never shows the platform's dialog.
+///
+/// By default, saving goes to the requested file name inside the directory,
+/// opening picks the requested file (or else the first matching file),
+/// opening multiple files picks every matching file, and opening a folder
+/// picks the directory itself. Use `with_chooser` to pick from the directory
+/// listing yourself instead, for example by showing an in-app picker.
+pub struct SandboxedDirectory {
+    root: PathBuf,
+    chooser: Option<DirectoryChooser>,
+}
+
+impl SandboxedDirectory {
+    pub fn new<P: Into<PathBuf>>(root: P) -> SandboxedDirectory {
+        SandboxedDirectory {
+            root: root.into(),
+            chooser: None,
+        }
+    }
+
+    pub fn with_chooser(mut self, chooser: DirectoryChooser) -> SandboxedDirectory {
+        self.chooser = Some(chooser);
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The files in the directory which match any of the dialog's filters.
+    /// MIME type filters can't be checked, so they match every file.
+    pub fn listing(&self, options: &FileDialogOptions) -> Vec<PathBuf> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("can't list `{}`: {}", self.root.display(), e);
+                return Vec::new();
+            }
+        };
+
+        let mut listing: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter(|path| {
+                options.filters.is_empty() || options.filters.iter().any(|f| f.matches(path))
+            })
+            .collect();
+        listing.sort();
+        listing
+    }
+
+    /// The requested file name, confined to the directory
+    fn sandboxed_file(&self, options: &FileDialogOptions) -> Option<PathBuf> {
+        Path::new(&options.default_file_name)
+            .file_name()
+            .map(|name| self.root.join(name))
+    }
+}
+
+impl DialogHandler for SandboxedDirectory {
+    fn on_file_dialog(
+        &self,
+        _browser: &Browser,
+        options: &FileDialogOptions,
+        callback: FileDialogCallback,
+    ) -> bool {
+        if let Some(chooser) = &self.chooser {
+            chooser(options, self.listing(options), callback);
+            return true;
+        }
+
+        let paths = match options.mode {
+            FileDialogMode::Save => vec![self
+                .sandboxed_file(options)
+                .unwrap_or_else(|| self.root.join("untitled"))],
+            FileDialogMode::OpenFolder => vec![self.root.clone()],
+            FileDialogMode::Open => self
+                .sandboxed_file(options)
+                .filter(|path| path.is_file())
+                .or_else(|| self.listing(options).into_iter().next())
+                .into_iter()
+                .collect(),
+            FileDialogMode::OpenMultiple => self.listing(options),
+        };
+
+        if paths.is_empty() {
+            callback.cancel();
+        } else {
+            callback.select(options.selected_filter, paths);
+        }
+        true
+    }
+}
+
+/// A dialog handler which answers file dialogs from a queue of canned
+/// answers, cancelling any dialog it has no answer for. Useful for automated
+/// tests; keep an `Rc` to it to queue answers and inspect which dialogs were
+/// opened.
+#[derive(Default)]
+pub struct AutomatedDialogs {
+    answers: RefCell<VecDeque<Option<Vec<PathBuf>>>>,
+    requests: RefCell<Vec<FileDialogOptions>>,
+}
+
+impl AutomatedDialogs {
+    pub fn new() -> AutomatedDialogs {
+        AutomatedDialogs::default()
+    }
+
+    /// Answer the next dialog with `paths`
+    pub fn answer(&self, paths: Vec<PathBuf>) {
+        self.answers.borrow_mut().push_back(Some(paths));
+    }
+
+    /// Cancel the next dialog
+    pub fn cancel_next(&self) {
+        self.answers.borrow_mut().push_back(None);
+    }
+
+    /// The options of every dialog which has been opened so far
+    pub fn requests(&self) -> Vec<FileDialogOptions> {
+        self.requests.borrow().clone()
+    }
+}
+
+impl DialogHandler for AutomatedDialogs {
+    fn on_file_dialog(
+        &self,
+        _browser: &Browser,
+        options: &FileDialogOptions,
+        callback: FileDialogCallback,
+    ) -> bool {
+        self.requests.borrow_mut().push(options.clone());
+        let answer = self.answers.borrow_mut().pop_front();
+        match answer {
+            Some(Some(paths)) if !paths.is_empty() => {
+                callback.select(options.selected_filter, paths)
+            }
+            _ => callback.cancel(),
+        }
+        true
+    }
+}
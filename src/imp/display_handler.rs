@@ -1,5 +1,6 @@
 use std::mem::size_of;
 use std::os::raw::c_int;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::bindings::{
@@ -9,12 +10,29 @@ use super::bindings::{
     cef_log_severity_t_LOGSEVERITY_INFO, cef_log_severity_t_LOGSEVERITY_WARNING, cef_string_t,
     cef_window_t,
 };
+use super::strings;
+
+/// A message a page logged to its console
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsoleMessage {
+    pub level: log::Level,
+    pub message: String,
+    /// The URL of the script which logged the message
+    pub source: String,
+    pub line: i32,
+}
+
+/// Called with every message a browser's pages log to the console
+pub type OnConsoleMessage = Rc<dyn Fn(&ConsoleMessage)>;
 
 #[repr(C)]
 pub struct DisplayHandler {
     display_handler: cef_display_handler_t,
     ref_count: AtomicUsize,
     window: *mut cef_window_t,
+    /// Keep the window fullscreen when the page leaves fullscreen
+    lock_fullscreen: bool,
+    on_console_message: Option<OnConsoleMessage>,
 }
 
 impl DisplayHandler {
@@ -29,6 +47,14 @@ unsafe extern "C" fn on_fullscreen_mode_change(
     fullscreen: i32,
 ) {
     let handler = slf as *mut DisplayHandler;
+    if (*handler).window.is_null() {
+        // windowless browsers have nothing to make fullscreen
+        return;
+    }
+    if fullscreen == 0 && (*handler).lock_fullscreen {
+        log::debug!("keeping the window fullscreen");
+        return;
+    }
     (*(*handler).window)
         .set_fullscreen
         .expect("set_fullscreen exists")((*handler).window, fullscreen);
@@ -43,12 +69,12 @@ unsafe extern "C" fn on_tooltip(
 }
 
 extern "C" fn on_console_message(
-    _slf: *mut cef_display_handler_t,
+    slf: *mut cef_display_handler_t,
     _browser: *mut cef_browser_t,
     level: cef_log_severity_t,
     message: *const cef_string_t,
-    _source: *const cef_string_t,
-    _line: i32,
+    source: *const cef_string_t,
+    line: i32,
 ) -> i32 {
     let chars: *mut u16 = unsafe { (*message).str_ };
     let len: usize = unsafe { (*message).length } as usize;
@@ -58,20 +84,35 @@ extern "C" fn on_console_message(
         .collect::<String>();
 
     #[allow(non_upper_case_globals)]
-    match level {
-        cef_log_severity_t_LOGSEVERITY_DEFAULT => log::info!("[CONSOLE] {}", message),
-        cef_log_severity_t_LOGSEVERITY_DEBUG => log::debug!("[CONSOLE] {}", message),
-        cef_log_severity_t_LOGSEVERITY_INFO => log::info!("[CONSOLE] {}", message),
-        cef_log_severity_t_LOGSEVERITY_WARNING => log::warn!("[CONSOLE] {}", message),
-        cef_log_severity_t_LOGSEVERITY_ERROR => log::error!("[CONSOLE] {}", message),
-        cef_log_severity_t_LOGSEVERITY_FATAL => log::error!("[CONSOLE] {}", message),
-        _ => log::info!("[CONSOLE] {}", message),
+    let level = match level {
+        cef_log_severity_t_LOGSEVERITY_DEFAULT => log::Level::Info,
+        cef_log_severity_t_LOGSEVERITY_DEBUG => log::Level::Debug,
+        cef_log_severity_t_LOGSEVERITY_INFO => log::Level::Info,
+        cef_log_severity_t_LOGSEVERITY_WARNING => log::Level::Warn,
+        cef_log_severity_t_LOGSEVERITY_ERROR => log::Level::Error,
+        cef_log_severity_t_LOGSEVERITY_FATAL => log::Level::Error,
+        _ => log::Level::Info,
+    };
+    log::log!(level, "[CONSOLE] {}", message);
+
+    let handler = slf as *mut DisplayHandler;
+    if let Some(on_console_message) = unsafe { &(*handler).on_console_message } {
+        on_console_message(&ConsoleMessage {
+            level,
+            message,
+            source: unsafe { strings::from_cef_string(source) },
+            line,
+        });
     }
 
     1
 }
 
-pub fn allocate(window: *mut cef_window_t) -> *mut DisplayHandler {
+pub fn allocate(
+    window: *mut cef_window_t,
+    lock_fullscreen: bool,
+    on_console_message: Option<OnConsoleMessage>,
+) -> *mut DisplayHandler {
     let handler = DisplayHandler {
         display_handler: cef_display_handler_t {
             base: cef_base_ref_counted_t {
@@ -92,6 +133,8 @@ pub fn allocate(window: *mut cef_window_t) -> *mut DisplayHandler {
             on_loading_progress_change: None,
         },
         window,
+        lock_fullscreen,
+        on_console_message,
         ref_count: AtomicUsize::new(1),
     };
 
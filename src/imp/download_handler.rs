@@ -0,0 +1,436 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Serialize;
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_before_download_callback_t, cef_browser_t, cef_download_handler_t,
+    cef_download_item_callback_t, cef_download_item_t, cef_string_t, cef_string_userfree_t,
+};
+use super::browser::Browser;
+use super::strings;
+
+/// A download, as of its latest update. Serializes to the camelCased JSON
+/// which pages get from `cef.downloads`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Download {
+    /// Unique for the lifetime of the app; used to cancel, pause and resume
+    /// the download
+    pub id: u32,
+    pub url: String,
+    /// The URL before any redirects
+    pub original_url: String,
+    pub suggested_file_name: String,
+    pub mime_type: String,
+    /// Where the download is being saved, once that has been decided
+    pub full_path: Option<PathBuf>,
+    pub received_bytes: i64,
+    /// `None` if the server didn't say how big the download is
+    pub total_bytes: Option<i64>,
+    /// Bytes per second
+    pub speed: i64,
+    /// `None` if the size of the download isn't known
+    pub percent_complete: Option<i32>,
+    pub state: DownloadState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadState {
+    InProgress,
+    Paused,
+    Complete,
+    Cancelled,
+}
+
+type ItemGetter<T> = Option<unsafe extern "C" fn(*mut cef_download_item_t) -> T>;
+
+impl Download {
+    unsafe fn from_raw(item: *mut cef_download_item_t) -> Download {
+        let get_string = |getter: ItemGetter<cef_string_userfree_t>| {
+            strings::from_userfree(getter.expect("getter is a function")(item))
+        };
+        let id = (*item).get_id.expect("get_id is a function")(item);
+        let full_path = get_string((*item).get_full_path);
+        let total_bytes = (*item)
+            .get_total_bytes
+            .expect("get_total_bytes is a function")(item);
+        let percent_complete = (*item)
+            .get_percent_complete
+            .expect("get_percent_complete is a function")(item);
+        let is = |getter: ItemGetter<c_int>| getter.expect("getter is a function")(item) == 1;
+        let state = if is((*item).is_complete) {
+            DownloadState::Complete
+        } else if is((*item).is_canceled) {
+            DownloadState::Cancelled
+        } else if PAUSED.with(|p| p.borrow().contains(&id)) {
+            DownloadState::Paused
+        } else {
+            DownloadState::InProgress
+        };
+
+        Download {
+            id,
+            url: get_string((*item).get_url),
+            original_url: get_string((*item).get_original_url),
+            suggested_file_name: get_string((*item).get_suggested_file_name),
+            mime_type: get_string((*item).get_mime_type),
+            full_path: Some(PathBuf::from(full_path)).filter(|p| !p.as_os_str().is_empty()),
+            received_bytes: (*item)
+                .get_received_bytes
+                .expect("get_received_bytes is a function")(item),
+            total_bytes: Some(total_bytes).filter(|bytes| *bytes > 0),
+            speed: (*item)
+                .get_current_speed
+                .expect("get_current_speed is a function")(item),
+            percent_complete: Some(percent_complete).filter(|percent| *percent >= 0),
+            state,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state == DownloadState::Complete || self.state == DownloadState::Cancelled
+    }
+}
+
+/// Where a download goes
+#[derive(Debug, Clone)]
+pub enum DownloadDestination {
+    /// Ask the user with a save dialog, which goes through the browser's
+    /// `DialogHandler` if it has one
+    Prompt,
+    /// Save the download to this path without asking
+    SaveTo(PathBuf),
+    /// Cancel the download
+    Deny,
+}
+
+/// Decides where downloads go and follows their progress. Browsers without
+/// one don't download anything.
+pub trait DownloadHandler {
+    /// Decide where `download` goes, before it starts
+    fn destination(&self, browser: &Browser, download: &Download) -> DownloadDestination;
+
+    /// Called whenever a download makes progress, is paused or resumed, and
+    /// once more when it completes or is cancelled. Pages can follow the same
+    /// updates through `cef.downloads`.
+    fn on_updated(&self, _browser: &Browser, _download: &Download) {}
+}
+
+/// Asks the user where to save every download
+pub struct PromptForDownloads;
+
+impl DownloadHandler for PromptForDownloads {
+    fn destination(&self, _browser: &Browser, _download: &Download) -> DownloadDestination {
+        DownloadDestination::Prompt
+    }
+}
+
+/// Saves every download into a directory without asking, adding a number to
+/// the file name rather than overwriting an existing file
+pub struct SaveToDirectory {
+    directory: PathBuf,
+}
+
+impl SaveToDirectory {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> SaveToDirectory {
+        SaveToDirectory {
+            directory: directory.into(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// A path in the directory for `file_name` which isn't taken yet
+    fn unused_path(&self, file_name: &str) -> PathBuf {
+        // only keep the file name, so downloads can't escape the directory
+        let file_name = Path::new(file_name)
+            .file_name()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("download"));
+        let stem = file_name
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = file_name
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+
+        let mut path = self.directory.join(&file_name);
+        let mut n = 1;
+        while path.exists() {
+            path = self
+                .directory
+                .join(format!("{} ({}){}", stem, n, extension));
+            n += 1;
+        }
+        path
+    }
+}
+
+impl DownloadHandler for SaveToDirectory {
+    fn destination(&self, _browser: &Browser, download: &Download) -> DownloadDestination {
+        if let Err(e) = std::fs::create_dir_all(&self.directory) {
+            log::warn!("can't create `{}`: {}", self.directory.display(), e);
+            return DownloadDestination::Deny;
+        }
+        DownloadDestination::SaveTo(self.unused_path(&download.suggested_file_name))
+    }
+}
+
+/// Cancels every download
+pub struct DenyDownloads;
+
+impl DownloadHandler for DenyDownloads {
+    fn destination(&self, _browser: &Browser, _download: &Download) -> DownloadDestination {
+        DownloadDestination::Deny
+    }
+}
+
+/// The latest callback CEF gave us for controlling a download which hasn't
+/// finished, and the browser it belongs to
+struct DownloadControl {
+    browser_id: c_int,
+    callback: *mut cef_download_item_callback_t,
+}
+
+impl Drop for DownloadControl {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.callback)
+                .base
+                .release
+                .expect("release is a function")(
+                self.callback as *mut cef_base_ref_counted_t
+            );
+        }
+    }
+}
+
+thread_local! {
+    static CONTROLS: RefCell<BTreeMap<u32, DownloadControl>> = RefCell::new(BTreeMap::new());
+    /// CEF doesn't report whether a download is paused, so we keep track
+    static PAUSED: RefCell<BTreeSet<u32>> = RefCell::new(BTreeSet::new());
+}
+
+/// Cancel a download which hasn't finished. Returns false if there is no
+/// such download.
+pub fn cancel(id: u32) -> bool {
+    control(id, |callback| unsafe {
+        (*callback).cancel.expect("cancel is a function")(callback)
+    })
+}
+
+pub fn pause(id: u32) -> bool {
+    let paused = control(id, |callback| unsafe {
+        (*callback).pause.expect("pause is a function")(callback)
+    });
+    if paused {
+        PAUSED.with(|p| p.borrow_mut().insert(id));
+    }
+    paused
+}
+
+pub fn resume(id: u32) -> bool {
+    let resumed = control(id, |callback| unsafe {
+        (*callback).resume.expect("resume is a function")(callback)
+    });
+    if resumed {
+        PAUSED.with(|p| p.borrow_mut().remove(&id));
+    }
+    resumed
+}
+
+/// Run `cef.downloads.cancel(id)`, `pause(id)` or `resume(id)` from a page,
+/// which may only control its own downloads
+pub fn page_command(browser: &Browser, command: &str, id: u32) {
+    let browser_id = browser_id(browser.as_raw());
+    let owned = CONTROLS.with(|c| c.borrow().get(&id).map(|c| c.browser_id) == Some(browser_id));
+    if !owned {
+        log::debug!(
+            "ignoring `{}` for download {} from another browser",
+            command,
+            id
+        );
+        return;
+    }
+    match command {
+        "cancel" => cancel(id),
+        "pause" => pause(id),
+        "resume" => resume(id),
+        _ => {
+            log::warn!("unrecognized download command `{}`", command);
+            false
+        }
+    };
+}
+
+fn control<F: FnOnce(*mut cef_download_item_callback_t)>(id: u32, f: F) -> bool {
+    let callback = CONTROLS.with(|c| c.borrow().get(&id).map(|c| c.callback));
+    match callback {
+        Some(callback) => {
+            f(callback);
+            true
+        }
+        None => {
+            log::debug!("no download {} to control", id);
+            false
+        }
+    }
+}
+
+#[repr(C)]
+pub struct CefDownloadHandler {
+    download_handler: cef_download_handler_t,
+    ref_count: AtomicUsize,
+    handler: Rc<dyn DownloadHandler>,
+}
+
+impl CefDownloadHandler {
+    pub fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+unsafe extern "C" fn on_before_download(
+    slf: *mut cef_download_handler_t,
+    browser: *mut cef_browser_t,
+    download_item: *mut cef_download_item_t,
+    suggested_name: *const cef_string_t,
+    callback: *mut cef_before_download_callback_t,
+) {
+    let handler = slf as *mut CefDownloadHandler;
+    let download = Download::from_raw(download_item);
+    let browser = Browser::from_raw(browser);
+    let handler = (*handler).handler.clone();
+
+    let (path, show_dialog) = match handler.destination(&browser, &download) {
+        DownloadDestination::Prompt => (strings::from_cef_string(suggested_name), true),
+        DownloadDestination::SaveTo(path) => (path.display().to_string(), false),
+        DownloadDestination::Deny => {
+            // the download is cancelled if we don't continue it
+            log::debug!("denied download of `{}`", download.url);
+            return;
+        }
+    };
+    log::debug!("downloading `{}` to `{}`", download.url, path);
+    (*callback).cont.expect("cont is a function")(
+        callback,
+        &strings::to_cef_string(&path),
+        show_dialog as c_int,
+    );
+}
+
+unsafe extern "C" fn on_download_updated(
+    slf: *mut cef_download_handler_t,
+    browser: *mut cef_browser_t,
+    download_item: *mut cef_download_item_t,
+    callback: *mut cef_download_item_callback_t,
+) {
+    let handler = slf as *mut CefDownloadHandler;
+    let download = Download::from_raw(download_item);
+    let browser = Browser::from_raw(browser);
+
+    if download.is_finished() {
+        CONTROLS.with(|c| c.borrow_mut().remove(&download.id));
+        PAUSED.with(|p| p.borrow_mut().remove(&download.id));
+    } else {
+        (*callback).base.add_ref.expect("add_ref is a function")(
+            callback as *mut cef_base_ref_counted_t,
+        );
+        let control = DownloadControl {
+            browser_id: browser_id(browser.as_raw()),
+            callback,
+        };
+        CONTROLS.with(|c| c.borrow_mut().insert(download.id, control));
+    }
+
+    let handler = (*handler).handler.clone();
+    handler.on_updated(&browser, &download);
+
+    match serde_json::to_string(&download) {
+        Ok(json) => browser.execute_javascript(&format!(
+            "if (typeof cef !== 'undefined' && cef.downloads) cef.downloads._update({});",
+            json
+        )),
+        Err(e) => log::warn!("failed to serialize download: {}", e),
+    }
+}
+
+fn browser_id(browser: *mut cef_browser_t) -> c_int {
+    unsafe {
+        (*browser)
+            .get_identifier
+            .expect("get_identifier is a function")(browser)
+    }
+}
+
+pub fn allocate(handler: Rc<dyn DownloadHandler>) -> *mut CefDownloadHandler {
+    let download_handler = CefDownloadHandler {
+        download_handler: cef_download_handler_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<CefDownloadHandler>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            on_before_download: Some(on_before_download),
+            on_download_updated: Some(on_download_updated),
+        },
+        ref_count: AtomicUsize::new(1),
+        handler,
+    };
+
+    Box::into_raw(Box::from(download_handler))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let download_handler = base as *mut CefDownloadHandler;
+    unsafe {
+        (*download_handler).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let download_handler = base as *mut CefDownloadHandler;
+    let count = unsafe { (*download_handler).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(download_handler);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let download_handler = base as *mut CefDownloadHandler;
+    let count = unsafe { (*download_handler).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let download_handler = base as *mut CefDownloadHandler;
+    let count = unsafe { (*download_handler).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
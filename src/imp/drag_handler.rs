@@ -0,0 +1,111 @@
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_drag_data_t, cef_drag_handler_t,
+    cef_drag_operations_mask_t, cef_draggable_region_t, cef_frame_t, size_t,
+};
+use super::windows::{self, WindowId};
+
+#[repr(C)]
+pub struct DragHandler {
+    drag_handler: cef_drag_handler_t,
+    ref_count: AtomicUsize,
+    window_id: WindowId,
+}
+
+impl DragHandler {
+    pub fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn on_drag_enter(
+    _slf: *mut cef_drag_handler_t,
+    _browser: *mut cef_browser_t,
+    _drag_data: *mut cef_drag_data_t,
+    _mask: cef_drag_operations_mask_t,
+) -> c_int {
+    0
+}
+
+/// Called with the page's `-webkit-app-region: drag` (and `no-drag`) areas,
+/// which move the window when dragged
+unsafe extern "C" fn on_draggable_regions_changed(
+    slf: *mut cef_drag_handler_t,
+    _browser: *mut cef_browser_t,
+    _frame: *mut cef_frame_t,
+    regions_count: size_t,
+    regions: *const cef_draggable_region_t,
+) {
+    let handler = slf as *mut DragHandler;
+    let window_id = (*handler).window_id;
+    if let Some(window) = windows::window(window_id) {
+        log::debug!("{} has {} draggable regions", window_id, regions_count);
+        (*window)
+            .set_draggable_regions
+            .expect("set_draggable_regions is a function")(window, regions_count, regions);
+    }
+}
+
+pub fn allocate(window_id: WindowId) -> *mut DragHandler {
+    let handler = DragHandler {
+        drag_handler: cef_drag_handler_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<DragHandler>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            on_drag_enter: Some(on_drag_enter),
+            on_draggable_regions_changed: Some(on_draggable_regions_changed),
+        },
+        ref_count: AtomicUsize::new(1),
+        window_id,
+    };
+
+    Box::into_raw(Box::from(handler))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let drag_handler = base as *mut DragHandler;
+    unsafe {
+        (*drag_handler).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let drag_handler = base as *mut DragHandler;
+    let count = unsafe { (*drag_handler).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(drag_handler);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let drag_handler = base as *mut DragHandler;
+    let count = unsafe { (*drag_handler).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let drag_handler = base as *mut DragHandler;
+    let count = unsafe { (*drag_handler).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,443 @@
+use std::path::{Path, PathBuf};
+
+use super::bindings::{
+    cef_file_dialog_mode_t, cef_file_dialog_mode_t_FILE_DIALOG_OPEN,
+    cef_file_dialog_mode_t_FILE_DIALOG_OPEN_FOLDER,
+    cef_file_dialog_mode_t_FILE_DIALOG_OPEN_MULTIPLE,
+    cef_file_dialog_mode_t_FILE_DIALOG_OVERWRITEPROMPT_FLAG,
+    cef_file_dialog_mode_t_FILE_DIALOG_SAVE, cef_file_dialog_mode_t_FILE_DIALOG_TYPE_MASK,
+    cef_list_value_t, cef_v8value_t,
+};
+use super::{strings, v8_values};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileDialogMode {
+    /// Pick a single existing file
+    Open,
+    /// Pick one or more existing files
+    OpenMultiple,
+    /// Pick an existing folder
+    OpenFolder,
+    /// Pick a file to save to, which may not exist yet
+    Save,
+}
+
+impl FileDialogMode {
+    pub fn to_cef(self) -> cef_file_dialog_mode_t {
+        match self {
+            FileDialogMode::Open => cef_file_dialog_mode_t_FILE_DIALOG_OPEN,
+            FileDialogMode::OpenMultiple => cef_file_dialog_mode_t_FILE_DIALOG_OPEN_MULTIPLE,
+            FileDialogMode::OpenFolder => cef_file_dialog_mode_t_FILE_DIALOG_OPEN_FOLDER,
+            FileDialogMode::Save => cef_file_dialog_mode_t_FILE_DIALOG_SAVE,
+        }
+    }
+
+    /// Convert from a CEF mode, ignoring any flags
+    #[allow(non_upper_case_globals)]
+    pub fn from_cef(mode: cef_file_dialog_mode_t) -> FileDialogMode {
+        match mode & cef_file_dialog_mode_t_FILE_DIALOG_TYPE_MASK {
+            cef_file_dialog_mode_t_FILE_DIALOG_OPEN_MULTIPLE => FileDialogMode::OpenMultiple,
+            cef_file_dialog_mode_t_FILE_DIALOG_OPEN_FOLDER => FileDialogMode::OpenFolder,
+            cef_file_dialog_mode_t_FILE_DIALOG_SAVE => FileDialogMode::Save,
+            _ => FileDialogMode::Open,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<FileDialogMode> {
+        match name {
+            "open" => Some(FileDialogMode::Open),
+            "openMultiple" => Some(FileDialogMode::OpenMultiple),
+            "openFolder" => Some(FileDialogMode::OpenFolder),
+            "save" => Some(FileDialogMode::Save),
+            _ => None,
+        }
+    }
+}
+
+/// A filter limiting which files are shown in a file dialog
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileFilter {
+    /// A MIME type such as `image/png` or `text/*`
+    MimeType(String),
+    /// A list of extensions (including the leading `.`), optionally with a
+    /// description to show in the dialog
+    Extensions {
+        description: Option<String>,
+        extensions: Vec<String>,
+    },
+}
+
+impl FileFilter {
+    pub fn mime_type<S: Into<String>>(mime_type: S) -> FileFilter {
+        FileFilter::MimeType(mime_type.into())
+    }
+
+    pub fn extensions<S: Into<String>>(description: S, extensions: &[&str]) -> FileFilter {
+        FileFilter::Extensions {
+            description: Some(description.into()),
+            extensions: extensions.iter().map(|e| normalize_extension(e)).collect(),
+        }
+    }
+
+    /// Parse a filter in CEF's format: a MIME type (`text/*`), extensions
+    /// (`.txt` or `.png;.jpg`) or a description followed by extensions
+    /// (`Images|.png;.jpg`)
+    pub fn parse(filter: &str) -> FileFilter {
+        let filter = filter.trim();
+        let parts: Vec<&str> = filter
+            .split(';')
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .collect();
+        if let Some(bar) = filter.find('|') {
+            let description = filter[..bar].trim();
+            FileFilter::Extensions {
+                description: if description.is_empty() {
+                    None
+                } else {
+                    Some(description.to_owned())
+                },
+                extensions: filter[bar + 1..]
+                    .split(';')
+                    .map(|e| e.trim())
+                    .filter(|e| !e.is_empty())
+                    .map(normalize_extension)
+                    .collect(),
+            }
+        } else if !parts.is_empty() && parts.iter().all(|part| part.starts_with('.')) {
+            FileFilter::Extensions {
+                description: None,
+                extensions: parts.into_iter().map(|part| part.to_owned()).collect(),
+            }
+        } else {
+            FileFilter::MimeType(filter.to_owned())
+        }
+    }
+
+    /// The filter in the format CEF expects
+    pub fn to_cef_filter(&self) -> String {
+        match self {
+            FileFilter::MimeType(mime_type) => mime_type.clone(),
+            FileFilter::Extensions {
+                description: None,
+                extensions,
+            } if extensions.len() == 1 => extensions[0].clone(),
+            FileFilter::Extensions {
+                description,
+                extensions,
+            } => {
+                let description = description.clone().unwrap_or_else(|| extensions.join(", "));
+                format!("{}|{}", description, extensions.join(";"))
+            }
+        }
+    }
+
+    /// The extension to append to a saved file's name, if the filter has one
+    pub fn default_extension(&self) -> Option<&str> {
+        match self {
+            FileFilter::MimeType(_) => None,
+            FileFilter::Extensions { extensions, .. } => extensions.first().map(|e| e.as_str()),
+        }
+    }
+
+    /// Whether `path` has one of the filter's extensions. MIME types can't be
+    /// checked without reading the file, so they match every path.
+    pub fn matches(&self, path: &Path) -> bool {
+        match self {
+            FileFilter::MimeType(_) => true,
+            FileFilter::Extensions { extensions, .. } => {
+                let file_name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                extensions
+                    .iter()
+                    .any(|e| file_name.ends_with(&e.to_lowercase()))
+            }
+        }
+    }
+
+    unsafe fn from_v8(value: *mut cef_v8value_t) -> Result<FileFilter, String> {
+        if let Some(filter) = v8_values::as_string(value) {
+            return Ok(FileFilter::parse(&filter));
+        }
+        if ((*value).is_object.expect("is_object is a function"))(value) != 1 {
+            return Err("filters must be strings or `{name, extensions}` objects".to_owned());
+        }
+
+        let description = v8_values::get(value, "name").and_then(|n| v8_values::as_string(n));
+        let extensions = v8_values::get(value, "extensions")
+            .and_then(|e| v8_values::as_array(e))
+            .ok_or_else(|| "filter extensions must be an array of strings".to_owned())?;
+        let extensions = extensions
+            .into_iter()
+            .map(|e| {
+                v8_values::as_string(e)
+                    .map(|e| normalize_extension(&e))
+                    .ok_or_else(|| "filter extensions must be an array of strings".to_owned())
+            })
+            .collect::<Result<Vec<String>, String>>()?;
+        if extensions.is_empty() {
+            return Err("filter extensions can't be empty".to_owned());
+        }
+        Ok(FileFilter::Extensions {
+            description,
+            extensions,
+        })
+    }
+}
+
+fn normalize_extension(extension: &str) -> String {
+    if extension.starts_with('.') {
+        extension.to_owned()
+    } else {
+        format!(".{}", extension)
+    }
+}
+
+/// Options for running a file dialog
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDialogOptions {
+    pub mode: FileDialogMode,
+    pub title: String,
+    /// The file name (or path) the dialog starts with
+    pub default_file_name: String,
+    pub filters: Vec<FileFilter>,
+    /// The index of the filter which is selected when the dialog opens
+    pub selected_filter: usize,
+    /// When saving, ask the user to confirm overwriting an existing file
+    pub overwrite_prompt: bool,
+}
+
+impl Default for FileDialogOptions {
+    fn default() -> FileDialogOptions {
+        FileDialogOptions {
+            mode: FileDialogMode::Open,
+            title: String::new(),
+            default_file_name: String::new(),
+            filters: Vec::new(),
+            selected_filter: 0,
+            overwrite_prompt: false,
+        }
+    }
+}
+
+impl FileDialogOptions {
+    /// The mode (including flags) to pass to CEF
+    pub fn cef_mode(&self) -> cef_file_dialog_mode_t {
+        let mut mode = self.mode.to_cef();
+        if self.overwrite_prompt && self.mode == FileDialogMode::Save {
+            mode |= cef_file_dialog_mode_t_FILE_DIALOG_OVERWRITEPROMPT_FLAG;
+        }
+        mode
+    }
+
+    /// Serialize the options into an IPC argument list, starting at `index`.
+    /// The list is resized to fit.
+    pub unsafe fn write_to_list(&self, args: *mut cef_list_value_t, index: usize) {
+        let set_int = (*args).set_int.expect("set_int is a function");
+        let set_string = (*args).set_string.expect("set_string is a function");
+        let i = index as u64;
+
+        ((*args).set_size.expect("set_size is a function"))(
+            args,
+            i + 5 + self.filters.len() as u64,
+        );
+        set_int(args, i, self.cef_mode() as i32);
+        set_string(args, i + 1, &strings::to_cef_string(&self.title));
+        set_string(
+            args,
+            i + 2,
+            &strings::to_cef_string(&self.default_file_name),
+        );
+        set_int(args, i + 3, self.selected_filter as i32);
+        set_int(args, i + 4, self.filters.len() as i32);
+        for (f, filter) in self.filters.iter().enumerate() {
+            set_string(
+                args,
+                i + 5 + f as u64,
+                &strings::to_cef_string(&filter.to_cef_filter()),
+            );
+        }
+    }
+
+    /// Deserialize options written with `write_to_list`
+    pub unsafe fn read_from_list(args: *mut cef_list_value_t, index: usize) -> FileDialogOptions {
+        let size = ((*args).get_size.expect("get_size is a function"))(args) as usize;
+        if size < index + 5 {
+            log::warn!("file dialog options missing from IPC message, using defaults");
+            return FileDialogOptions::default();
+        }
+
+        let get_int = (*args).get_int.expect("get_int is a function");
+        let get_string = (*args).get_string.expect("get_string is a function");
+        let i = index as u64;
+
+        let filter_count = (get_int(args, i + 4).max(0) as usize).min(size - index - 5);
+        let filters = (0..filter_count)
+            .map(|f| FileFilter::parse(&strings::from_userfree(get_string(args, i + 5 + f as u64))))
+            .collect();
+
+        let mode = get_int(args, i) as cef_file_dialog_mode_t;
+        FileDialogOptions {
+            mode: FileDialogMode::from_cef(mode),
+            title: strings::from_userfree(get_string(args, i + 1)),
+            default_file_name: strings::from_userfree(get_string(args, i + 2)),
+            filters,
+            selected_filter: get_int(args, i + 3).max(0) as usize,
+            overwrite_prompt: mode & cef_file_dialog_mode_t_FILE_DIALOG_OVERWRITEPROMPT_FLAG != 0,
+        }
+    }
+
+    /// Parse options from a JS object such as:
+    ///
+    /// ```js
+    /// {
+    ///     mode: "save", // or "open", "openMultiple", "openFolder"
+    ///     title: "Save Image",
+    ///     defaultFileName: "image.png",
+    ///     filters: [{ name: "Images", extensions: [".png", ".jpg"] }, "text/*"],
+    ///     selectedFilter: 0,
+    ///     overwritePrompt: true,
+    /// }
+    /// ```
+    ///
+    /// `filters` may also be a single filter string (such as
+    /// `"Text Files|.txt"`). Missing keys take on their default values.
+    pub unsafe fn from_v8(object: *mut cef_v8value_t) -> Result<FileDialogOptions, String> {
+        let mut options = FileDialogOptions::default();
+        if v8_values::is_nullish(object) {
+            return Ok(options);
+        }
+        if ((*object).is_object.expect("is_object is a function"))(object) != 1 {
+            return Err("file dialog options must be an object".to_owned());
+        }
+
+        if let Some(mode) = v8_values::get(object, "mode") {
+            options.mode = v8_values::as_string(mode)
+                .and_then(|m| FileDialogMode::from_name(&m))
+                .ok_or_else(|| {
+                    "mode must be `open`, `openMultiple`, `openFolder` or `save`".to_owned()
+                })?;
+        }
+
+        if let Some(title) = v8_values::get(object, "title") {
+            options.title = v8_values::as_string(title).unwrap_or_default();
+        }
+
+        if let Some(default_file_name) = v8_values::get(object, "defaultFileName") {
+            options.default_file_name = v8_values::as_string(default_file_name).unwrap_or_default();
+        }
+
+        if let Some(filters) = v8_values::get(object, "filters") {
+            options.filters = match v8_values::as_array(filters) {
+                Some(filters) => filters
+                    .into_iter()
+                    .map(|f| FileFilter::from_v8(f))
+                    .collect::<Result<Vec<FileFilter>, String>>()?,
+                None => vec![FileFilter::from_v8(filters)?],
+            };
+        }
+
+        if let Some(selected_filter) = v8_values::get(object, "selectedFilter") {
+            match v8_values::as_number(selected_filter) {
+                Some(selected) if selected >= 0.0 => options.selected_filter = selected as usize,
+                _ => return Err("selectedFilter must be a number >= 0".to_owned()),
+            }
+        }
+
+        if let Some(overwrite_prompt) = v8_values::get(object, "overwritePrompt") {
+            options.overwrite_prompt = v8_values::as_bool(overwrite_prompt).unwrap_or(false);
+        }
+
+        Ok(options)
+    }
+}
+
+/// The outcome of a file dialog which wasn't cancelled
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDialogResult {
+    /// The selected paths; this always has at least one entry, and only has
+    /// more than one for `FileDialogMode::OpenMultiple`
+    pub paths: Vec<PathBuf>,
+    /// The index of the filter the user had selected
+    pub filter_index: usize,
+    /// The filter the user had selected, if any filters were given
+    pub filter: Option<FileFilter>,
+}
+
+impl FileDialogResult {
+    /// The first selected path
+    pub fn path(&self) -> &Path {
+        &self.paths[0]
+    }
+
+    /// The first selected path, with the selected filter's extension appended
+    /// if the path doesn't already end in one of the filter's extensions
+    pub fn path_with_extension(&self) -> PathBuf {
+        let path = self.path();
+        let extensions = match &self.filter {
+            Some(FileFilter::Extensions { extensions, .. }) => extensions,
+            _ => return path.to_path_buf(),
+        };
+        if self
+            .filter
+            .as_ref()
+            .map(|f| f.matches(path))
+            .unwrap_or(true)
+        {
+            return path.to_path_buf();
+        }
+
+        match extensions.first() {
+            Some(extension) => {
+                let mut path = path.as_os_str().to_owned();
+                path.push(extension);
+                PathBuf::from(path)
+            }
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extensions(description: Option<&str>, extensions: &[&str]) -> FileFilter {
+        FileFilter::Extensions {
+            description: description.map(|d| d.to_owned()),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_mime_types() {
+        assert_eq!(
+            FileFilter::parse(" image/* "),
+            FileFilter::mime_type("image/*")
+        );
+    }
+
+    #[test]
+    fn parses_bare_extensions() {
+        assert_eq!(FileFilter::parse(".txt"), extensions(None, &[".txt"]));
+        assert_eq!(
+            FileFilter::parse(".png;.jpg"),
+            extensions(None, &[".png", ".jpg"])
+        );
+        assert_eq!(
+            FileFilter::parse(" .png ; .jpg ;"),
+            extensions(None, &[".png", ".jpg"])
+        );
+    }
+
+    #[test]
+    fn parses_described_extensions() {
+        assert_eq!(
+            FileFilter::parse("Images|.png;jpg"),
+            extensions(Some("Images"), &[".png", ".jpg"])
+        );
+        assert_eq!(FileFilter::parse("|.csv"), extensions(None, &[".csv"]));
+    }
+}
@@ -0,0 +1,472 @@
+use std::cell::Cell;
+use std::os::raw::c_int;
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_host_t, cef_event_flags_t_EVENTFLAG_ALT_DOWN,
+    cef_event_flags_t_EVENTFLAG_COMMAND_DOWN, cef_event_flags_t_EVENTFLAG_CONTROL_DOWN,
+    cef_event_flags_t_EVENTFLAG_LEFT_MOUSE_BUTTON, cef_event_flags_t_EVENTFLAG_MIDDLE_MOUSE_BUTTON,
+    cef_event_flags_t_EVENTFLAG_RIGHT_MOUSE_BUTTON, cef_event_flags_t_EVENTFLAG_SHIFT_DOWN,
+    cef_key_event_t, cef_key_event_type_t, cef_key_event_type_t_KEYEVENT_CHAR,
+    cef_key_event_type_t_KEYEVENT_KEYUP, cef_key_event_type_t_KEYEVENT_RAWKEYDOWN,
+    cef_mouse_button_type_t, cef_mouse_button_type_t_MBT_LEFT, cef_mouse_button_type_t_MBT_MIDDLE,
+    cef_mouse_button_type_t_MBT_RIGHT, cef_mouse_event_t,
+    cef_pointer_type_t_CEF_POINTER_TYPE_TOUCH, cef_range_t, cef_touch_event_t,
+    cef_touch_event_type_t, cef_touch_event_type_t_CEF_TET_CANCELLED,
+    cef_touch_event_type_t_CEF_TET_MOVED, cef_touch_event_type_t_CEF_TET_PRESSED,
+    cef_touch_event_type_t_CEF_TET_RELEASED,
+};
+use super::browser::Browser;
+use super::shortcuts::KeyCombo;
+use super::strings;
+
+/// The modifier keys held down during a mouse or touch event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    /// The command key on macOS, or the Windows / super key elsewhere
+    pub command: bool,
+}
+
+impl Modifiers {
+    fn flags(&self) -> u32 {
+        let mut flags = 0;
+        for (pressed, flag) in &[
+            (self.ctrl, cef_event_flags_t_EVENTFLAG_CONTROL_DOWN),
+            (self.shift, cef_event_flags_t_EVENTFLAG_SHIFT_DOWN),
+            (self.alt, cef_event_flags_t_EVENTFLAG_ALT_DOWN),
+            (self.command, cef_event_flags_t_EVENTFLAG_COMMAND_DOWN),
+        ] {
+            if *pressed {
+                flags |= *flag as u32;
+            }
+        }
+        flags
+    }
+}
+
+impl From<&KeyCombo> for Modifiers {
+    fn from(combo: &KeyCombo) -> Modifiers {
+        Modifiers {
+            ctrl: combo.ctrl,
+            shift: combo.shift,
+            alt: combo.alt,
+            command: combo.command,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+impl MouseButton {
+    fn to_cef(self) -> cef_mouse_button_type_t {
+        match self {
+            MouseButton::Left => cef_mouse_button_type_t_MBT_LEFT,
+            MouseButton::Middle => cef_mouse_button_type_t_MBT_MIDDLE,
+            MouseButton::Right => cef_mouse_button_type_t_MBT_RIGHT,
+        }
+    }
+
+    /// The flag marking the button as held during mouse moves
+    fn flag(self) -> u32 {
+        (match self {
+            MouseButton::Left => cef_event_flags_t_EVENTFLAG_LEFT_MOUSE_BUTTON,
+            MouseButton::Middle => cef_event_flags_t_EVENTFLAG_MIDDLE_MOUSE_BUTTON,
+            MouseButton::Right => cef_event_flags_t_EVENTFLAG_RIGHT_MOUSE_BUTTON,
+        }) as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchPhase {
+    Pressed,
+    Moved,
+    Released,
+    Cancelled,
+}
+
+impl TouchPhase {
+    fn to_cef(self) -> cef_touch_event_type_t {
+        match self {
+            TouchPhase::Pressed => cef_touch_event_type_t_CEF_TET_PRESSED,
+            TouchPhase::Moved => cef_touch_event_type_t_CEF_TET_MOVED,
+            TouchPhase::Released => cef_touch_event_type_t_CEF_TET_RELEASED,
+            TouchPhase::Cancelled => cef_touch_event_type_t_CEF_TET_CANCELLED,
+        }
+    }
+}
+
+/// The character a key combination types on a US keyboard, if any
+fn key_char(combo: &KeyCombo) -> Option<u16> {
+    if combo.ctrl || combo.alt || combo.command {
+        return None;
+    }
+    let (plain, shifted) = match combo.key_code {
+        code @ 0x41..=0x5A => ((code as u8).to_ascii_lowercase(), code as u8),
+        code @ 0x30..=0x39 => (code as u8, b")!@#$%^&*("[(code - 0x30) as usize]),
+        0x20 => (b' ', b' '),
+        0x0D => (b'\r', b'\r'),
+        0x09 => (b'\t', b'\t'),
+        0xBB => (b'=', b'+'),
+        0xBC => (b',', b'<'),
+        0xBD => (b'-', b'_'),
+        0xBE => (b'.', b'>'),
+        0xBF => (b'/', b'?'),
+        _ => return None,
+    };
+    let character = if combo.shift { shifted } else { plain };
+    Some(character as u16)
+}
+
+/// A range CEF treats as "no range"
+const NO_RANGE: cef_range_t = cef_range_t {
+    from: std::u32::MAX as _,
+    to: std::u32::MAX as _,
+};
+
+/// Sends input to a browser as though it came from the user, for windowless
+/// browsers (which have no window to get input from) and for driving pages
+/// in automated tests. Mouse buttons pressed with `mouse_down` are treated as
+/// held during mouse moves until `mouse_up`, so drags work.
+pub struct BrowserInput {
+    browser: Browser,
+    /// The browser's host, which we hold a reference to until dropped
+    host: *mut cef_browser_host_t,
+    held_buttons: Cell<u32>,
+}
+
+impl BrowserInput {
+    pub fn new(browser: &Browser) -> BrowserInput {
+        let raw = browser.as_raw();
+        let host = unsafe { (*raw).get_host.expect("get_host is a function")(raw) };
+        BrowserInput {
+            browser: browser.clone(),
+            host,
+            held_buttons: Cell::new(0),
+        }
+    }
+
+    fn host(&self) -> *mut cef_browser_host_t {
+        self.host
+    }
+
+    fn mouse_event(&self, x: i32, y: i32, modifiers: Modifiers) -> cef_mouse_event_t {
+        cef_mouse_event_t {
+            x,
+            y,
+            modifiers: modifiers.flags() | self.held_buttons.get(),
+        }
+    }
+
+    /// Give the page keyboard focus, or take it away
+    pub fn focus(&self, focused: bool) {
+        let host = self.host();
+        unsafe { (*host).set_focus.expect("set_focus is a function")(host, focused as c_int) };
+    }
+
+    pub fn mouse_move(&self, x: i32, y: i32, modifiers: Modifiers) {
+        let host = self.host();
+        let event = self.mouse_event(x, y, modifiers);
+        unsafe {
+            (*host)
+                .send_mouse_move_event
+                .expect("send_mouse_move_event is a function")(host, &event, 0)
+        };
+    }
+
+    /// Tell the page the mouse has left the view
+    pub fn mouse_leave(&self, x: i32, y: i32) {
+        let host = self.host();
+        let event = self.mouse_event(x, y, Modifiers::default());
+        unsafe {
+            (*host)
+                .send_mouse_move_event
+                .expect("send_mouse_move_event is a function")(host, &event, 1)
+        };
+    }
+
+    /// Press `button`. `click_count` is 1 for a single click, 2 for the
+    /// second press of a double click and so on.
+    pub fn mouse_down(
+        &self,
+        x: i32,
+        y: i32,
+        button: MouseButton,
+        click_count: i32,
+        modifiers: Modifiers,
+    ) {
+        self.held_buttons
+            .set(self.held_buttons.get() | button.flag());
+        self.mouse_click(x, y, button, false, click_count, modifiers);
+    }
+
+    pub fn mouse_up(
+        &self,
+        x: i32,
+        y: i32,
+        button: MouseButton,
+        click_count: i32,
+        modifiers: Modifiers,
+    ) {
+        self.mouse_click(x, y, button, true, click_count, modifiers);
+        self.held_buttons
+            .set(self.held_buttons.get() & !button.flag());
+    }
+
+    fn mouse_click(
+        &self,
+        x: i32,
+        y: i32,
+        button: MouseButton,
+        mouse_up: bool,
+        click_count: i32,
+        modifiers: Modifiers,
+    ) {
+        let host = self.host();
+        let event = self.mouse_event(x, y, modifiers);
+        unsafe {
+            (*host)
+                .send_mouse_click_event
+                .expect("send_mouse_click_event is a function")(
+                host,
+                &event,
+                button.to_cef(),
+                mouse_up as c_int,
+                click_count,
+            )
+        };
+    }
+
+    /// Move to `(x, y)` and click `button` there
+    pub fn click(&self, x: i32, y: i32, button: MouseButton) {
+        self.mouse_move(x, y, Modifiers::default());
+        self.mouse_down(x, y, button, 1, Modifiers::default());
+        self.mouse_up(x, y, button, 1, Modifiers::default());
+    }
+
+    /// Scroll by `(delta_x, delta_y)` pixels with the mouse at `(x, y)`;
+    /// positive `delta_y` scrolls up
+    pub fn mouse_wheel(&self, x: i32, y: i32, delta_x: i32, delta_y: i32, modifiers: Modifiers) {
+        let host = self.host();
+        let event = self.mouse_event(x, y, modifiers);
+        unsafe {
+            (*host)
+                .send_mouse_wheel_event
+                .expect("send_mouse_wheel_event is a function")(
+                host, &event, delta_x, delta_y
+            )
+        };
+    }
+
+    fn key_event(&self, type_: cef_key_event_type_t, key_code: i32, character: u16, flags: u32) {
+        let host = self.host();
+        let event = cef_key_event_t {
+            type_,
+            modifiers: flags,
+            windows_key_code: key_code,
+            character,
+            unmodified_character: character,
+            ..Default::default()
+        };
+        unsafe {
+            (*host)
+                .send_key_event
+                .expect("send_key_event is a function")(host, &event)
+        };
+    }
+
+    /// Press the keys in `combo`, such as `Ctrl+A` or `Enter`, written as for
+    /// `KeyCombo::parse`
+    pub fn key_down(&self, combo: &str) -> Result<(), String> {
+        let combo = KeyCombo::parse(combo)?;
+        let flags = Modifiers::from(&combo).flags();
+        self.key_event(
+            cef_key_event_type_t_KEYEVENT_RAWKEYDOWN,
+            combo.key_code,
+            0,
+            flags,
+        );
+        if let Some(character) = key_char(&combo) {
+            // the character event is what types text into the page
+            self.key_event(
+                cef_key_event_type_t_KEYEVENT_CHAR,
+                character as i32,
+                character,
+                flags,
+            );
+        }
+        Ok(())
+    }
+
+    pub fn key_up(&self, combo: &str) -> Result<(), String> {
+        let combo = KeyCombo::parse(combo)?;
+        self.key_event(
+            cef_key_event_type_t_KEYEVENT_KEYUP,
+            combo.key_code,
+            0,
+            Modifiers::from(&combo).flags(),
+        );
+        Ok(())
+    }
+
+    /// Press and release the keys in `combo`
+    pub fn press_key(&self, combo: &str) -> Result<(), String> {
+        self.key_down(combo)?;
+        self.key_up(combo)
+    }
+
+    /// Type `text` into the focused element, one character at a time. Unlike
+    /// `press_key`, this works for any character, but only sends character
+    /// events, so pages watching `keydown` won't see it.
+    pub fn type_text(&self, text: &str) {
+        for character in text.encode_utf16() {
+            self.key_event(
+                cef_key_event_type_t_KEYEVENT_CHAR,
+                character as i32,
+                character,
+                0,
+            );
+        }
+    }
+
+    /// Send a touch event for the finger `id`
+    pub fn touch(&self, id: i32, x: f32, y: f32, phase: TouchPhase, modifiers: Modifiers) {
+        let host = self.host();
+        let event = cef_touch_event_t {
+            id,
+            x,
+            y,
+            type_: phase.to_cef(),
+            modifiers: modifiers.flags(),
+            pointer_type: cef_pointer_type_t_CEF_POINTER_TYPE_TOUCH,
+            ..Default::default()
+        };
+        unsafe {
+            (*host)
+                .send_touch_event
+                .expect("send_touch_event is a function")(host, &event)
+        };
+    }
+
+    /// Show `text` as the input method's composition (the text being composed
+    /// but not yet committed), with the cursor at `cursor` UTF-16 code units
+    /// into it
+    pub fn ime_set_composition(&self, text: &str, cursor: u32) {
+        let host = self.host();
+        let cef_text = strings::to_cef_string(text);
+        let selection = cef_range_t {
+            from: cursor as _,
+            to: cursor as _,
+        };
+        unsafe {
+            (*host)
+                .ime_set_composition
+                .expect("ime_set_composition is a function")(
+                host,
+                &cef_text,
+                0,
+                std::ptr::null(),
+                &NO_RANGE,
+                &selection,
+            )
+        };
+    }
+
+    /// Commit `text` from the input method, replacing any composition
+    pub fn ime_commit_text(&self, text: &str) {
+        let host = self.host();
+        let cef_text = strings::to_cef_string(text);
+        unsafe {
+            (*host)
+                .ime_commit_text
+                .expect("ime_commit_text is a function")(host, &cef_text, &NO_RANGE, 0)
+        };
+    }
+
+    /// Commit the current composition as it is
+    pub fn ime_finish_composing_text(&self, keep_selection: bool) {
+        let host = self.host();
+        unsafe {
+            (*host)
+                .ime_finish_composing_text
+                .expect("ime_finish_composing_text is a function")(
+                host, keep_selection as c_int
+            )
+        };
+    }
+
+    /// Throw away the current composition
+    pub fn ime_cancel_composition(&self) {
+        let host = self.host();
+        unsafe {
+            (*host)
+                .ime_cancel_composition
+                .expect("ime_cancel_composition is a function")(host)
+        };
+    }
+}
+
+impl Drop for BrowserInput {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.host).base.release.expect("release is a function")(
+                self.host as *mut cef_base_ref_counted_t,
+            );
+        }
+    }
+}
+
+impl std::fmt::Debug for BrowserInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BrowserInput")
+            .field("browser", &self.browser)
+            .field("held_buttons", &self.held_buttons.get())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(combo: &str) -> Option<char> {
+        key_char(&KeyCombo::parse(combo).unwrap()).map(|c| c as u8 as char)
+    }
+
+    #[test]
+    fn types_letters_and_digits() {
+        assert_eq!(key("A"), Some('a'));
+        assert_eq!(key("Shift+A"), Some('A'));
+        assert_eq!(key("7"), Some('7'));
+        assert_eq!(key("Shift+7"), Some('&'));
+        assert_eq!(key("Shift+0"), Some(')'));
+    }
+
+    #[test]
+    fn types_whitespace_and_punctuation() {
+        assert_eq!(key("Space"), Some(' '));
+        assert_eq!(key("Enter"), Some('\r'));
+        assert_eq!(key("Tab"), Some('\t'));
+        assert_eq!(key("="), Some('='));
+        assert_eq!(key("Shift+="), Some('+'));
+        assert_eq!(key("Shift+Comma"), Some('<'));
+        assert_eq!(key("Minus"), Some('-'));
+        assert_eq!(key("Shift+/"), Some('?'));
+    }
+
+    #[test]
+    fn doesnt_type_with_modifiers_or_for_other_keys() {
+        assert_eq!(key("Ctrl+A"), None);
+        assert_eq!(key("Alt+1"), None);
+        assert_eq!(key("Cmd+Space"), None);
+        assert_eq!(key("F5"), None);
+        assert_eq!(key("Left"), None);
+        assert_eq!(key("Escape"), None);
+    }
+}
@@ -0,0 +1,423 @@
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_jsdialog_callback_t, cef_jsdialog_handler_t,
+    cef_jsdialog_type_t, cef_jsdialog_type_t_JSDIALOGTYPE_CONFIRM,
+    cef_jsdialog_type_t_JSDIALOGTYPE_PROMPT, cef_string_t,
+};
+use super::browser::Browser;
+use super::windows;
+use super::{modal_overlay, strings};
+
+/// The kind of dialog a page asked for
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsDialogKind {
+    /// `alert(message)`
+    Alert,
+    /// `confirm(message)`
+    Confirm,
+    /// `prompt(message, default_text)`
+    Prompt { default_text: String },
+    /// The page's `beforeunload` handler asked the user to confirm leaving
+    BeforeUnload { is_reload: bool },
+}
+
+/// A dialog requested by a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsDialog {
+    pub kind: JsDialogKind,
+    /// The URL of the page showing the dialog (empty for `beforeunload`)
+    pub origin_url: String,
+    pub message: String,
+}
+
+impl JsDialog {
+    /// Answer the dialog the way clicking "OK" would
+    pub fn accept(&self, callback: JsDialogCallback) {
+        match &self.kind {
+            JsDialogKind::Prompt { default_text } => callback.accept(Some(default_text)),
+            _ => callback.accept(None),
+        }
+    }
+}
+
+/// Handles `alert()`, `confirm()`, `prompt()` and `beforeunload` dialogs
+/// instead of showing CEF's default dialogs.
+pub trait JsDialogHandler {
+    /// Answer `dialog` by calling `callback.accept` or `callback.deny`, either
+    /// immediately or later on; the page is blocked until one of them is
+    /// called. Return false to show CEF's default dialog instead (in which
+    /// case `callback` must not be used).
+    fn on_dialog(&self, browser: &Browser, dialog: &JsDialog, callback: JsDialogCallback) -> bool;
+
+    /// Called when any pending dialogs should be dismissed, for example
+    /// because the page navigated away
+    fn on_reset(&self, _browser: &Browser) {}
+}
+
+/// Used to answer a dialog handled by a `JsDialogHandler`
+pub struct JsDialogCallback {
+    callback: *mut cef_jsdialog_callback_t,
+}
+
+impl JsDialogCallback {
+    unsafe fn from_raw(callback: *mut cef_jsdialog_callback_t) -> Self {
+        (*callback).base.add_ref.expect("add_ref is a function")(
+            callback as *mut cef_base_ref_counted_t,
+        );
+        JsDialogCallback { callback }
+    }
+
+    /// Click "OK", with `user_input` as the answer to a `prompt()`
+    pub fn accept(self, user_input: Option<&str>) {
+        self.cont(true, user_input.unwrap_or_default());
+    }
+
+    /// Click "Cancel" (or stay on the page, for `beforeunload`)
+    pub fn deny(self) {
+        self.cont(false, "");
+    }
+
+    fn cont(self, success: bool, user_input: &str) {
+        let cef_user_input = strings::to_cef_string(user_input);
+        unsafe {
+            (*self.callback).cont.expect("cont is a function")(
+                self.callback,
+                success as c_int,
+                &cef_user_input,
+            );
+        }
+    }
+}
+
+impl Drop for JsDialogCallback {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.callback)
+                .base
+                .release
+                .expect("release is a function")(
+                self.callback as *mut cef_base_ref_counted_t
+            );
+        }
+    }
+}
+
+#[repr(C)]
+pub struct CefJsDialogHandler {
+    jsdialog_handler: cef_jsdialog_handler_t,
+    ref_count: AtomicUsize,
+    handler: Rc<dyn JsDialogHandler>,
+}
+
+impl CefJsDialogHandler {
+    pub fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+unsafe fn handle_dialog(
+    slf: *mut cef_jsdialog_handler_t,
+    browser: *mut cef_browser_t,
+    dialog: JsDialog,
+    callback: *mut cef_jsdialog_callback_t,
+) -> c_int {
+    log::debug!("page requested dialog: {:?}", dialog);
+    let handler = (*(slf as *mut CefJsDialogHandler)).handler.clone();
+    let browser = Browser::from_raw(browser);
+    let callback = JsDialogCallback::from_raw(callback);
+    handler.on_dialog(&browser, &dialog, callback) as c_int
+}
+
+#[allow(non_upper_case_globals)]
+unsafe extern "C" fn on_jsdialog(
+    slf: *mut cef_jsdialog_handler_t,
+    browser: *mut cef_browser_t,
+    origin_url: *const cef_string_t,
+    dialog_type: cef_jsdialog_type_t,
+    message_text: *const cef_string_t,
+    default_prompt_text: *const cef_string_t,
+    callback: *mut cef_jsdialog_callback_t,
+    _suppress_message: *mut c_int,
+) -> c_int {
+    let kind = match dialog_type {
+        cef_jsdialog_type_t_JSDIALOGTYPE_CONFIRM => JsDialogKind::Confirm,
+        cef_jsdialog_type_t_JSDIALOGTYPE_PROMPT => JsDialogKind::Prompt {
+            default_text: strings::from_cef_string(default_prompt_text),
+        },
+        _ => JsDialogKind::Alert,
+    };
+    let dialog = JsDialog {
+        kind,
+        origin_url: strings::from_cef_string(origin_url),
+        message: strings::from_cef_string(message_text),
+    };
+    handle_dialog(slf, browser, dialog, callback)
+}
+
+unsafe extern "C" fn on_before_unload_dialog(
+    slf: *mut cef_jsdialog_handler_t,
+    browser: *mut cef_browser_t,
+    message_text: *const cef_string_t,
+    is_reload: c_int,
+    callback: *mut cef_jsdialog_callback_t,
+) -> c_int {
+    let dialog = JsDialog {
+        kind: JsDialogKind::BeforeUnload {
+            is_reload: is_reload == 1,
+        },
+        origin_url: String::new(),
+        message: strings::from_cef_string(message_text),
+    };
+    handle_dialog(slf, browser, dialog, callback)
+}
+
+unsafe extern "C" fn on_reset_dialog_state(
+    slf: *mut cef_jsdialog_handler_t,
+    browser: *mut cef_browser_t,
+) {
+    let handler = (*(slf as *mut CefJsDialogHandler)).handler.clone();
+    handler.on_reset(&Browser::from_raw(browser));
+}
+
+pub fn allocate(handler: Rc<dyn JsDialogHandler>) -> *mut CefJsDialogHandler {
+    let jsdialog_handler = CefJsDialogHandler {
+        jsdialog_handler: cef_jsdialog_handler_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<CefJsDialogHandler>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            on_jsdialog: Some(on_jsdialog),
+            on_before_unload_dialog: Some(on_before_unload_dialog),
+            on_reset_dialog_state: Some(on_reset_dialog_state),
+            on_dialog_closed: None,
+        },
+        ref_count: AtomicUsize::new(1),
+        handler,
+    };
+
+    Box::into_raw(Box::from(jsdialog_handler))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let jsdialog_handler = base as *mut CefJsDialogHandler;
+    unsafe {
+        (*jsdialog_handler).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let jsdialog_handler = base as *mut CefJsDialogHandler;
+    let count = unsafe { (*jsdialog_handler).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(jsdialog_handler);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let jsdialog_handler = base as *mut CefJsDialogHandler;
+    let count = unsafe { (*jsdialog_handler).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let jsdialog_handler = base as *mut CefJsDialogHandler;
+    let count = unsafe { (*jsdialog_handler).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Accepts every dialog: alerts are dismissed, `confirm()` returns true,
+/// `prompt()` returns its default text and `beforeunload` lets the page go
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AutoAccept;
+
+impl JsDialogHandler for AutoAccept {
+    fn on_dialog(&self, _browser: &Browser, dialog: &JsDialog, callback: JsDialogCallback) -> bool {
+        dialog.accept(callback);
+        true
+    }
+}
+
+/// Denies every dialog: alerts are dismissed, `confirm()` returns false,
+/// `prompt()` returns null and `beforeunload` keeps the page open
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DenyAll;
+
+impl JsDialogHandler for DenyAll {
+    fn on_dialog(&self, _browser: &Browser, dialog: &JsDialog, callback: JsDialogCallback) -> bool {
+        match dialog.kind {
+            JsDialogKind::Alert => callback.accept(None),
+            _ => callback.deny(),
+        }
+        true
+    }
+}
+
+/// Logs every dialog and answers it without involving the user: alerts are
+/// dismissed, `confirm()` returns false, `prompt()` returns null and
+/// `beforeunload` lets the page go. This never blocks, which makes it
+/// suitable for headless browsers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogOnly;
+
+impl JsDialogHandler for LogOnly {
+    fn on_dialog(&self, _browser: &Browser, dialog: &JsDialog, callback: JsDialogCallback) -> bool {
+        log::info!("[DIALOG] {:?}: {}", dialog.kind, dialog.message);
+        match dialog.kind {
+            JsDialogKind::Alert | JsDialogKind::BeforeUnload { .. } => callback.accept(None),
+            _ => callback.deny(),
+        }
+        true
+    }
+}
+
+/// Shows dialogs as a modal drawn over the page inside its window, rather than
+/// as a native window. The page stays blocked until the user answers with the
+/// modal's buttons (and text field, for `prompt()`).
+///
+/// Chromium doesn't run any of the page's code while a dialog is pending, so
+/// the modal is a separate page overlaid on the window. Dialogs from browsers
+/// which aren't shown in one of our windows, such as windowless ones, are
+/// answered by `fallback` instead (`LogOnly` by default), so they never hang.
+pub struct InPageModal {
+    fallback: Rc<dyn JsDialogHandler>,
+}
+
+impl InPageModal {
+    pub fn new() -> InPageModal {
+        InPageModal {
+            fallback: Rc::new(LogOnly),
+        }
+    }
+
+    /// Answer dialogs which can't be shown in a window with `handler`
+    pub fn with_fallback(handler: Rc<dyn JsDialogHandler>) -> InPageModal {
+        InPageModal { fallback: handler }
+    }
+}
+
+impl Default for InPageModal {
+    fn default() -> InPageModal {
+        InPageModal::new()
+    }
+}
+
+const MODAL_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<style>
+    html, body { margin: 0; height: 100%; }
+    body { display: flex; align-items: center; justify-content: center; background: rgba(0, 0, 0, 0.4); font-family: sans-serif; }
+    #box { background: #fff; color: #000; padding: 1.5em; border-radius: 6px; min-width: 20em; max-width: 80%; box-shadow: 0 4px 24px rgba(0, 0, 0, 0.3); }
+    #message { margin: 0 0 1em 0; white-space: pre-wrap; }
+    #input { display: block; box-sizing: border-box; width: 100%; margin-bottom: 1em; }
+    #buttons { text-align: right; }
+    button { margin-left: 0.5em; }
+</style>
+</head>
+<body>
+<div id="box" role="alertdialog" aria-modal="true" aria-describedby="message">
+    <p id="message"></p>
+    <input id="input" hidden>
+    <div id="buttons"><button id="cancel" hidden>Cancel</button><button id="ok">OK</button></div>
+</div>
+<script>
+    (function(kind, message, defaultText) {
+        var input = document.getElementById("input");
+        var ok = document.getElementById("ok");
+        var cancel = document.getElementById("cancel");
+        document.getElementById("message").textContent = message;
+        if (kind === "prompt") {
+            input.hidden = false;
+            input.value = defaultText;
+        }
+        if (kind !== "alert") {
+            cancel.hidden = false;
+        }
+        if (kind === "beforeunload") {
+            ok.textContent = "Leave";
+            cancel.textContent = "Stay";
+        }
+
+        var answered = false;
+        function answer(accept) {
+            if (answered) return;
+            answered = true;
+            cef._answerDialog(accept, input.value);
+        }
+        ok.onclick = function() { answer(true); };
+        cancel.onclick = function() { answer(false); };
+        document.addEventListener("keydown", function(event) {
+            if (event.key === "Enter") answer(true);
+            // an alert only has the one answer
+            else if (event.key === "Escape") answer(kind === "alert");
+        });
+        (kind === "prompt" ? input : ok).focus();
+    })
+"#;
+
+fn modal_html(dialog: &JsDialog) -> String {
+    let (kind, default_text) = match &dialog.kind {
+        JsDialogKind::Alert => ("alert", ""),
+        JsDialogKind::Confirm => ("confirm", ""),
+        JsDialogKind::Prompt { default_text } => ("prompt", default_text.as_str()),
+        JsDialogKind::BeforeUnload { .. } => ("beforeunload", ""),
+    };
+    format!(
+        "{}({}, {}, {});\n</script>\n</body>\n</html>\n",
+        MODAL_HTML.trim_end(),
+        strings::to_js_string_literal(kind),
+        strings::to_js_string_literal(&dialog.message),
+        strings::to_js_string_literal(default_text)
+    )
+}
+
+impl JsDialogHandler for InPageModal {
+    fn on_dialog(&self, browser: &Browser, dialog: &JsDialog, callback: JsDialogCallback) -> bool {
+        let window_id =
+            match windows::window_of(browser).filter(|id| windows::window(*id).is_some()) {
+                Some(window_id) => window_id,
+                None => return self.fallback.on_dialog(browser, dialog, callback),
+            };
+
+        let is_prompt = matches!(dialog.kind, JsDialogKind::Prompt { .. });
+        let on_answer = Box::from(move |accept: bool, input: String| {
+            if !accept {
+                callback.deny();
+            } else if is_prompt {
+                callback.accept(Some(&input));
+            } else {
+                callback.accept(None);
+            }
+        });
+        modal_overlay::show(window_id, &modal_html(dialog), on_answer)
+    }
+
+    fn on_reset(&self, browser: &Browser) {
+        match windows::window_of(browser) {
+            Some(window_id) => modal_overlay::dismiss(window_id),
+            None => self.fallback.on_reset(browser),
+        }
+    }
+}
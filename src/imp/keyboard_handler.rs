@@ -0,0 +1,118 @@
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_event_flags_t_EVENTFLAG_ALT_DOWN,
+    cef_event_flags_t_EVENTFLAG_COMMAND_DOWN, cef_event_flags_t_EVENTFLAG_CONTROL_DOWN,
+    cef_event_flags_t_EVENTFLAG_SHIFT_DOWN, cef_event_handle_t, cef_key_event_t,
+    cef_key_event_type_t_KEYEVENT_RAWKEYDOWN, cef_keyboard_handler_t,
+};
+use super::browser::Browser;
+use super::shortcuts::{self, KeyCombo, Shortcuts};
+
+#[repr(C)]
+pub struct KeyboardHandler {
+    keyboard_handler: cef_keyboard_handler_t,
+    ref_count: AtomicUsize,
+    /// The window's own shortcuts; app-wide shortcuts are looked up as keys
+    /// are pressed
+    shortcuts: Shortcuts,
+}
+
+impl KeyboardHandler {
+    pub fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Called before the page sees a key, so shortcuts can keep it from the page
+unsafe extern "C" fn on_pre_key_event(
+    slf: *mut cef_keyboard_handler_t,
+    browser: *mut cef_browser_t,
+    event: *const cef_key_event_t,
+    _os_event: cef_event_handle_t,
+    _is_keyboard_shortcut: *mut c_int,
+) -> c_int {
+    let handler = slf as *mut KeyboardHandler;
+    if (*event).type_ != cef_key_event_type_t_KEYEVENT_RAWKEYDOWN {
+        return 0;
+    }
+
+    let modifiers = (*event).modifiers;
+    let combo = KeyCombo {
+        ctrl: modifiers & cef_event_flags_t_EVENTFLAG_CONTROL_DOWN as u32 != 0,
+        shift: modifiers & cef_event_flags_t_EVENTFLAG_SHIFT_DOWN as u32 != 0,
+        alt: modifiers & cef_event_flags_t_EVENTFLAG_ALT_DOWN as u32 != 0,
+        command: modifiers & cef_event_flags_t_EVENTFLAG_COMMAND_DOWN as u32 != 0,
+        key_code: (*event).windows_key_code,
+    };
+    match shortcuts::find(&(*handler).shortcuts, &combo) {
+        Some(shortcut) => {
+            shortcut.run(&Browser::from_raw(browser));
+            shortcut.swallow as c_int
+        }
+        None => 0,
+    }
+}
+
+pub fn allocate(shortcuts: Shortcuts) -> *mut KeyboardHandler {
+    let handler = KeyboardHandler {
+        keyboard_handler: cef_keyboard_handler_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<KeyboardHandler>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            on_pre_key_event: Some(on_pre_key_event),
+            on_key_event: None,
+        },
+        ref_count: AtomicUsize::new(1),
+        shortcuts,
+    };
+
+    Box::into_raw(Box::from(handler))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let keyboard_handler = base as *mut KeyboardHandler;
+    unsafe {
+        (*keyboard_handler).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let keyboard_handler = base as *mut KeyboardHandler;
+    let count = unsafe { (*keyboard_handler).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(keyboard_handler);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let keyboard_handler = base as *mut KeyboardHandler;
+    let count = unsafe { (*keyboard_handler).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let keyboard_handler = base as *mut KeyboardHandler;
+    let count = unsafe { (*keyboard_handler).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
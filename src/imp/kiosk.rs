@@ -0,0 +1,209 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use super::task;
+use super::windows::{self, WindowId};
+
+/// The kiosk-specific settings for `WindowOptions::kiosk`
+#[derive(Debug, Clone, Default)]
+pub struct KioskOptions {
+    /// URLs the window may navigate to, along with everything below them on
+    /// the same site: `https://kiosk.example.com/help` allows
+    /// `https://kiosk.example.com/help/faq`, but not `/helpdesk` or any other
+    /// host. The home URL is always allowed; anything else is blocked.
+    pub allowed_urls: Vec<String>,
+    /// Go back to the home URL once nobody has touched the window for this
+    /// long
+    pub idle_timeout: Option<Duration>,
+}
+
+impl KioskOptions {
+    /// The URLs a kiosk with this home URL may navigate to, as checked by
+    /// `is_allowed`
+    pub(crate) fn navigation_allowlist(&self, home_url: &str) -> Vec<String> {
+        let mut allowlist = vec![home_url.to_string()];
+        allowlist.extend(self.allowed_urls.iter().cloned());
+        allowlist
+    }
+}
+
+/// Whether `url` is covered by one of the URLs in `allowlist`: it has the
+/// same scheme, host and port, and its path is the allowed path or below it.
+/// Queries and fragments are ignored, and URLs which don't parse never match.
+pub fn is_allowed(allowlist: &[String], url: &str) -> bool {
+    let url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    allowlist.iter().any(|allowed| match Url::parse(allowed) {
+        Ok(allowed) => covers(&allowed, &url),
+        Err(_) => {
+            log::warn!("ignoring invalid kiosk URL `{}`", allowed);
+            false
+        }
+    })
+}
+
+fn covers(allowed: &Url, url: &Url) -> bool {
+    if allowed.scheme() != url.scheme()
+        || allowed.host_str() != url.host_str()
+        || allowed.port_or_known_default() != url.port_or_known_default()
+    {
+        return false;
+    }
+
+    // only match whole path segments, so `/app` doesn't allow `/apps`
+    let (prefix, path) = (allowed.path(), url.path());
+    path == prefix
+        || (prefix.ends_with('/') && path.starts_with(prefix))
+        || (path.starts_with(prefix) && path[prefix.len()..].starts_with('/'))
+}
+
+struct Kiosk {
+    home_url: String,
+    idle_timeout: Option<Duration>,
+    last_activity: Instant,
+}
+
+thread_local! {
+    static KIOSKS: RefCell<BTreeMap<WindowId, Kiosk>> = RefCell::new(BTreeMap::new());
+}
+
+/// Start treating a window as a kiosk, watching for it going idle
+pub fn start(id: WindowId, home_url: &str, options: &KioskOptions) {
+    KIOSKS.with(|k| {
+        k.borrow_mut().insert(
+            id,
+            Kiosk {
+                home_url: home_url.to_string(),
+                idle_timeout: options.idle_timeout,
+                last_activity: Instant::now(),
+            },
+        )
+    });
+    if let Some(idle_timeout) = options.idle_timeout {
+        check_idle_after(id, idle_timeout);
+    }
+}
+
+pub fn stop(id: WindowId) {
+    KIOSKS.with(|k| k.borrow_mut().remove(&id));
+}
+
+pub fn is_kiosk(id: WindowId) -> bool {
+    KIOSKS.with(|k| k.borrow().contains_key(&id))
+}
+
+/// Called as the user uses the window, restarting its idle timer
+pub fn activity(id: WindowId) {
+    KIOSKS.with(|k| {
+        if let Some(kiosk) = k.borrow_mut().get_mut(&id) {
+            kiosk.last_activity = Instant::now();
+        }
+    });
+}
+
+fn check_idle_after(id: WindowId, delay: Duration) {
+    task::post_delayed_task(move || check_idle(id), delay.as_millis() as i64);
+}
+
+/// Go home if the kiosk has been idle for too long, then check again once it
+/// next could be
+fn check_idle(id: WindowId) {
+    let kiosk = KIOSKS.with(|k| {
+        k.borrow().get(&id).map(|k| {
+            (
+                k.home_url.clone(),
+                k.idle_timeout,
+                k.last_activity.elapsed(),
+            )
+        })
+    });
+    let (home_url, idle_timeout, idle) = match kiosk {
+        Some((home_url, Some(idle_timeout), idle)) => (home_url, idle_timeout, idle),
+        // the window has closed
+        _ => return,
+    };
+
+    if idle < idle_timeout {
+        check_idle_after(id, idle_timeout - idle);
+        return;
+    }
+
+    log::info!("{} has been idle for {:?}, going home", id, idle);
+    if let Some(browser) = windows::browser(id) {
+        browser.load_url(&home_url);
+    }
+    activity(id);
+    check_idle_after(id, idle_timeout);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist(urls: &[&str]) -> Vec<String> {
+        urls.iter().map(|url| url.to_string()).collect()
+    }
+
+    #[test]
+    fn allows_the_same_site() {
+        let allowlist = allowlist(&["https://kiosk.example.com"]);
+        assert!(is_allowed(&allowlist, "https://kiosk.example.com"));
+        assert!(is_allowed(&allowlist, "https://kiosk.example.com/"));
+        assert!(is_allowed(
+            &allowlist,
+            "https://kiosk.example.com/menu?item=3#top"
+        ));
+        assert!(is_allowed(&allowlist, "https://kiosk.example.com:443/menu"));
+    }
+
+    #[test]
+    fn blocks_lookalike_hosts() {
+        let allowlist = allowlist(&["https://kiosk.example.com"]);
+        assert!(!is_allowed(
+            &allowlist,
+            "https://kiosk.example.com.evil.net/"
+        ));
+        assert!(!is_allowed(
+            &allowlist,
+            "https://kiosk.example.com@evil.net/"
+        ));
+        assert!(!is_allowed(
+            &allowlist,
+            "https://evil.net/https://kiosk.example.com"
+        ));
+        assert!(!is_allowed(&allowlist, "https://sub.kiosk.example.com/"));
+    }
+
+    #[test]
+    fn blocks_other_schemes_and_ports() {
+        let allowlist = allowlist(&["https://kiosk.example.com/"]);
+        assert!(!is_allowed(&allowlist, "http://kiosk.example.com/"));
+        assert!(!is_allowed(&allowlist, "https://kiosk.example.com:8443/"));
+        assert!(!is_allowed(&allowlist, "javascript:alert(1)"));
+    }
+
+    #[test]
+    fn matches_paths_on_segment_boundaries() {
+        let allowlist = allowlist(&["https://example.com/help", "https://example.com/docs/"]);
+        assert!(is_allowed(&allowlist, "https://example.com/help"));
+        assert!(is_allowed(&allowlist, "https://example.com/help/faq"));
+        assert!(!is_allowed(&allowlist, "https://example.com/helpdesk"));
+        assert!(is_allowed(&allowlist, "https://example.com/docs/intro"));
+        assert!(!is_allowed(&allowlist, "https://example.com/docs"));
+        assert!(!is_allowed(&allowlist, "https://example.com/"));
+    }
+
+    #[test]
+    fn never_matches_invalid_urls() {
+        assert!(!is_allowed(&allowlist(&["not a url"]), "not a url"));
+        assert!(!is_allowed(
+            &allowlist(&["https://example.com/"]),
+            "example.com/"
+        ));
+    }
+}
@@ -3,13 +3,27 @@ use std::os::raw::c_int;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::bindings::{
-    cef_base_ref_counted_t, cef_browser_t, cef_life_span_handler_t, cef_quit_message_loop,
+    cef_base_ref_counted_t, cef_browser_settings_t, cef_browser_t, cef_client_t,
+    cef_dictionary_value_t, cef_frame_t, cef_life_span_handler_t, cef_popup_features_t,
+    cef_string_t, cef_window_info_t, cef_window_open_disposition_t,
 };
+use super::client;
+use super::devtools_protocol;
+use super::popups::{self, Popup, PopupAction};
+use super::strings;
+use super::window_delegate::WindowOptions;
+use super::windows::{self, WindowId};
 
 #[repr(C)]
 pub struct LifeSpanHandler {
     life_span_handler: cef_life_span_handler_t,
     ref_count: AtomicUsize,
+    /// The window the browser is shown in, if any
+    window_id: Option<WindowId>,
+    /// The options popups inherit, if popups are allowed
+    popup_options: Option<WindowOptions>,
+    /// Called once the browser has closed, while its window is still known
+    on_closed: Option<Box<dyn FnOnce()>>,
 }
 
 impl LifeSpanHandler {
@@ -18,18 +32,104 @@ impl LifeSpanHandler {
     }
 }
 
-extern "C" fn do_close(_slf: *mut cef_life_span_handler_t, _browser: *mut cef_browser_t) -> c_int {
+unsafe extern "C" fn on_before_popup(
+    slf: *mut cef_life_span_handler_t,
+    browser: *mut cef_browser_t,
+    _frame: *mut cef_frame_t,
+    target_url: *const cef_string_t,
+    target_frame_name: *const cef_string_t,
+    _target_disposition: cef_window_open_disposition_t,
+    user_gesture: c_int,
+    popup_features: *const cef_popup_features_t,
+    _window_info: *mut cef_window_info_t,
+    client: *mut *mut cef_client_t,
+    _settings: *mut cef_browser_settings_t,
+    _extra_info: *mut *mut cef_dictionary_value_t,
+    _no_javascript_access: *mut c_int,
+) -> c_int {
+    let handler = slf as *mut LifeSpanHandler;
+    let url = strings::from_cef_string(target_url);
+    let popup_options = match &(*handler).popup_options {
+        Some(popup_options) => popup_options,
+        None => {
+            log::debug!("denied popup to `{}`", url);
+            return 1;
+        }
+    };
+
+    let mut options = popup_options.popup(&url);
+    if (*popup_features).widthSet == 1 && (*popup_features).heightSet == 1 {
+        options.size = Some(((*popup_features).width, (*popup_features).height));
+    }
+    let popup = Popup {
+        url: url.clone(),
+        frame_name: strings::from_cef_string(target_frame_name),
+        user_gesture: user_gesture == 1,
+        options,
+    };
+
+    // returning 1 cancels the popup
+    match popup_options.popup_policy.decide(popup) {
+        PopupAction::Deny => {
+            log::debug!("denied popup to `{}`", url);
+            1
+        }
+        PopupAction::SameBrowser => {
+            let frame = (*browser)
+                .get_main_frame
+                .expect("get_main_frame is a function")(browser);
+            (*frame).load_url.expect("load_url is a function")(frame, target_url);
+            1
+        }
+        PopupAction::SystemBrowser => {
+            popups::open_in_system_browser(&url);
+            1
+        }
+        PopupAction::NewWindow(mut options) => {
+            // CEF creates the popup's browser view, which gets its own window
+            // once it exists (see `browser_view_delegate`)
+            options.url = url;
+            let window_id = windows::register();
+            *client = client::allocate(std::ptr::null_mut(), window_id, options.client_hooks())
+                as *mut cef_client_t;
+            windows::set_pending_popup(window_id, options);
+            0
+        }
+    }
+}
+
+extern "C" fn do_close(slf: *mut cef_life_span_handler_t, _browser: *mut cef_browser_t) -> c_int {
+    // the page's beforeunload handlers have let it go
+    let handler = slf as *mut LifeSpanHandler;
+    if let Some(window_id) = unsafe { (*handler).window_id } {
+        windows::closing(window_id);
+    }
     0
 }
 
 unsafe extern "C" fn on_before_close(
-    _slf: *mut cef_life_span_handler_t,
-    _browser: *mut cef_browser_t,
+    slf: *mut cef_life_span_handler_t,
+    browser: *mut cef_browser_t,
 ) {
-    cef_quit_message_loop();
+    let browser_id = (*browser)
+        .get_identifier
+        .expect("get_identifier is a function")(browser);
+    devtools_protocol::forget(browser_id);
+
+    let handler = slf as *mut LifeSpanHandler;
+    if let Some(on_closed) = (*handler).on_closed.take() {
+        on_closed();
+    }
+    if let Some(window_id) = (*handler).window_id {
+        windows::closed(window_id);
+    }
 }
 
-pub fn allocate() -> *mut LifeSpanHandler {
+pub fn allocate(
+    window_id: Option<WindowId>,
+    popup_options: Option<WindowOptions>,
+    on_closed: Option<Box<dyn FnOnce()>>,
+) -> *mut LifeSpanHandler {
     let handler = LifeSpanHandler {
         life_span_handler: cef_life_span_handler_t {
             base: cef_base_ref_counted_t {
@@ -39,12 +139,15 @@ pub fn allocate() -> *mut LifeSpanHandler {
                 has_one_ref: Some(has_one_ref),
                 has_at_least_one_ref: Some(has_at_least_one_ref),
             },
-            on_before_popup: None,
+            on_before_popup: Some(on_before_popup),
             on_after_created: None,
             do_close: Some(do_close),
             on_before_close: Some(on_before_close),
         },
         ref_count: AtomicUsize::new(1),
+        window_id,
+        popup_options,
+        on_closed,
     };
 
     Box::into_raw(Box::from(handler))
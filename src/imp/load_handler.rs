@@ -0,0 +1,139 @@
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_errorcode_t, cef_errorcode_t_ERR_ABORTED,
+    cef_frame_t, cef_load_handler_t, cef_string_t,
+};
+use super::browser::Browser;
+use super::strings;
+
+/// Called once the page has finished loading, with an error message if the
+/// main frame failed to load
+pub type OnLoaded = Box<dyn FnMut(Browser, Result<(), String>)>;
+
+#[repr(C)]
+pub struct LoadHandler {
+    load_handler: cef_load_handler_t,
+    ref_count: AtomicUsize,
+    on_loaded: Option<OnLoaded>,
+    load_error: Option<String>,
+}
+
+impl LoadHandler {
+    pub fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+unsafe extern "C" fn on_loading_state_change(
+    slf: *mut cef_load_handler_t,
+    browser: *mut cef_browser_t,
+    is_loading: c_int,
+    _can_go_back: c_int,
+    _can_go_forward: c_int,
+) {
+    if is_loading == 1 {
+        return;
+    }
+
+    let handler = slf as *mut LoadHandler;
+    let result = match (*handler).load_error.take() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    };
+    log::debug!("page finished loading: {:?}", result);
+    if let Some(on_loaded) = &mut (*handler).on_loaded {
+        on_loaded(Browser::from_raw(browser), result);
+    }
+}
+
+unsafe extern "C" fn on_load_error(
+    slf: *mut cef_load_handler_t,
+    _browser: *mut cef_browser_t,
+    frame: *mut cef_frame_t,
+    error_code: cef_errorcode_t,
+    error_text: *const cef_string_t,
+    failed_url: *const cef_string_t,
+) {
+    // aborted loads are usually just a new navigation replacing the old one
+    if error_code == cef_errorcode_t_ERR_ABORTED
+        || ((*frame).is_main.expect("is_main is a function"))(frame) != 1
+    {
+        return;
+    }
+
+    let handler = slf as *mut LoadHandler;
+    let error = format!(
+        "failed to load `{}`: {}",
+        strings::from_cef_string(failed_url),
+        strings::from_cef_string(error_text)
+    );
+    log::warn!("{}", error);
+    (*handler).load_error = Some(error);
+}
+
+pub fn allocate(on_loaded: Option<OnLoaded>) -> *mut LoadHandler {
+    let handler = LoadHandler {
+        load_handler: cef_load_handler_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<LoadHandler>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            on_loading_state_change: Some(on_loading_state_change),
+            on_load_start: None,
+            on_load_end: None,
+            on_load_error: Some(on_load_error),
+        },
+        ref_count: AtomicUsize::new(1),
+        on_loaded,
+        load_error: None,
+    };
+
+    Box::into_raw(Box::from(handler))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let load_handler = base as *mut LoadHandler;
+    unsafe {
+        (*load_handler).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let load_handler = base as *mut LoadHandler;
+    let count = unsafe { (*load_handler).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(load_handler);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let load_handler = base as *mut LoadHandler;
+    let count = unsafe { (*load_handler).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let load_handler = base as *mut LoadHandler;
+    let count = unsafe { (*load_handler).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
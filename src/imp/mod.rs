@@ -1,3 +1,12 @@
+//! The CEF bindings and the handlers implementing them.
+//!
+//! CEF calls into the browser process on its UI thread, which is also the
+//! thread `Cef::initialize_with` and `Cef::run` are called on, and the public
+//! API is only meant to be used from there too. So state shared between
+//! handlers and the API lives in `thread_local!`s, never in `static`s with
+//! atomics or locks; work which needs to happen later or from elsewhere is
+//! posted back to the UI thread with `task::post_task`.
+
 pub mod app;
 pub mod bindings;
 pub mod browser;
@@ -5,13 +14,51 @@ pub mod browser_process_handler;
 pub mod browser_view_delegate;
 pub mod client;
 pub mod context_menu_handler;
+pub mod cookie_manager;
+pub mod devtools;
+pub mod devtools_protocol;
+pub mod dialog_handler;
 pub mod display_handler;
+pub mod download_handler;
+pub mod drag_handler;
+pub mod file_dialog;
+pub mod input;
+pub mod js_dialog_handler;
+pub mod keyboard_handler;
+pub mod kiosk;
 pub mod life_span_handler;
+pub mod load_handler;
+pub mod modal_overlay;
+pub mod offscreen;
+pub mod pdf_options;
+pub mod pdf_renderer;
+pub mod pending;
+pub mod popups;
+pub mod print_backend;
 pub mod print_handler;
 pub mod print_pdf_callback;
+pub mod print_settings;
+pub mod render_handler;
 pub mod render_process_handler;
 pub mod request_handler;
 pub mod run_file_dialog_callback;
+pub mod screenshot;
+pub mod settings;
+pub mod shortcuts;
+pub mod strings;
+pub mod task;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tracing;
+pub mod v8_close_handler;
+pub mod v8_dialog_handler;
+pub mod v8_download_handler;
 pub mod v8_file_dialog_handler;
 pub mod v8_pdf_print_handler;
+#[cfg(debug_assertions)]
+pub mod v8_trace_handler;
+pub mod v8_values;
+pub mod v8_window_handler;
 pub mod window_delegate;
+pub mod window_state;
+pub mod windows;
@@ -0,0 +1,150 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::mem::size_of;
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_settings_t, cef_browser_view_create, cef_browser_view_t,
+    cef_client_t, cef_docking_mode_t_CEF_DOCKING_MODE_CUSTOM, cef_overlay_controller_t, cef_rect_t,
+    cef_request_context_get_global_context, cef_view_t,
+};
+use super::browser::Browser;
+use super::client::{self, ClientHooks};
+use super::windows::{self, WindowId};
+use super::{strings, task};
+
+/// A browser shown on top of everything else in one of our windows
+struct Overlay {
+    serial: u64,
+    controller: *mut cef_overlay_controller_t,
+    browser_view: *mut cef_browser_view_t,
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        unsafe {
+            let browser = (*self.browser_view)
+                .get_browser
+                .expect("get_browser is a function")(self.browser_view);
+            if !browser.is_null() {
+                Browser::from_raw_owned(browser).close(true);
+            }
+            // the window destroys its overlays itself when it closes
+            if (*self.controller).is_valid.expect("is_valid is a function")(self.controller) == 1 {
+                (*self.controller).destroy.expect("destroy is a function")(self.controller);
+            }
+            (*self.controller)
+                .base
+                .release
+                .expect("release is a function")(
+                self.controller as *mut cef_base_ref_counted_t
+            );
+            (*self.browser_view)
+                .base
+                .base
+                .release
+                .expect("release is a function")(
+                self.browser_view as *mut cef_base_ref_counted_t
+            );
+        }
+    }
+}
+
+thread_local! {
+    static OVERLAYS: RefCell<BTreeMap<WindowId, Overlay>> = RefCell::new(BTreeMap::new());
+    static NEXT_SERIAL: Cell<u64> = Cell::new(0);
+}
+
+/// Show the page `html` over the whole of window `id`, until the page answers
+/// with `cef._answerDialog(accept, input)`. `on_answer` is then called with
+/// the answer and the overlay is removed. Returns false (without calling
+/// `on_answer`) if the window isn't open.
+pub fn show(id: WindowId, html: &str, on_answer: Box<dyn FnOnce(bool, String)>) -> bool {
+    let window = match windows::window(id) {
+        Some(window) => window,
+        None => return false,
+    };
+    dismiss(id);
+
+    let serial = NEXT_SERIAL.with(|n| n.replace(n.get() + 1));
+    let hooks = ClientHooks {
+        on_dialog_answer: Some(Box::from(move |accept: bool, input: String| {
+            // the answer comes from the overlay's own browser, so only remove
+            // it once that's done with the message (unless the page has
+            // opened another dialog in the meantime)
+            task::post_task(move || {
+                let overlay = OVERLAYS.with(|o| {
+                    let mut overlays = o.borrow_mut();
+                    match overlays.get(&id) {
+                        Some(overlay) if overlay.serial == serial => overlays.remove(&id),
+                        _ => None,
+                    }
+                });
+                drop(overlay);
+            });
+            on_answer(accept, input);
+        })),
+        ..ClientHooks::default()
+    };
+    let client = client::allocate_overlay(hooks);
+    let cef_url =
+        strings::to_cef_string(&format!("data:text/html;base64,{}", base64::encode(html)));
+    let mut browser_settings = cef_browser_settings_t::default();
+    browser_settings.size = size_of::<cef_browser_settings_t>() as u64;
+    // transparent, so the page shows through around the modal where CEF
+    // supports it
+    browser_settings.background_color = 0;
+
+    unsafe {
+        (*client).inc_ref();
+        let browser_view = cef_browser_view_create(
+            client as *mut cef_client_t,
+            &cef_url,
+            &browser_settings,
+            std::ptr::null_mut(),
+            cef_request_context_get_global_context(),
+            std::ptr::null_mut(),
+        );
+        let controller = (*window)
+            .add_overlay_view
+            .expect("add_overlay_view is a function")(
+            window,
+            browser_view as *mut cef_view_t,
+            cef_docking_mode_t_CEF_DOCKING_MODE_CUSTOM,
+        );
+
+        // cover the whole window, so nothing else can be clicked
+        let view = window as *mut cef_view_t;
+        let size = (*view).get_size.expect("get_size is a function")(view);
+        let bounds = cef_rect_t {
+            x: 0,
+            y: 0,
+            width: size.width,
+            height: size.height,
+        };
+        (*controller).set_bounds.expect("set_bounds is a function")(controller, &bounds);
+        (*controller)
+            .set_visible
+            .expect("set_visible is a function")(controller, 1);
+        (*(browser_view as *mut cef_view_t))
+            .request_focus
+            .expect("request_focus is a function")(browser_view as *mut cef_view_t);
+
+        OVERLAYS.with(|o| {
+            o.borrow_mut().insert(
+                id,
+                Overlay {
+                    serial,
+                    controller,
+                    browser_view,
+                },
+            )
+        });
+    }
+    true
+}
+
+/// Remove the overlay from window `id`, if it has one, without answering it
+pub fn dismiss(id: WindowId) {
+    let overlay = OVERLAYS.with(|o| o.borrow_mut().remove(&id));
+    drop(overlay);
+}
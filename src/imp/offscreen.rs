@@ -0,0 +1,262 @@
+use std::cell::Cell;
+use std::mem::size_of;
+use std::rc::Rc;
+
+use super::bindings::{
+    cef_browser_host_create_browser_sync, cef_browser_settings_t, cef_client_t, cef_cursor_type_t,
+    cef_cursor_type_t_CT_COLUMNRESIZE, cef_cursor_type_t_CT_CROSS, cef_cursor_type_t_CT_CUSTOM,
+    cef_cursor_type_t_CT_EASTWESTRESIZE, cef_cursor_type_t_CT_GRAB, cef_cursor_type_t_CT_GRABBING,
+    cef_cursor_type_t_CT_HAND, cef_cursor_type_t_CT_HELP, cef_cursor_type_t_CT_IBEAM,
+    cef_cursor_type_t_CT_MOVE, cef_cursor_type_t_CT_NONE, cef_cursor_type_t_CT_NORTHSOUTHRESIZE,
+    cef_cursor_type_t_CT_NOTALLOWED, cef_cursor_type_t_CT_POINTER, cef_cursor_type_t_CT_PROGRESS,
+    cef_cursor_type_t_CT_ROWRESIZE, cef_cursor_type_t_CT_WAIT, cef_rect_t, cef_window_info_t,
+};
+use super::browser::Browser;
+use super::client::{self, ClientHooks};
+use super::dialog_handler::DialogHandler;
+use super::download_handler::DownloadHandler;
+use super::js_dialog_handler::{JsDialogHandler, LogOnly};
+use super::load_handler::OnLoaded;
+use super::strings;
+
+thread_local! {
+    // whether CEF was initialized with `Settings::with_windowless_rendering`
+    static ENABLED: Cell<bool> = Cell::new(false);
+}
+
+pub(crate) fn set_enabled(enabled: bool) {
+    ENABLED.with(|e| e.set(enabled));
+}
+
+/// Fail unless CEF was initialized with windowless rendering, which it needs
+/// before any windowless browser can be created
+pub(crate) fn check_enabled() -> Result<(), String> {
+    if ENABLED.with(|e| e.get()) {
+        Ok(())
+    } else {
+        Err("windowless rendering is disabled; initialize CEF with \
+             `Settings::with_windowless_rendering`"
+            .to_string())
+    }
+}
+
+/// A rectangle in view coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl From<cef_rect_t> for Rect {
+    fn from(rect: cef_rect_t) -> Rect {
+        Rect {
+            x: rect.x,
+            y: rect.y,
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+}
+
+/// What a frame is a picture of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaintElement {
+    /// The page itself
+    View,
+    /// A popup widget, such as an open `<select>`, which should be drawn over
+    /// the view at the rect last given to `FrameSink::on_popup_rect`
+    Popup,
+}
+
+/// A frame painted by a windowless browser
+#[derive(Debug)]
+pub struct Frame<'a> {
+    pub element: PaintElement,
+    pub width: i32,
+    pub height: i32,
+    /// The parts of the frame which changed since the last one
+    pub dirty_rects: &'a [Rect],
+    /// `width` × `height` BGRA pixels, row by row from the top left, with
+    /// premultiplied alpha
+    pub buffer: &'a [u8],
+}
+
+/// The mouse cursor the page wants shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cursor {
+    Pointer,
+    Cross,
+    Hand,
+    IBeam,
+    Wait,
+    Progress,
+    Help,
+    Move,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    ColumnResize,
+    RowResize,
+    EastWestResize,
+    NorthSouthResize,
+    /// Hide the cursor
+    None,
+    /// An image the page supplied
+    Custom,
+    /// Any other CEF cursor type
+    Other(cef_cursor_type_t),
+}
+
+impl Cursor {
+    #[allow(non_upper_case_globals)]
+    pub(crate) fn from_cef(cursor: cef_cursor_type_t) -> Cursor {
+        match cursor {
+            cef_cursor_type_t_CT_POINTER => Cursor::Pointer,
+            cef_cursor_type_t_CT_CROSS => Cursor::Cross,
+            cef_cursor_type_t_CT_HAND => Cursor::Hand,
+            cef_cursor_type_t_CT_IBEAM => Cursor::IBeam,
+            cef_cursor_type_t_CT_WAIT => Cursor::Wait,
+            cef_cursor_type_t_CT_PROGRESS => Cursor::Progress,
+            cef_cursor_type_t_CT_HELP => Cursor::Help,
+            cef_cursor_type_t_CT_MOVE => Cursor::Move,
+            cef_cursor_type_t_CT_NOTALLOWED => Cursor::NotAllowed,
+            cef_cursor_type_t_CT_GRAB => Cursor::Grab,
+            cef_cursor_type_t_CT_GRABBING => Cursor::Grabbing,
+            cef_cursor_type_t_CT_COLUMNRESIZE => Cursor::ColumnResize,
+            cef_cursor_type_t_CT_ROWRESIZE => Cursor::RowResize,
+            cef_cursor_type_t_CT_EASTWESTRESIZE => Cursor::EastWestResize,
+            cef_cursor_type_t_CT_NORTHSOUTHRESIZE => Cursor::NorthSouthResize,
+            cef_cursor_type_t_CT_NONE => Cursor::None,
+            cef_cursor_type_t_CT_CUSTOM => Cursor::Custom,
+            other => Cursor::Other(other),
+        }
+    }
+}
+
+/// Receives what a windowless browser paints, to draw it wherever it's
+/// wanted (a texture in a game engine, a software-rendered dashboard, ...)
+pub trait FrameSink {
+    /// The size of the view the page is laid out in. Call
+    /// `Browser::was_resized` after this changes.
+    fn view_size(&self) -> (i32, i32);
+
+    /// Called whenever the view or a popup has been repainted. The buffer is
+    /// only valid during the call, so copy out what you need.
+    fn on_frame(&self, frame: &Frame);
+
+    /// Called when a popup widget is shown or hidden
+    fn on_popup_show(&self, _show: bool) {}
+
+    /// Called with where a popup widget is shown, in view coordinates
+    fn on_popup_rect(&self, _rect: Rect) {}
+
+    /// Called when the page wants a different mouse cursor
+    fn on_cursor_change(&self, _cursor: Cursor) {}
+}
+
+/// Options for a browser which renders off-screen, into a `FrameSink`,
+/// rather than into a window
+pub struct WindowlessOptions {
+    pub url: String,
+    pub frame_sink: Rc<dyn FrameSink>,
+    /// The most frames per second to paint
+    pub frame_rate: i32,
+    /// Leave the page's background transparent instead of painting it white,
+    /// so that pages without a background can be drawn over other content
+    pub transparent: bool,
+    /// Intercepts file dialogs
+    pub dialog_handler: Option<Rc<dyn DialogHandler>>,
+    /// Handles `alert()`, `confirm()`, `prompt()` and `beforeunload` dialogs.
+    /// Nobody can see dialogs in a windowless browser, so by default they're
+    /// logged and answered without blocking (see `LogOnly`).
+    pub js_dialog_handler: Option<Rc<dyn JsDialogHandler>>,
+    /// Decides where downloads go; nothing is downloaded without one
+    pub download_handler: Option<Rc<dyn DownloadHandler>>,
+}
+
+impl WindowlessOptions {
+    pub fn new(url: &str, frame_sink: Rc<dyn FrameSink>) -> WindowlessOptions {
+        WindowlessOptions {
+            url: url.to_string(),
+            frame_sink,
+            frame_rate: 30,
+            transparent: false,
+            dialog_handler: None,
+            js_dialog_handler: Some(Rc::new(LogOnly)),
+            download_handler: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for WindowlessOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WindowlessOptions")
+            .field("url", &self.url)
+            .field("frame_rate", &self.frame_rate)
+            .field("transparent", &self.transparent)
+            .field("dialog_handler", &self.dialog_handler.is_some())
+            .field("js_dialog_handler", &self.js_dialog_handler.is_some())
+            .field("download_handler", &self.download_handler.is_some())
+            .finish()
+    }
+}
+
+/// Create a windowless browser painting into `options.frame_sink`
+pub fn create_browser(options: WindowlessOptions) -> Option<Browser> {
+    create_browser_with(options, None, ClientHooks::default())
+}
+
+/// Create a windowless browser with extra hooks on top of the ones in
+/// `options`, calling `on_loaded` whenever a page finishes loading
+pub(crate) fn create_browser_with(
+    options: WindowlessOptions,
+    on_loaded: Option<OnLoaded>,
+    hooks: ClientHooks,
+) -> Option<Browser> {
+    let mut window_info = cef_window_info_t::default();
+    window_info.windowless_rendering_enabled = 1;
+    let mut browser_settings = cef_browser_settings_t::default();
+    browser_settings.size = size_of::<cef_browser_settings_t>() as u64;
+    browser_settings.windowless_frame_rate = options.frame_rate.max(1).min(60);
+    // ARGB; CEF makes windowless browsers with a fully transparent background
+    // color paint transparently
+    browser_settings.background_color = if options.transparent {
+        0x00000000
+    } else {
+        0xFFFFFFFF
+    };
+
+    let (width, height) = options.frame_sink.view_size();
+    let client = client::allocate_windowless(
+        width,
+        height,
+        on_loaded,
+        None,
+        ClientHooks {
+            dialog_handler: options.dialog_handler,
+            js_dialog_handler: options.js_dialog_handler,
+            download_handler: options.download_handler,
+            frame_sink: Some(options.frame_sink),
+            ..hooks
+        },
+    );
+    let cef_url = strings::to_cef_string(&options.url);
+    let browser = unsafe {
+        (*client).inc_ref();
+        cef_browser_host_create_browser_sync(
+            &window_info,
+            client as *mut cef_client_t,
+            &cef_url,
+            &browser_settings,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if browser.is_null() {
+        None
+    } else {
+        Some(unsafe { Browser::from_raw_owned(browser) })
+    }
+}
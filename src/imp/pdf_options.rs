@@ -0,0 +1,368 @@
+use super::bindings::{
+    _cef_pdf_print_settings_t, cef_list_value_t, cef_pdf_print_margin_type_t,
+    cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_CUSTOM,
+    cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_DEFAULT,
+    cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_MINIMUM,
+    cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_NONE, cef_string_t, cef_v8value_t,
+};
+use super::{strings, v8_values};
+
+/// Number of microns in an inch, for converting paper sizes
+pub const MICRONS_PER_INCH: i32 = 25400;
+
+/// The size of the paper to render PDFs onto
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaperSize {
+    /// 8.5 × 11 inches
+    Letter,
+    /// 8.5 × 14 inches
+    Legal,
+    /// 297 × 420 mm
+    A3,
+    /// 210 × 297 mm
+    A4,
+    /// 148 × 210 mm
+    A5,
+    /// A custom paper size, in microns (portrait orientation)
+    Custom { width: i32, height: i32 },
+}
+
+impl PaperSize {
+    /// The (width, height) of the paper in microns, in portrait orientation
+    pub fn microns(&self) -> (i32, i32) {
+        match self {
+            PaperSize::Letter => (85 * MICRONS_PER_INCH / 10, 11 * MICRONS_PER_INCH),
+            PaperSize::Legal => (85 * MICRONS_PER_INCH / 10, 14 * MICRONS_PER_INCH),
+            PaperSize::A3 => (297000, 420000),
+            PaperSize::A4 => (210000, 297000),
+            PaperSize::A5 => (148000, 210000),
+            PaperSize::Custom { width, height } => (*width, *height),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<PaperSize> {
+        match name.to_lowercase().as_ref() {
+            "letter" => Some(PaperSize::Letter),
+            "legal" => Some(PaperSize::Legal),
+            "a3" => Some(PaperSize::A3),
+            "a4" => Some(PaperSize::A4),
+            "a5" => Some(PaperSize::A5),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+/// Page margins. Custom margins are specified in millimeters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Margins {
+    /// Chrome's default margins (~1 inch)
+    Default,
+    None,
+    /// The minimum margins supported by the printer
+    Minimum,
+    Custom {
+        top: f64,
+        right: f64,
+        bottom: f64,
+        left: f64,
+    },
+}
+
+/// Text to print in the page headers and footers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeaderFooter {
+    pub title: String,
+    pub url: String,
+}
+
+/// Options controlling how a page gets printed to PDF
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfOptions {
+    pub paper_size: PaperSize,
+    pub orientation: Orientation,
+    pub margins: Margins,
+    /// Scale of the page contents, as a percent (100 = unscaled)
+    pub scale: u32,
+    /// Print headers and footers with the given title and URL
+    pub header_footer: Option<HeaderFooter>,
+    /// Only print the current selection
+    pub selection_only: bool,
+    /// Print background colours and graphics
+    pub backgrounds: bool,
+}
+
+impl Default for PdfOptions {
+    fn default() -> PdfOptions {
+        PdfOptions {
+            paper_size: PaperSize::A4,
+            orientation: Orientation::Portrait,
+            margins: Margins::Default,
+            scale: 100,
+            header_footer: None,
+            selection_only: false,
+            backgrounds: true,
+        }
+    }
+}
+
+/// The number of entries `PdfOptions` occupies in an IPC argument list
+pub const LIST_LEN: usize = 14;
+
+impl PdfOptions {
+    fn margin_type(&self) -> cef_pdf_print_margin_type_t {
+        match self.margins {
+            Margins::Default => cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_DEFAULT,
+            Margins::None => cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_NONE,
+            Margins::Minimum => cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_MINIMUM,
+            Margins::Custom { .. } => cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_CUSTOM,
+        }
+    }
+
+    fn margin_values(&self) -> (f64, f64, f64, f64) {
+        match self.margins {
+            Margins::Custom {
+                top,
+                right,
+                bottom,
+                left,
+            } => (top, right, bottom, left),
+            _ => (0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Build the CEF settings struct. Note that the header / footer strings
+    /// are allocated by CEF and owned by the returned struct.
+    pub fn to_cef_settings(&self) -> _cef_pdf_print_settings_t {
+        let (page_width, page_height) = self.paper_size.microns();
+        let (margin_top, margin_right, margin_bottom, margin_left) = self.margin_values();
+        let (header_footer_title, header_footer_url) = match &self.header_footer {
+            Some(hf) => (
+                strings::to_cef_string(&hf.title),
+                strings::to_cef_string(&hf.url),
+            ),
+            None => (cef_string_t::default(), cef_string_t::default()),
+        };
+
+        _cef_pdf_print_settings_t {
+            header_footer_title,
+            header_footer_url,
+            page_width,
+            page_height,
+            scale_factor: self.scale as i32,
+            margin_top,
+            margin_right,
+            margin_bottom,
+            margin_left,
+            margin_type: self.margin_type(),
+            header_footer_enabled: self.header_footer.is_some() as i32,
+            selection_only: self.selection_only as i32,
+            landscape: (self.orientation == Orientation::Landscape) as i32,
+            backgrounds_enabled: self.backgrounds as i32,
+        }
+    }
+
+    /// Serialize the options into an IPC argument list, starting at `index`.
+    /// The list must already be large enough to hold `LIST_LEN` more values.
+    pub unsafe fn write_to_list(&self, args: *mut cef_list_value_t, index: usize) {
+        let set_int = (*args).set_int.expect("set_int is a function");
+        let set_double = (*args).set_double.expect("set_double is a function");
+        let set_bool = (*args).set_bool.expect("set_bool is a function");
+        let set_string = (*args).set_string.expect("set_string is a function");
+        let i = index as u64;
+
+        let (page_width, page_height) = self.paper_size.microns();
+        let (margin_top, margin_right, margin_bottom, margin_left) = self.margin_values();
+        set_int(args, i, page_width);
+        set_int(args, i + 1, page_height);
+        set_bool(
+            args,
+            i + 2,
+            (self.orientation == Orientation::Landscape) as i32,
+        );
+        set_int(args, i + 3, self.margin_type() as i32);
+        set_double(args, i + 4, margin_top);
+        set_double(args, i + 5, margin_right);
+        set_double(args, i + 6, margin_bottom);
+        set_double(args, i + 7, margin_left);
+        set_int(args, i + 8, self.scale as i32);
+        set_bool(args, i + 9, self.header_footer.is_some() as i32);
+        let header_footer = self.header_footer.clone().unwrap_or_default();
+        set_string(args, i + 10, &strings::to_cef_string(&header_footer.title));
+        set_string(args, i + 11, &strings::to_cef_string(&header_footer.url));
+        set_bool(args, i + 12, self.selection_only as i32);
+        set_bool(args, i + 13, self.backgrounds as i32);
+    }
+
+    /// Deserialize options written with `write_to_list`. If the list is too
+    /// short, the default options are returned.
+    pub unsafe fn read_from_list(args: *mut cef_list_value_t, index: usize) -> PdfOptions {
+        let size = ((*args).get_size.expect("get_size is a function"))(args) as usize;
+        if size < index + LIST_LEN {
+            log::warn!("PDF options missing from IPC message, using defaults");
+            return PdfOptions::default();
+        }
+
+        let get_int = (*args).get_int.expect("get_int is a function");
+        let get_double = (*args).get_double.expect("get_double is a function");
+        let get_bool = (*args).get_bool.expect("get_bool is a function");
+        let get_string = (*args).get_string.expect("get_string is a function");
+        let i = index as u64;
+
+        #[allow(non_upper_case_globals)]
+        let margins = match get_int(args, i + 3) as cef_pdf_print_margin_type_t {
+            cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_NONE => Margins::None,
+            cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_MINIMUM => Margins::Minimum,
+            cef_pdf_print_margin_type_t_PDF_PRINT_MARGIN_CUSTOM => Margins::Custom {
+                top: get_double(args, i + 4),
+                right: get_double(args, i + 5),
+                bottom: get_double(args, i + 6),
+                left: get_double(args, i + 7),
+            },
+            _ => Margins::Default,
+        };
+
+        let header_footer = if get_bool(args, i + 9) == 1 {
+            Some(HeaderFooter {
+                title: strings::from_userfree(get_string(args, i + 10)),
+                url: strings::from_userfree(get_string(args, i + 11)),
+            })
+        } else {
+            None
+        };
+
+        PdfOptions {
+            paper_size: PaperSize::Custom {
+                width: get_int(args, i),
+                height: get_int(args, i + 1),
+            },
+            orientation: if get_bool(args, i + 2) == 1 {
+                Orientation::Landscape
+            } else {
+                Orientation::Portrait
+            },
+            margins,
+            scale: get_int(args, i + 8).max(1) as u32,
+            header_footer,
+            selection_only: get_bool(args, i + 12) == 1,
+            backgrounds: get_bool(args, i + 13) == 1,
+        }
+    }
+
+    /// Parse options from a JS object such as:
+    ///
+    /// ```js
+    /// {
+    ///     paperSize: "letter", // or "legal", "a3", "a4", "a5", {width, height} in microns
+    ///     orientation: "landscape", // or "portrait"
+    ///     margins: "none", // or "default", "minimum", {top, right, bottom, left} in mm
+    ///     scale: 100,
+    ///     headerFooter: { title: "My Report", url: "https://example.com/" },
+    ///     selectionOnly: false,
+    ///     backgrounds: true,
+    /// }
+    /// ```
+    ///
+    /// Missing keys take on their default values.
+    pub unsafe fn from_v8(object: *mut cef_v8value_t) -> Result<PdfOptions, String> {
+        let mut options = PdfOptions::default();
+        if v8_values::is_nullish(object) {
+            return Ok(options);
+        }
+        if ((*object).is_object.expect("is_object is a function"))(object) != 1 {
+            return Err("PDF options must be an object".to_owned());
+        }
+
+        if let Some(paper_size) = v8_values::get(object, "paperSize") {
+            options.paper_size = if let Some(name) = v8_values::as_string(paper_size) {
+                PaperSize::from_name(&name)
+                    .ok_or_else(|| format!("unknown paper size `{}`", name))?
+            } else {
+                let width =
+                    v8_values::get(paper_size, "width").and_then(|v| v8_values::as_number(v));
+                let height =
+                    v8_values::get(paper_size, "height").and_then(|v| v8_values::as_number(v));
+                match (width, height) {
+                    (Some(width), Some(height)) if width > 0.0 && height > 0.0 => {
+                        PaperSize::Custom {
+                            width: width as i32,
+                            height: height as i32,
+                        }
+                    }
+                    _ => {
+                        return Err(
+                            "paperSize must be a name or {width, height} in microns".to_owned()
+                        )
+                    }
+                }
+            };
+        }
+
+        if let Some(orientation) = v8_values::get(object, "orientation") {
+            options.orientation = match v8_values::as_string(orientation).as_deref() {
+                Some("portrait") => Orientation::Portrait,
+                Some("landscape") => Orientation::Landscape,
+                _ => return Err("orientation must be `portrait` or `landscape`".to_owned()),
+            };
+        }
+
+        if let Some(margins) = v8_values::get(object, "margins") {
+            options.margins = match v8_values::as_string(margins).as_deref() {
+                Some("default") => Margins::Default,
+                Some("none") => Margins::None,
+                Some("minimum") => Margins::Minimum,
+                Some(other) => return Err(format!("unknown margins `{}`", other)),
+                None => {
+                    let margin = |key: &str| {
+                        v8_values::get(margins, key)
+                            .and_then(|v| v8_values::as_number(v))
+                            .unwrap_or(0.0)
+                    };
+                    Margins::Custom {
+                        top: margin("top"),
+                        right: margin("right"),
+                        bottom: margin("bottom"),
+                        left: margin("left"),
+                    }
+                }
+            };
+        }
+
+        if let Some(scale) = v8_values::get(object, "scale") {
+            match v8_values::as_number(scale) {
+                Some(scale) if scale >= 1.0 => options.scale = scale as u32,
+                _ => return Err("scale must be a percentage >= 1".to_owned()),
+            }
+        }
+
+        if let Some(header_footer) = v8_values::get(object, "headerFooter") {
+            let text = |key: &str| {
+                v8_values::get(header_footer, key)
+                    .and_then(|v| v8_values::as_string(v))
+                    .unwrap_or_default()
+            };
+            options.header_footer = match v8_values::as_bool(header_footer) {
+                Some(false) => None,
+                _ => Some(HeaderFooter {
+                    title: text("title"),
+                    url: text("url"),
+                }),
+            };
+        }
+
+        if let Some(selection_only) = v8_values::get(object, "selectionOnly") {
+            options.selection_only = v8_values::as_bool(selection_only).unwrap_or(false);
+        }
+
+        if let Some(backgrounds) = v8_values::get(object, "backgrounds") {
+            options.backgrounds = v8_values::as_bool(backgrounds).unwrap_or(true);
+        }
+
+        Ok(options)
+    }
+}
@@ -0,0 +1,346 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+use super::bindings::{
+    cef_browser_host_create_browser_sync, cef_browser_settings_t, cef_client_t, cef_window_info_t,
+};
+use super::browser::Browser;
+use super::client::ClientHooks;
+use super::js_dialog_handler::{JsDialogHandler, LogOnly};
+use super::load_handler::OnLoaded;
+use super::pdf_options::PdfOptions;
+use super::{client, strings, task};
+
+/// What to render into a PDF
+#[derive(Debug, Clone)]
+pub enum PdfSource {
+    /// A complete HTML document
+    Html(String),
+    /// A URL to load
+    Url(String),
+}
+
+impl PdfSource {
+    fn url(&self) -> String {
+        match self {
+            PdfSource::Html(html) => format!("data:text/html;base64,{}", base64::encode(html)),
+            PdfSource::Url(url) => url.clone(),
+        }
+    }
+}
+
+/// A single page to render with a `PdfRenderer`
+#[derive(Debug, Clone)]
+pub struct PdfJob {
+    pub source: PdfSource,
+    pub options: PdfOptions,
+    /// After the page loads, wait for it to call `cef.signalReady()` before
+    /// printing (for pages which render asynchronously)
+    pub wait_for_ready_signal: bool,
+    /// How long to wait for the page to load (and signal that it is ready)
+    /// before giving up on the job
+    pub timeout: Duration,
+    /// The size of the off-screen view the page is laid out in
+    pub viewport: (i32, i32),
+}
+
+impl PdfJob {
+    pub fn html<S: Into<String>>(html: S) -> PdfJob {
+        PdfJob {
+            source: PdfSource::Html(html.into()),
+            ..PdfJob::default()
+        }
+    }
+
+    pub fn url<S: Into<String>>(url: S) -> PdfJob {
+        PdfJob {
+            source: PdfSource::Url(url.into()),
+            ..PdfJob::default()
+        }
+    }
+}
+
+impl Default for PdfJob {
+    fn default() -> PdfJob {
+        PdfJob {
+            source: PdfSource::Html(String::new()),
+            options: PdfOptions::default(),
+            wait_for_ready_signal: false,
+            timeout: Duration::from_secs(30),
+            viewport: (1280, 720),
+        }
+    }
+}
+
+enum PdfOutput {
+    File(PathBuf, Option<Box<dyn FnMut(bool)>>),
+    Memory(Option<Box<dyn FnMut(Option<Vec<u8>>)>>),
+}
+
+struct State {
+    queue: VecDeque<(PdfJob, PdfOutput)>,
+    active: usize,
+    max_concurrent: usize,
+    on_idle: Option<Box<dyn FnMut()>>,
+    js_dialog_handler: Rc<dyn JsDialogHandler>,
+}
+
+/// Renders HTML / URLs to PDF using windowless browsers, without showing any
+/// UI. Jobs are queued and rendered with at most `max_concurrent` browsers at
+/// a time. Obtain one with `Cef::pdf_renderer`.
+#[derive(Clone)]
+pub struct PdfRenderer {
+    state: Rc<RefCell<State>>,
+}
+
+impl PdfRenderer {
+    pub(crate) fn new(max_concurrent: usize) -> PdfRenderer {
+        PdfRenderer {
+            state: Rc::new(RefCell::new(State {
+                queue: VecDeque::new(),
+                active: 0,
+                max_concurrent: max_concurrent.max(1),
+                on_idle: None,
+                js_dialog_handler: Rc::new(LogOnly),
+            })),
+        }
+    }
+
+    /// Queue a job, writing the PDF to `path`. `on_done` is called with `true`
+    /// if the PDF was successfully written.
+    pub fn render_to_file<P: Into<PathBuf>>(
+        &self,
+        job: PdfJob,
+        path: P,
+        on_done: Option<Box<dyn FnMut(bool)>>,
+    ) {
+        self.enqueue(job, PdfOutput::File(path.into(), on_done));
+    }
+
+    /// Queue a job, returning the PDF's bytes (or `None` if rendering failed)
+    pub fn render(&self, job: PdfJob, on_done: Option<Box<dyn FnMut(Option<Vec<u8>>)>>) {
+        self.enqueue(job, PdfOutput::Memory(on_done));
+    }
+
+    /// Called whenever the queue has been fully processed
+    pub fn on_idle(&self, on_idle: Box<dyn FnMut()>) {
+        self.state.borrow_mut().on_idle = Some(on_idle);
+    }
+
+    /// Handle `alert()` etc. in rendered pages with `handler`. Pages can't
+    /// show dialogs to anyone, so by default they're logged and answered
+    /// without blocking (see `LogOnly`).
+    pub fn set_js_dialog_handler(&self, handler: Rc<dyn JsDialogHandler>) {
+        self.state.borrow_mut().js_dialog_handler = handler;
+    }
+
+    /// The number of jobs which are queued or currently rendering
+    pub fn pending(&self) -> usize {
+        let state = self.state.borrow();
+        state.queue.len() + state.active
+    }
+
+    fn enqueue(&self, job: PdfJob, output: PdfOutput) {
+        self.state.borrow_mut().queue.push_back((job, output));
+        let state = self.state.clone();
+        task::post_task(move || pump(&state));
+    }
+}
+
+fn pump(state: &Rc<RefCell<State>>) {
+    loop {
+        let next = {
+            let mut s = state.borrow_mut();
+            if s.active >= s.max_concurrent {
+                return;
+            }
+            match s.queue.pop_front() {
+                Some(next) => {
+                    s.active += 1;
+                    next
+                }
+                None => {
+                    if s.active == 0 {
+                        let on_idle = s.on_idle.take();
+                        drop(s);
+                        if let Some(mut on_idle) = on_idle {
+                            on_idle();
+                            let mut s = state.borrow_mut();
+                            if s.on_idle.is_none() {
+                                s.on_idle = Some(on_idle);
+                            }
+                        }
+                    }
+                    return;
+                }
+            }
+        };
+        let (job, output) = next;
+        start(state.clone(), job, output);
+    }
+}
+
+struct Run {
+    state: Rc<RefCell<State>>,
+    options: PdfOptions,
+    wait_for_ready_signal: bool,
+    output: RefCell<Option<PdfOutput>>,
+    browser: RefCell<Option<Browser>>,
+    loaded: Cell<bool>,
+    ready: Cell<bool>,
+    printing: Cell<bool>,
+}
+
+impl Run {
+    fn try_print(self: &Rc<Self>) {
+        if !self.loaded.get() || (self.wait_for_ready_signal && !self.ready.get()) {
+            return;
+        }
+        if self.printing.replace(true) {
+            return;
+        }
+
+        let browser = match self.browser.borrow().clone() {
+            Some(browser) => browser,
+            None => return self.fail(),
+        };
+        let output = self.output.borrow_mut().take();
+        let run = self.clone();
+        match output {
+            Some(PdfOutput::File(path, mut on_done)) => browser.print_to_pdf(
+                &path,
+                &self.options,
+                Some(Box::from(move |ok: bool| {
+                    run.close();
+                    if let Some(on_done) = &mut on_done {
+                        on_done(ok);
+                    }
+                })),
+            ),
+            Some(PdfOutput::Memory(mut on_done)) => browser.print_to_pdf_data(
+                &self.options,
+                Some(Box::from(move |data: Option<Vec<u8>>| {
+                    run.close();
+                    if let Some(on_done) = &mut on_done {
+                        on_done(data);
+                    }
+                })),
+            ),
+            None => {}
+        }
+    }
+
+    fn fail(self: &Rc<Self>) {
+        self.printing.set(true);
+        let output = self.output.borrow_mut().take();
+        match output {
+            Some(PdfOutput::File(_, Some(mut on_done))) => on_done(false),
+            Some(PdfOutput::Memory(Some(mut on_done))) => on_done(None),
+            _ => {}
+        }
+        self.close();
+    }
+
+    fn close(&self) {
+        let browser = self.browser.borrow_mut().take();
+        if let Some(browser) = browser {
+            browser.close(true);
+        }
+        self.state.borrow_mut().active -= 1;
+        let state = self.state.clone();
+        task::post_task(move || pump(&state));
+    }
+}
+
+fn start(state: Rc<RefCell<State>>, job: PdfJob, output: PdfOutput) {
+    log::debug!("rendering PDF from {:?}", job.source);
+    let run = Rc::new(Run {
+        state,
+        options: job.options,
+        wait_for_ready_signal: job.wait_for_ready_signal,
+        output: RefCell::new(Some(output)),
+        browser: RefCell::new(None),
+        loaded: Cell::new(false),
+        ready: Cell::new(false),
+        printing: Cell::new(false),
+    });
+
+    let on_loaded: OnLoaded = {
+        let run = run.clone();
+        Box::from(move |browser: Browser, result: Result<(), String>| {
+            if run.printing.get() {
+                return;
+            }
+            if run.browser.borrow().is_none() {
+                *run.browser.borrow_mut() = Some(browser);
+            }
+            match result {
+                Ok(()) => {
+                    run.loaded.set(true);
+                    run.try_print();
+                }
+                Err(e) => {
+                    log::error!("PDF job failed to load: {}", e);
+                    run.fail();
+                }
+            }
+        })
+    };
+    let on_page_ready: Box<dyn FnMut(Browser)> = {
+        let run = run.clone();
+        Box::from(move |_browser: Browser| {
+            run.ready.set(true);
+            run.try_print();
+        })
+    };
+
+    let mut window_info = cef_window_info_t::default();
+    window_info.windowless_rendering_enabled = 1;
+    let mut browser_settings = cef_browser_settings_t::default();
+    browser_settings.size = size_of::<cef_browser_settings_t>() as u64;
+
+    let (width, height) = job.viewport;
+    let client = client::allocate_windowless(
+        width,
+        height,
+        Some(on_loaded),
+        Some(on_page_ready),
+        ClientHooks {
+            js_dialog_handler: Some(run.state.borrow().js_dialog_handler.clone()),
+            ..ClientHooks::default()
+        },
+    );
+    let cef_url = strings::to_cef_string(&job.source.url());
+    let browser = unsafe {
+        (*client).inc_ref();
+        cef_browser_host_create_browser_sync(
+            &window_info,
+            client as *mut cef_client_t,
+            &cef_url,
+            &browser_settings,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if browser.is_null() {
+        log::error!("failed to create windowless browser for PDF job");
+        run.fail();
+        return;
+    }
+    *run.browser.borrow_mut() = Some(unsafe { Browser::from_raw_owned(browser) });
+
+    let timeout = {
+        let run = run.clone();
+        move || {
+            if !run.printing.get() {
+                log::error!("PDF job timed out");
+                run.fail();
+            }
+        }
+    };
+    task::post_delayed_task(timeout, job.timeout.as_millis() as i64);
+}
@@ -0,0 +1,124 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+struct State<T> {
+    result: Option<Result<T, String>>,
+    resolved: bool,
+    waker: Option<Waker>,
+}
+
+/// The result of a request CEF answers later, such as a DevTools protocol
+/// call, as a `Future`. CEF answers on the UI thread while its message loop
+/// runs, so await it from a single-threaded executor driven from the UI
+/// thread, and never block the UI thread waiting for it.
+///
+/// Resolves to an error if the request is dropped without an answer, for
+/// example because its browser closed.
+pub struct Pending<T> {
+    state: Rc<RefCell<State<T>>>,
+}
+
+/// Resolves the `Pending` it was made with
+pub(crate) struct Resolver<T> {
+    state: Rc<RefCell<State<T>>>,
+}
+
+/// Make a `Pending` along with the `Resolver` which answers it
+pub(crate) fn pending<T>() -> (Pending<T>, Resolver<T>) {
+    let state = Rc::new(RefCell::new(State {
+        result: None,
+        resolved: false,
+        waker: None,
+    }));
+    (
+        Pending {
+            state: state.clone(),
+        },
+        Resolver { state },
+    )
+}
+
+impl<T> Resolver<T> {
+    pub fn resolve(self, result: Result<T, String>) {
+        self.set(result);
+    }
+
+    fn set(&self, result: Result<T, String>) {
+        let waker = {
+            let mut state = self.state.borrow_mut();
+            if state.resolved {
+                return;
+            }
+            state.resolved = true;
+            state.result = Some(result);
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for Resolver<T> {
+    fn drop(&mut self) {
+        self.set(Err("the request was dropped without an answer".to_string()));
+    }
+}
+
+impl<T> Future for Pending<T> {
+    type Output = Result<T, String>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.borrow_mut();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    struct CountWakes(AtomicUsize);
+
+    impl Wake for CountWakes {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn poll<T>(pending: &mut Pending<T>, wakes: &Arc<CountWakes>) -> Poll<Result<T, String>> {
+        let waker = Waker::from(wakes.clone());
+        Pin::new(pending).poll(&mut Context::from_waker(&waker))
+    }
+
+    #[test]
+    fn resolves_with_the_result() {
+        let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+        let (mut pending, resolver) = pending();
+        assert_eq!(poll(&mut pending, &wakes), Poll::Pending);
+
+        resolver.resolve(Ok(42));
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+        assert_eq!(poll(&mut pending, &wakes), Poll::Ready(Ok(42)));
+    }
+
+    #[test]
+    fn fails_when_dropped_without_an_answer() {
+        let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+        let (mut pending, resolver) = pending::<()>();
+        drop(resolver);
+        assert!(matches!(poll(&mut pending, &wakes), Poll::Ready(Err(_))));
+    }
+}
@@ -0,0 +1,102 @@
+use std::process::Command;
+use std::rc::Rc;
+
+use super::window_delegate::WindowOptions;
+
+/// What to do when a page opens a popup, with `window.open` or a
+/// `target="_blank"` link
+#[derive(Clone)]
+pub enum PopupPolicy {
+    /// Don't open the popup at all
+    Deny,
+    /// Load the popup's URL in the browser which tried to open it
+    SameBrowser,
+    /// Open the popup's URL in the user's default browser
+    SystemBrowser,
+    /// Open the popup in a new window using the opener's options (see
+    /// `WindowOptions::popup`). The popup keeps its `window.opener`, so this
+    /// works for OAuth flows which report back to the opener.
+    NewWindow,
+    /// Decide for each popup
+    Decide(Rc<dyn Fn(Popup) -> PopupAction>),
+}
+
+impl Default for PopupPolicy {
+    fn default() -> PopupPolicy {
+        PopupPolicy::NewWindow
+    }
+}
+
+impl std::fmt::Debug for PopupPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PopupPolicy::Deny => write!(f, "Deny"),
+            PopupPolicy::SameBrowser => write!(f, "SameBrowser"),
+            PopupPolicy::SystemBrowser => write!(f, "SystemBrowser"),
+            PopupPolicy::NewWindow => write!(f, "NewWindow"),
+            PopupPolicy::Decide(_) => write!(f, "Decide"),
+        }
+    }
+}
+
+/// What to do with a single popup, returned from `PopupPolicy::Decide`
+#[derive(Debug)]
+pub enum PopupAction {
+    Deny,
+    SameBrowser,
+    SystemBrowser,
+    /// Open the popup in a new window with these options. Start from
+    /// `Popup::options` to inherit the opener's options; the options' `url`
+    /// is ignored.
+    NewWindow(WindowOptions),
+}
+
+/// A popup which a page is trying to open
+#[derive(Debug)]
+pub struct Popup {
+    pub url: String,
+    /// The name given to `window.open`, if any
+    pub frame_name: String,
+    /// Whether the popup was opened in response to the user clicking or typing
+    pub user_gesture: bool,
+    /// The options the popup's window gets under `PopupPolicy::NewWindow`
+    pub options: WindowOptions,
+}
+
+impl PopupPolicy {
+    pub(crate) fn decide(&self, popup: Popup) -> PopupAction {
+        match self {
+            PopupPolicy::Deny => PopupAction::Deny,
+            PopupPolicy::SameBrowser => PopupAction::SameBrowser,
+            PopupPolicy::SystemBrowser => PopupAction::SystemBrowser,
+            PopupPolicy::NewWindow => PopupAction::NewWindow(popup.options),
+            PopupPolicy::Decide(decide) => decide(popup),
+        }
+    }
+}
+
+/// Open `url` in the user's default browser. Only web and mail links are
+/// opened, so that pages can't launch local files or programs.
+pub fn open_in_system_browser(url: &str) {
+    let lower = url.to_ascii_lowercase();
+    if !["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| lower.starts_with(scheme))
+    {
+        log::warn!("not opening `{}` in the system browser", url);
+        return;
+    }
+
+    #[cfg(target_os = "windows")]
+    let result = Command::new("rundll32")
+        .args(&["url.dll,FileProtocolHandler", url])
+        .spawn();
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg(url).spawn();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = Command::new("xdg-open").arg(url).spawn();
+
+    if let Err(e) = result {
+        log::error!("failed to open `{}` in the system browser: {}", url, e);
+    }
+}
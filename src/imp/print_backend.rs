@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_print_dialog_callback_t, cef_print_job_callback_t,
+    cef_print_settings_create,
+};
+use super::print_handler;
+use super::print_settings::PrintSettings;
+
+/// Handles native printing (i.e. `window.print()` and `cef.print(options)`).
+/// Chromium renders the document to a PDF and hands it to the backend, which
+/// is responsible for getting it to a printer (or wherever else).
+pub trait PrintBackend {
+    /// The settings to start a print with, unless the print was started by
+    /// `cef.print(options)`
+    fn default_settings(&mut self) -> PrintSettings {
+        PrintSettings::default()
+    }
+
+    /// Show a print dialog, letting the user adjust `settings`. Call
+    /// `callback.proceed` or `callback.cancel` once the user is done; this
+    /// may happen after returning. Return false to cancel printing
+    /// immediately.
+    fn show_dialog(
+        &mut self,
+        has_selection: bool,
+        settings: PrintSettings,
+        callback: PrintDialogCallback,
+    ) -> bool;
+
+    /// Print the PDF at `pdf_path`, calling `callback.done` once finished
+    /// (which may happen after returning). The file is deleted once the job is
+    /// done. Return false to cancel the job.
+    fn print_job(
+        &mut self,
+        document_name: &str,
+        pdf_path: &Path,
+        settings: &PrintSettings,
+        callback: PrintJobCallback,
+    ) -> bool;
+
+    /// Called when printing has completed (or been cancelled), in order to
+    /// reset any state
+    fn reset(&mut self) {}
+}
+
+/// Used to continue or cancel printing after showing a print dialog
+pub struct PrintDialogCallback {
+    callback: *mut cef_print_dialog_callback_t,
+}
+
+impl PrintDialogCallback {
+    pub(crate) unsafe fn from_raw(callback: *mut cef_print_dialog_callback_t) -> Self {
+        (*callback).base.add_ref.expect("add_ref is a function")(
+            callback as *mut cef_base_ref_counted_t,
+        );
+        PrintDialogCallback { callback }
+    }
+
+    /// Continue printing with the given settings
+    pub fn proceed(self, settings: &PrintSettings) {
+        print_handler::set_current_settings(settings.clone());
+        unsafe {
+            let cef_settings = cef_print_settings_create();
+            settings.apply_to(cef_settings);
+            (*self.callback).cont.expect("cont is a function")(self.callback, cef_settings);
+        }
+    }
+
+    pub fn cancel(self) {
+        unsafe { (*self.callback).cancel.expect("cancel is a function")(self.callback) };
+    }
+}
+
+impl Drop for PrintDialogCallback {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.callback)
+                .base
+                .release
+                .expect("release is a function")(
+                self.callback as *mut cef_base_ref_counted_t
+            );
+        }
+    }
+}
+
+/// Used to signal that a print job has been completed
+pub struct PrintJobCallback {
+    callback: *mut cef_print_job_callback_t,
+}
+
+impl PrintJobCallback {
+    pub(crate) unsafe fn from_raw(callback: *mut cef_print_job_callback_t) -> Self {
+        (*callback).base.add_ref.expect("add_ref is a function")(
+            callback as *mut cef_base_ref_counted_t,
+        );
+        PrintJobCallback { callback }
+    }
+
+    pub fn done(self) {
+        unsafe { (*self.callback).cont.expect("cont is a function")(self.callback) };
+    }
+}
+
+impl Drop for PrintJobCallback {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.callback)
+                .base
+                .release
+                .expect("release is a function")(
+                self.callback as *mut cef_base_ref_counted_t
+            );
+        }
+    }
+}
+
+/// A print backend that "prints" by saving the PDF into a directory, without
+/// showing a dialog
+#[derive(Debug, Clone)]
+pub struct PrintToFileBackend {
+    pub directory: PathBuf,
+    /// Settings to print with, before any overrides from `cef.print`
+    pub settings: PrintSettings,
+}
+
+impl PrintToFileBackend {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> PrintToFileBackend {
+        PrintToFileBackend {
+            directory: directory.into(),
+            settings: PrintSettings::default(),
+        }
+    }
+
+    fn destination(&self, document_name: &str) -> PathBuf {
+        let stem: String = document_name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let stem = if stem.trim().is_empty() {
+            "document".to_owned()
+        } else {
+            stem.trim().to_owned()
+        };
+
+        let mut path = self.directory.join(format!("{}.pdf", stem));
+        let mut i = 1;
+        while path.exists() {
+            path = self.directory.join(format!("{} ({}).pdf", stem, i));
+            i += 1;
+        }
+        path
+    }
+}
+
+impl PrintBackend for PrintToFileBackend {
+    fn default_settings(&mut self) -> PrintSettings {
+        self.settings.clone()
+    }
+
+    fn show_dialog(
+        &mut self,
+        _has_selection: bool,
+        settings: PrintSettings,
+        callback: PrintDialogCallback,
+    ) -> bool {
+        callback.proceed(&settings);
+        true
+    }
+
+    fn print_job(
+        &mut self,
+        document_name: &str,
+        pdf_path: &Path,
+        _settings: &PrintSettings,
+        callback: PrintJobCallback,
+    ) -> bool {
+        if let Err(e) = std::fs::create_dir_all(&self.directory) {
+            log::error!(
+                "failed to create print directory `{}`: {}",
+                self.directory.display(),
+                e
+            );
+            return false;
+        }
+
+        let destination = self.destination(document_name);
+        match std::fs::copy(pdf_path, &destination) {
+            Ok(_) => {
+                log::info!("printed `{}` to `{}`", document_name, destination.display());
+                callback.done();
+                true
+            }
+            Err(e) => {
+                log::error!("failed to print to `{}`: {}", destination.display(), e);
+                false
+            }
+        }
+    }
+}
@@ -1,8 +1,34 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::mem::size_of;
 use std::os::raw::c_int;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use super::bindings::{cef_base_ref_counted_t, cef_print_handler_t, cef_size_t};
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_print_dialog_callback_t, cef_print_handler_t,
+    cef_print_job_callback_t, cef_print_settings_t, cef_size_t, cef_string_t,
+};
+use super::pdf_options::MICRONS_PER_INCH;
+use super::print_backend::{PrintBackend, PrintDialogCallback, PrintJobCallback};
+use super::print_settings::PrintSettings;
+use super::strings;
+
+thread_local! {
+    // printing callbacks all happen on the browser process' UI thread
+    static BACKEND: RefCell<Option<Box<dyn PrintBackend>>> = RefCell::new(None);
+    // settings requested through `cef.print(options)` for the next print
+    static PENDING_SETTINGS: RefCell<Option<PrintSettings>> = RefCell::new(None);
+    // the settings of the print in progress
+    static CURRENT_SETTINGS: RefCell<PrintSettings> = RefCell::new(PrintSettings::default());
+    // the paper size (in microns) of the PDF print in progress, if any
+    static PDF_PAPER_SIZE: Cell<Option<(i32, i32)>> = Cell::new(None);
+    // PDF prints waiting for the one in progress, with their paper sizes
+    static PDF_QUEUE: RefCell<VecDeque<((i32, i32), Box<dyn FnOnce()>)>> =
+        RefCell::new(VecDeque::new());
+}
+
+/// The paper size (in microns) of a PDF print we don't know about: A4
+const DEFAULT_PDF_PAPER_SIZE: (i32, i32) = (210000, 297000);
 
 #[derive(Debug)]
 #[repr(C)]
@@ -11,21 +37,142 @@ pub struct PrintHandler {
     ref_count: AtomicUsize,
 }
 
+/// Run `start` to begin a PDF print on `paper_size` (in microns), once any
+/// PDF print in progress has finished. CEF asks for the paper size without
+/// saying which browser is printing, so only one PDF print runs at a time;
+/// call `pdf_print_finished` when it's done.
+pub fn queue_pdf_print(paper_size: (i32, i32), start: Box<dyn FnOnce()>) {
+    if PDF_PAPER_SIZE.with(|s| s.get()).is_some() {
+        PDF_QUEUE.with(|q| q.borrow_mut().push_back((paper_size, start)));
+        return;
+    }
+    PDF_PAPER_SIZE.with(|s| s.set(Some(paper_size)));
+    start();
+}
+
+/// Start the next queued PDF print, if there is one
+pub fn pdf_print_finished() {
+    let next = PDF_QUEUE.with(|q| q.borrow_mut().pop_front());
+    PDF_PAPER_SIZE.with(|s| s.set(next.as_ref().map(|(paper_size, _)| *paper_size)));
+    if let Some((_, start)) = next {
+        start();
+    }
+}
+
 unsafe extern "C" fn get_pdf_paper_size(
     _slf: *mut cef_print_handler_t,
     device_units_per_inch: c_int,
 ) -> cef_size_t {
-    let mm_per_in = 25.4;
-    // TODO: less hacky / hard-coded?
-    let device_units_per_mm = (device_units_per_inch as f64) / mm_per_in;
-    let width = 210.0 * device_units_per_mm;
-    let height = 297.0 * device_units_per_mm;
+    let (width_microns, height_microns) = PDF_PAPER_SIZE
+        .with(|s| s.get())
+        .unwrap_or(DEFAULT_PDF_PAPER_SIZE);
+    let device_units_per_micron = (device_units_per_inch as f64) / (MICRONS_PER_INCH as f64);
+    let width = width_microns as f64 * device_units_per_micron;
+    let height = height_microns as f64 * device_units_per_micron;
     cef_size_t {
         width: width as i32,
         height: height as i32,
     }
 }
 
+pub fn set_backend(backend: Box<dyn PrintBackend>) {
+    BACKEND.with(|b| *b.borrow_mut() = Some(backend));
+}
+
+/// Override the default settings for the next print
+pub fn set_pending_settings(settings: PrintSettings) {
+    PENDING_SETTINGS.with(|s| *s.borrow_mut() = Some(settings));
+}
+
+pub fn set_current_settings(settings: PrintSettings) {
+    CURRENT_SETTINGS.with(|s| *s.borrow_mut() = settings);
+}
+
+fn current_settings() -> PrintSettings {
+    CURRENT_SETTINGS.with(|s| s.borrow().clone())
+}
+
+/// Run `f` with the backend (if there is one). The backend is taken out of its
+/// slot while `f` runs so that CEF calling back into us re-entrantly doesn't
+/// cause a double borrow.
+fn with_backend<R, F: FnOnce(&mut Box<dyn PrintBackend>) -> R>(f: F) -> Option<R> {
+    let backend = BACKEND.with(|b| b.borrow_mut().take());
+    if let Some(mut backend) = backend {
+        let result = f(&mut backend);
+        BACKEND.with(|b| {
+            let mut b = b.borrow_mut();
+            if b.is_none() {
+                *b = Some(backend);
+            }
+        });
+        Some(result)
+    } else {
+        log::warn!("no print backend has been set, see `Cef::set_print_backend`");
+        None
+    }
+}
+
+unsafe extern "C" fn on_print_start(_slf: *mut cef_print_handler_t, _browser: *mut cef_browser_t) {
+    log::debug!("print started");
+}
+
+unsafe extern "C" fn on_print_settings(
+    _slf: *mut cef_print_handler_t,
+    _browser: *mut cef_browser_t,
+    settings: *mut cef_print_settings_t,
+    get_defaults: c_int,
+) {
+    if get_defaults == 1 {
+        let pending = PENDING_SETTINGS.with(|s| s.borrow_mut().take());
+        let defaults = match pending {
+            Some(pending) => pending,
+            None => with_backend(|backend| backend.default_settings()).unwrap_or_default(),
+        };
+        set_current_settings(defaults);
+    }
+    current_settings().apply_to(settings);
+}
+
+unsafe extern "C" fn on_print_dialog(
+    _slf: *mut cef_print_handler_t,
+    _browser: *mut cef_browser_t,
+    has_selection: c_int,
+    callback: *mut cef_print_dialog_callback_t,
+) -> c_int {
+    let callback = PrintDialogCallback::from_raw(callback);
+    let settings = current_settings();
+    with_backend(move |backend| backend.show_dialog(has_selection == 1, settings, callback))
+        .unwrap_or(false) as c_int
+}
+
+unsafe extern "C" fn on_print_job(
+    _slf: *mut cef_print_handler_t,
+    _browser: *mut cef_browser_t,
+    document_name: *const cef_string_t,
+    pdf_file_path: *const cef_string_t,
+    callback: *mut cef_print_job_callback_t,
+) -> c_int {
+    let document_name = strings::from_cef_string(document_name);
+    let pdf_file_path = std::path::PathBuf::from(strings::from_cef_string(pdf_file_path));
+    log::debug!(
+        "print job `{}` ready at `{}`",
+        document_name,
+        pdf_file_path.display()
+    );
+    let callback = PrintJobCallback::from_raw(callback);
+    let settings = current_settings();
+    with_backend(move |backend| {
+        backend.print_job(&document_name, &pdf_file_path, &settings, callback)
+    })
+    .unwrap_or(false) as c_int
+}
+
+unsafe extern "C" fn on_print_reset(_slf: *mut cef_print_handler_t, _browser: *mut cef_browser_t) {
+    log::debug!("print reset");
+    with_backend(|backend| backend.reset());
+    PENDING_SETTINGS.with(|s| *s.borrow_mut() = None);
+}
+
 pub fn allocate() -> *mut PrintHandler {
     let handler = PrintHandler {
         print_handler: cef_print_handler_t {
@@ -36,12 +183,11 @@ pub fn allocate() -> *mut PrintHandler {
                 has_one_ref: Some(has_one_ref),
                 has_at_least_one_ref: Some(has_at_least_one_ref),
             },
-            // TODO: implement these?
-            on_print_start: None,
-            on_print_settings: None,
-            on_print_dialog: None,
-            on_print_job: None,
-            on_print_reset: None,
+            on_print_start: Some(on_print_start),
+            on_print_settings: Some(on_print_settings),
+            on_print_dialog: Some(on_print_dialog),
+            on_print_job: Some(on_print_job),
+            on_print_reset: Some(on_print_reset),
             get_pdf_paper_size: Some(get_pdf_paper_size),
         },
         ref_count: AtomicUsize::new(1),
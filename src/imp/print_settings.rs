@@ -0,0 +1,421 @@
+use super::bindings::{
+    cef_color_model_t, cef_color_model_t_COLOR_MODEL_COLOR, cef_color_model_t_COLOR_MODEL_GRAY,
+    cef_duplex_mode_t, cef_duplex_mode_t_DUPLEX_MODE_LONG_EDGE,
+    cef_duplex_mode_t_DUPLEX_MODE_SHORT_EDGE, cef_duplex_mode_t_DUPLEX_MODE_SIMPLEX,
+    cef_list_value_t, cef_print_settings_t, cef_range_t, cef_v8value_t, size_t,
+};
+use super::{strings, v8_values};
+
+/// An inclusive range of pages to print, numbered from 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageRange {
+    pub from: u32,
+    pub to: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Duplex {
+    Simplex,
+    LongEdge,
+    ShortEdge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    Color,
+    Grayscale,
+}
+
+/// Settings for a print job
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintSettings {
+    /// The printer to print to, if the backend cares
+    pub printer: Option<String>,
+    /// Pages to print; all pages are printed if this is empty
+    pub page_ranges: Vec<PageRange>,
+    pub copies: u32,
+    pub duplex: Duplex,
+    pub color: ColorMode,
+    pub landscape: bool,
+    pub selection_only: bool,
+    pub collate: bool,
+}
+
+impl Default for PrintSettings {
+    fn default() -> PrintSettings {
+        PrintSettings {
+            printer: None,
+            page_ranges: Vec::new(),
+            copies: 1,
+            duplex: Duplex::Simplex,
+            color: ColorMode::Color,
+            landscape: false,
+            selection_only: false,
+            collate: true,
+        }
+    }
+}
+
+/// Parse page ranges such as `1-3, 5, 8-10`
+pub fn parse_page_ranges(ranges: &str) -> Result<Vec<PageRange>, String> {
+    ranges
+        .split(',')
+        .map(|r| r.trim())
+        .filter(|r| !r.is_empty())
+        .map(|r| {
+            let mut parts = r.splitn(2, '-');
+            let from = parts.next().unwrap_or_default().trim();
+            let to = parts.next().unwrap_or(from).trim();
+            let from: u32 = from
+                .parse()
+                .map_err(|_| format!("invalid page range `{}`", r))?;
+            let to: u32 = to
+                .parse()
+                .map_err(|_| format!("invalid page range `{}`", r))?;
+            if from < 1 || to < from {
+                return Err(format!("invalid page range `{}`", r));
+            }
+            Ok(PageRange { from, to })
+        })
+        .collect()
+}
+
+fn format_page_ranges(ranges: &[PageRange]) -> String {
+    ranges
+        .iter()
+        .map(|r| format!("{}-{}", r.from, r.to))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// The number of entries `PrintSettings` occupies in an IPC argument list
+pub const LIST_LEN: usize = 8;
+
+impl PrintSettings {
+    /// Copy these settings into a CEF print settings object
+    pub unsafe fn apply_to(&self, settings: *mut cef_print_settings_t) {
+        if ((*settings)
+            .is_read_only
+            .expect("is_read_only is a function"))(settings)
+            == 1
+        {
+            log::warn!("print settings are read-only, can't apply them");
+            return;
+        }
+
+        if let Some(printer) = &self.printer {
+            let cef_printer = strings::to_cef_string(printer);
+            ((*settings)
+                .set_device_name
+                .expect("set_device_name is a function"))(settings, &cef_printer);
+        }
+        let ranges: Vec<cef_range_t> = self
+            .page_ranges
+            .iter()
+            .map(|r| cef_range_t {
+                from: r.from as i32 - 1,
+                to: r.to as i32 - 1,
+            })
+            .collect();
+        ((*settings)
+            .set_page_ranges
+            .expect("set_page_ranges is a function"))(
+            settings,
+            ranges.len() as size_t,
+            ranges.as_ptr(),
+        );
+        ((*settings).set_copies.expect("set_copies is a function"))(
+            settings,
+            self.copies.max(1) as i32,
+        );
+        ((*settings)
+            .set_duplex_mode
+            .expect("set_duplex_mode is a function"))(settings, duplex_to_cef(self.duplex));
+        ((*settings)
+            .set_color_model
+            .expect("set_color_model is a function"))(settings, color_to_cef(self.color));
+        ((*settings)
+            .set_orientation
+            .expect("set_orientation is a function"))(settings, self.landscape as i32);
+        ((*settings)
+            .set_selection_only
+            .expect("set_selection_only is a function"))(
+            settings, self.selection_only as i32
+        );
+        ((*settings).set_collate.expect("set_collate is a function"))(
+            settings,
+            self.collate as i32,
+        );
+    }
+
+    /// Read settings out of a CEF print settings object
+    pub unsafe fn from_cef(settings: *mut cef_print_settings_t) -> PrintSettings {
+        let printer = strings::from_userfree(((*settings)
+            .get_device_name
+            .expect("get_device_name is a function"))(
+            settings
+        ));
+
+        let mut count = ((*settings)
+            .get_page_ranges_count
+            .expect("get_page_ranges_count is a function"))(settings);
+        let mut ranges: Vec<cef_range_t> = vec![cef_range_t::default(); count as usize];
+        ((*settings)
+            .get_page_ranges
+            .expect("get_page_ranges is a function"))(
+            settings, &mut count, ranges.as_mut_ptr()
+        );
+        ranges.truncate(count as usize);
+
+        #[allow(non_upper_case_globals)]
+        let duplex = match ((*settings)
+            .get_duplex_mode
+            .expect("get_duplex_mode is a function"))(settings)
+        {
+            cef_duplex_mode_t_DUPLEX_MODE_LONG_EDGE => Duplex::LongEdge,
+            cef_duplex_mode_t_DUPLEX_MODE_SHORT_EDGE => Duplex::ShortEdge,
+            _ => Duplex::Simplex,
+        };
+
+        #[allow(non_upper_case_globals)]
+        let color = match ((*settings)
+            .get_color_model
+            .expect("get_color_model is a function"))(settings)
+        {
+            cef_color_model_t_COLOR_MODEL_GRAY => ColorMode::Grayscale,
+            _ => ColorMode::Color,
+        };
+
+        PrintSettings {
+            printer: if printer.is_empty() {
+                None
+            } else {
+                Some(printer)
+            },
+            page_ranges: ranges
+                .iter()
+                .map(|r| PageRange {
+                    from: (r.from + 1) as u32,
+                    to: (r.to + 1) as u32,
+                })
+                .collect(),
+            copies: ((*settings).get_copies.expect("get_copies is a function"))(settings).max(1)
+                as u32,
+            duplex,
+            color,
+            landscape: ((*settings)
+                .is_landscape
+                .expect("is_landscape is a function"))(settings)
+                == 1,
+            selection_only: ((*settings)
+                .is_selection_only
+                .expect("is_selection_only is a function"))(settings)
+                == 1,
+            collate: ((*settings)
+                .will_collate
+                .expect("will_collate is a function"))(settings)
+                == 1,
+        }
+    }
+
+    /// Serialize the settings into an IPC argument list, starting at `index`.
+    /// The list must already be large enough to hold `LIST_LEN` more values.
+    pub unsafe fn write_to_list(&self, args: *mut cef_list_value_t, index: usize) {
+        let set_int = (*args).set_int.expect("set_int is a function");
+        let set_bool = (*args).set_bool.expect("set_bool is a function");
+        let set_string = (*args).set_string.expect("set_string is a function");
+        let i = index as u64;
+
+        let printer = self.printer.clone().unwrap_or_default();
+        set_string(args, i, &strings::to_cef_string(&printer));
+        let ranges = format_page_ranges(&self.page_ranges);
+        set_string(args, i + 1, &strings::to_cef_string(&ranges));
+        set_int(args, i + 2, self.copies as i32);
+        set_int(args, i + 3, duplex_to_cef(self.duplex) as i32);
+        set_int(args, i + 4, color_to_cef(self.color) as i32);
+        set_bool(args, i + 5, self.landscape as i32);
+        set_bool(args, i + 6, self.selection_only as i32);
+        set_bool(args, i + 7, self.collate as i32);
+    }
+
+    /// Deserialize settings written with `write_to_list`. If the list is too
+    /// short, the default settings are returned.
+    pub unsafe fn read_from_list(args: *mut cef_list_value_t, index: usize) -> PrintSettings {
+        let size = ((*args).get_size.expect("get_size is a function"))(args) as usize;
+        if size < index + LIST_LEN {
+            log::warn!("print settings missing from IPC message, using defaults");
+            return PrintSettings::default();
+        }
+
+        let get_int = (*args).get_int.expect("get_int is a function");
+        let get_bool = (*args).get_bool.expect("get_bool is a function");
+        let get_string = (*args).get_string.expect("get_string is a function");
+        let i = index as u64;
+
+        let printer = strings::from_userfree(get_string(args, i));
+        let ranges = strings::from_userfree(get_string(args, i + 1));
+
+        #[allow(non_upper_case_globals)]
+        let duplex = match get_int(args, i + 3) as cef_duplex_mode_t {
+            cef_duplex_mode_t_DUPLEX_MODE_LONG_EDGE => Duplex::LongEdge,
+            cef_duplex_mode_t_DUPLEX_MODE_SHORT_EDGE => Duplex::ShortEdge,
+            _ => Duplex::Simplex,
+        };
+
+        #[allow(non_upper_case_globals)]
+        let color = match get_int(args, i + 4) as cef_color_model_t {
+            cef_color_model_t_COLOR_MODEL_GRAY => ColorMode::Grayscale,
+            _ => ColorMode::Color,
+        };
+
+        PrintSettings {
+            printer: if printer.is_empty() {
+                None
+            } else {
+                Some(printer)
+            },
+            page_ranges: parse_page_ranges(&ranges).unwrap_or_default(),
+            copies: get_int(args, i + 2).max(1) as u32,
+            duplex,
+            color,
+            landscape: get_bool(args, i + 5) == 1,
+            selection_only: get_bool(args, i + 6) == 1,
+            collate: get_bool(args, i + 7) == 1,
+        }
+    }
+
+    /// Parse settings from a JS object such as:
+    ///
+    /// ```js
+    /// {
+    ///     printer: "Office Laser",
+    ///     pageRanges: "1-3, 5",
+    ///     copies: 2,
+    ///     duplex: "long-edge", // or "simplex", "short-edge"
+    ///     color: "grayscale", // or "color"
+    ///     landscape: false,
+    ///     selectionOnly: false,
+    ///     collate: true,
+    /// }
+    /// ```
+    ///
+    /// Missing keys take on their default values.
+    pub unsafe fn from_v8(object: *mut cef_v8value_t) -> Result<PrintSettings, String> {
+        let mut settings = PrintSettings::default();
+        if v8_values::is_nullish(object) {
+            return Ok(settings);
+        }
+        if ((*object).is_object.expect("is_object is a function"))(object) != 1 {
+            return Err("print options must be an object".to_owned());
+        }
+
+        if let Some(printer) = v8_values::get(object, "printer") {
+            settings.printer = v8_values::as_string(printer);
+        }
+
+        if let Some(page_ranges) = v8_values::get(object, "pageRanges") {
+            let page_ranges = v8_values::as_string(page_ranges)
+                .ok_or_else(|| "pageRanges must be a string such as `1-3, 5`".to_owned())?;
+            settings.page_ranges = parse_page_ranges(&page_ranges)?;
+        }
+
+        if let Some(copies) = v8_values::get(object, "copies") {
+            match v8_values::as_number(copies) {
+                Some(copies) if copies >= 1.0 => settings.copies = copies as u32,
+                _ => return Err("copies must be a number >= 1".to_owned()),
+            }
+        }
+
+        if let Some(duplex) = v8_values::get(object, "duplex") {
+            settings.duplex = match v8_values::as_string(duplex).as_deref() {
+                Some("simplex") => Duplex::Simplex,
+                Some("long-edge") => Duplex::LongEdge,
+                Some("short-edge") => Duplex::ShortEdge,
+                _ => return Err("duplex must be `simplex`, `long-edge` or `short-edge`".to_owned()),
+            };
+        }
+
+        if let Some(color) = v8_values::get(object, "color") {
+            settings.color = match v8_values::as_string(color).as_deref() {
+                Some("color") => ColorMode::Color,
+                Some("grayscale") => ColorMode::Grayscale,
+                _ => return Err("color must be `color` or `grayscale`".to_owned()),
+            };
+        }
+
+        if let Some(landscape) = v8_values::get(object, "landscape") {
+            settings.landscape = v8_values::as_bool(landscape).unwrap_or(false);
+        }
+
+        if let Some(selection_only) = v8_values::get(object, "selectionOnly") {
+            settings.selection_only = v8_values::as_bool(selection_only).unwrap_or(false);
+        }
+
+        if let Some(collate) = v8_values::get(object, "collate") {
+            settings.collate = v8_values::as_bool(collate).unwrap_or(true);
+        }
+
+        Ok(settings)
+    }
+}
+
+fn duplex_to_cef(duplex: Duplex) -> cef_duplex_mode_t {
+    match duplex {
+        Duplex::Simplex => cef_duplex_mode_t_DUPLEX_MODE_SIMPLEX,
+        Duplex::LongEdge => cef_duplex_mode_t_DUPLEX_MODE_LONG_EDGE,
+        Duplex::ShortEdge => cef_duplex_mode_t_DUPLEX_MODE_SHORT_EDGE,
+    }
+}
+
+fn color_to_cef(color: ColorMode) -> cef_color_model_t {
+    match color {
+        ColorMode::Color => cef_color_model_t_COLOR_MODEL_COLOR,
+        ColorMode::Grayscale => cef_color_model_t_COLOR_MODEL_GRAY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(from: u32, to: u32) -> PageRange {
+        PageRange { from, to }
+    }
+
+    #[test]
+    fn parses_ranges_and_single_pages() {
+        assert_eq!(
+            parse_page_ranges("1-3,5"),
+            Ok(vec![range(1, 3), range(5, 5)])
+        );
+        assert_eq!(
+            format_page_ranges(&parse_page_ranges("1-3,5").unwrap()),
+            "1-3,5-5"
+        );
+    }
+
+    #[test]
+    fn ignores_whitespace() {
+        assert_eq!(
+            parse_page_ranges(" 1 - 3 ,  5,8-10 "),
+            Ok(vec![range(1, 3), range(5, 5), range(8, 10)])
+        );
+    }
+
+    #[test]
+    fn empty_input_means_every_page() {
+        assert_eq!(parse_page_ranges(""), Ok(vec![]));
+        assert_eq!(parse_page_ranges(" , "), Ok(vec![]));
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        for ranges in &["0", "0-2", "3-1", "a-b", "1-", "-3", "1,x"] {
+            assert!(
+                parse_page_ranges(ranges).is_err(),
+                "`{}` should be invalid",
+                ranges
+            );
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::mem::size_of;
+use std::os::raw::{c_int, c_void};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_cursor_handle_t, cef_cursor_info_t,
+    cef_cursor_type_t, cef_paint_element_type_t, cef_paint_element_type_t_PET_POPUP, cef_rect_t,
+    cef_render_handler_t, size_t,
+};
+use super::offscreen::{Cursor, Frame, FrameSink, PaintElement, Rect};
+
+/// Render handler for windowless (off-screen) browsers
+#[repr(C)]
+pub struct RenderHandler {
+    render_handler: cef_render_handler_t,
+    ref_count: AtomicUsize,
+    width: i32,
+    height: i32,
+    /// Where frames go; without one, nothing is displayed
+    frame_sink: Option<Rc<dyn FrameSink>>,
+}
+
+impl RenderHandler {
+    pub fn inc_ref(&self) {
+        self.ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+unsafe extern "C" fn get_view_rect(
+    slf: *mut cef_render_handler_t,
+    _browser: *mut cef_browser_t,
+    rect: *mut cef_rect_t,
+) {
+    let handler = slf as *mut RenderHandler;
+    let (width, height) = match &(*handler).frame_sink {
+        Some(frame_sink) => frame_sink.view_size(),
+        None => ((*handler).width, (*handler).height),
+    };
+    *rect = cef_rect_t {
+        x: 0,
+        y: 0,
+        width: width.max(1),
+        height: height.max(1),
+    };
+}
+
+unsafe extern "C" fn on_paint(
+    slf: *mut cef_render_handler_t,
+    _browser: *mut cef_browser_t,
+    type_: cef_paint_element_type_t,
+    dirty_rects_count: size_t,
+    dirty_rects: *const cef_rect_t,
+    buffer: *const c_void,
+    width: c_int,
+    height: c_int,
+) {
+    let handler = slf as *mut RenderHandler;
+    let frame_sink = match &(*handler).frame_sink {
+        Some(frame_sink) => frame_sink,
+        // nothing is displayed, so there's nothing to paint
+        None => return,
+    };
+
+    let dirty_rects: Vec<Rect> = if dirty_rects.is_null() {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(dirty_rects, dirty_rects_count as usize)
+            .iter()
+            .map(|&rect| Rect::from(rect))
+            .collect()
+    };
+    let buffer = std::slice::from_raw_parts(buffer as *const u8, (width * height * 4) as usize);
+    frame_sink.on_frame(&Frame {
+        element: if type_ == cef_paint_element_type_t_PET_POPUP {
+            PaintElement::Popup
+        } else {
+            PaintElement::View
+        },
+        width,
+        height,
+        dirty_rects: &dirty_rects,
+        buffer,
+    });
+}
+
+unsafe extern "C" fn on_popup_show(
+    slf: *mut cef_render_handler_t,
+    _browser: *mut cef_browser_t,
+    show: c_int,
+) {
+    let handler = slf as *mut RenderHandler;
+    if let Some(frame_sink) = &(*handler).frame_sink {
+        frame_sink.on_popup_show(show == 1);
+    }
+}
+
+unsafe extern "C" fn on_popup_size(
+    slf: *mut cef_render_handler_t,
+    _browser: *mut cef_browser_t,
+    rect: *const cef_rect_t,
+) {
+    let handler = slf as *mut RenderHandler;
+    if let Some(frame_sink) = &(*handler).frame_sink {
+        frame_sink.on_popup_rect(Rect::from(*rect));
+    }
+}
+
+unsafe extern "C" fn on_cursor_change(
+    slf: *mut cef_render_handler_t,
+    _browser: *mut cef_browser_t,
+    _cursor: cef_cursor_handle_t,
+    type_: cef_cursor_type_t,
+    _custom_cursor_info: *const cef_cursor_info_t,
+) {
+    let handler = slf as *mut RenderHandler;
+    if let Some(frame_sink) = &(*handler).frame_sink {
+        frame_sink.on_cursor_change(Cursor::from_cef(type_));
+    }
+}
+
+/// Allocate a render handler for a `width` × `height` view, painting into
+/// `frame_sink` (which decides the view's size instead) if there is one
+pub fn allocate(
+    width: i32,
+    height: i32,
+    frame_sink: Option<Rc<dyn FrameSink>>,
+) -> *mut RenderHandler {
+    let handler = RenderHandler {
+        render_handler: cef_render_handler_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<RenderHandler>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            get_view_rect: Some(get_view_rect),
+            on_paint: Some(on_paint),
+            on_popup_show: Some(on_popup_show),
+            on_popup_size: Some(on_popup_size),
+            on_cursor_change: Some(on_cursor_change),
+            ..Default::default()
+        },
+        ref_count: AtomicUsize::new(1),
+        width,
+        height,
+        frame_sink,
+    };
+
+    Box::into_raw(Box::from(handler))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let render_handler = base as *mut RenderHandler;
+    unsafe {
+        (*render_handler).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let render_handler = base as *mut RenderHandler;
+    let count = unsafe { (*render_handler).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(render_handler);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let render_handler = base as *mut RenderHandler;
+    let count = unsafe { (*render_handler).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let render_handler = base as *mut RenderHandler;
+    let count = unsafe { (*render_handler).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
@@ -5,10 +5,16 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use super::bindings::{
     cef_base_ref_counted_t, cef_browser_t, cef_dictionary_value_t, cef_frame_t, cef_process_id_t,
     cef_process_message_t, cef_render_process_handler_t, cef_string_userfree_t,
-    cef_string_userfree_utf16_free,
+    cef_string_userfree_utf16_free, cef_v8context_t,
 };
+use super::v8_close_handler::{self, V8CloseHandler};
+use super::v8_dialog_handler::{self, V8DialogHandler};
+use super::v8_download_handler::{self, V8DownloadHandler};
 use super::v8_file_dialog_handler::{self, V8FileDialogHandler};
 use super::v8_pdf_print_handler::{self, V8PDFPrintHandler};
+#[cfg(debug_assertions)]
+use super::v8_trace_handler::{self, V8TraceHandler};
+use super::v8_window_handler::{self, V8WindowHandler};
 
 #[repr(C)]
 pub struct RenderProcessHandler {
@@ -16,6 +22,12 @@ pub struct RenderProcessHandler {
     ref_count: AtomicUsize,
     pdf_print_extension: *mut V8PDFPrintHandler,
     file_dialog_extension: *mut V8FileDialogHandler,
+    close_extension: *mut V8CloseHandler,
+    window_extension: *mut V8WindowHandler,
+    download_extension: *mut V8DownloadHandler,
+    dialog_extension: *mut V8DialogHandler,
+    #[cfg(debug_assertions)]
+    trace_extension: *mut V8TraceHandler,
 }
 
 impl RenderProcessHandler {
@@ -28,33 +40,79 @@ unsafe extern "C" fn on_web_kit_initialized(slf: *mut cef_render_process_handler
     let _self = slf as *mut RenderProcessHandler;
     super::v8_pdf_print_handler::register_extension((*_self).pdf_print_extension);
     super::v8_file_dialog_handler::register_extension((*_self).file_dialog_extension);
+    super::v8_close_handler::register_extension((*_self).close_extension);
+    super::v8_window_handler::register_extension((*_self).window_extension);
+    super::v8_download_handler::register_extension((*_self).download_extension);
+    super::v8_dialog_handler::register_extension((*_self).dialog_extension);
+    #[cfg(debug_assertions)]
+    super::v8_trace_handler::register_extension((*_self).trace_extension);
     log::debug!("web kit initialized");
 }
 
 unsafe extern "C" fn on_browser_created(
     slf: *mut cef_render_process_handler_t,
     browser: *mut cef_browser_t,
-    _extra_info: *mut cef_dictionary_value_t,
+    extra_info: *mut cef_dictionary_value_t,
 ) {
     log::debug!("browser created");
     let _self = slf as *mut RenderProcessHandler;
+    super::v8_window_handler::browser_created((*_self).window_extension, browser, extra_info);
     (*(*_self).pdf_print_extension).browser = Some(browser);
     (*(*_self).file_dialog_extension).browser = Some(browser);
+    (*(*_self).close_extension).browser = Some(browser);
+    (*(*_self).window_extension).browser = Some(browser);
+    (*(*_self).download_extension).browser = Some(browser);
+    (*(*_self).dialog_extension).browser = Some(browser);
+    #[cfg(debug_assertions)]
+    {
+        (*(*_self).trace_extension).browser = Some(browser);
+    }
 }
 
 unsafe extern "C" fn on_browser_destroyed(
     slf: *mut cef_render_process_handler_t,
-    _browser: *mut cef_browser_t,
+    browser: *mut cef_browser_t,
 ) {
     log::debug!("browser destroyed");
     let _self = slf as *mut RenderProcessHandler;
+    super::v8_close_handler::browser_destroyed((*_self).close_extension, browser);
+    super::v8_window_handler::browser_destroyed((*_self).window_extension, browser);
     (*(*_self).pdf_print_extension).browser = None;
     (*(*_self).file_dialog_extension).browser = None;
+    (*(*_self).close_extension).browser = None;
+    (*(*_self).window_extension).browser = None;
+    (*(*_self).download_extension).browser = None;
+    (*(*_self).dialog_extension).browser = None;
+    #[cfg(debug_assertions)]
+    {
+        (*(*_self).trace_extension).browser = None;
+    }
+}
+
+unsafe extern "C" fn on_context_created(
+    slf: *mut cef_render_process_handler_t,
+    browser: *mut cef_browser_t,
+    frame: *mut cef_frame_t,
+    _context: *mut cef_v8context_t,
+) {
+    let _self = slf as *mut RenderProcessHandler;
+    super::v8_window_handler::context_created((*_self).window_extension, browser, frame);
+}
+
+unsafe extern "C" fn on_context_released(
+    slf: *mut cef_render_process_handler_t,
+    browser: *mut cef_browser_t,
+    _frame: *mut cef_frame_t,
+    context: *mut cef_v8context_t,
+) {
+    let _self = slf as *mut RenderProcessHandler;
+    super::v8_close_handler::context_released((*_self).close_extension, browser, context);
+    super::v8_pdf_print_handler::context_released((*_self).pdf_print_extension, context);
 }
 
 unsafe extern "C" fn on_process_message_received(
     slf: *mut cef_render_process_handler_t,
-    _browser: *mut cef_browser_t,
+    browser: *mut cef_browser_t,
     _frame: *mut cef_frame_t,
     _source_process: cef_process_id_t,
     message: *mut cef_process_message_t,
@@ -85,6 +143,14 @@ unsafe extern "C" fn on_process_message_received(
     ) {
         return 1;
     }
+    if super::v8_close_handler::process_message(
+        (*_self).close_extension,
+        browser,
+        &message_name,
+        message,
+    ) {
+        return 1;
+    }
     log::warn!("unhandled process message in renderer: `{}`", message_name);
     0
 }
@@ -104,8 +170,8 @@ pub fn allocate() -> *mut RenderProcessHandler {
             on_browser_created: Some(on_browser_created),
             on_browser_destroyed: Some(on_browser_destroyed),
             get_load_handler: None,
-            on_context_created: None,
-            on_context_released: None,
+            on_context_created: Some(on_context_created),
+            on_context_released: Some(on_context_released),
             on_uncaught_exception: None,
             on_focused_node_changed: None,
             on_process_message_received: Some(on_process_message_received),
@@ -113,6 +179,12 @@ pub fn allocate() -> *mut RenderProcessHandler {
         ref_count: AtomicUsize::new(1),
         pdf_print_extension: v8_pdf_print_handler::allocate(),
         file_dialog_extension: v8_file_dialog_handler::allocate(),
+        close_extension: v8_close_handler::allocate(),
+        window_extension: v8_window_handler::allocate(),
+        download_extension: v8_download_handler::allocate(),
+        dialog_extension: v8_dialog_handler::allocate(),
+        #[cfg(debug_assertions)]
+        trace_extension: v8_trace_handler::allocate(),
     };
 
     Box::into_raw(Box::from(handler))
@@ -2,13 +2,21 @@ use std::mem::size_of;
 use std::os::raw::c_int;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use super::bindings::{cef_base_ref_counted_t, cef_request_handler_t};
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_frame_t, cef_request_handler_t, cef_request_t,
+};
+use super::{kiosk, strings};
 
 #[derive(Debug)]
 #[repr(C)]
 pub struct RequestHandler {
     request_handler: cef_request_handler_t,
     ref_count: AtomicUsize,
+    /// The URLs the main frame may navigate to, if it's restricted: a URL is
+    /// allowed if it has the same scheme, host and port as one of them and its
+    /// path is at or below that one's, by whole path segments (see
+    /// `kiosk::is_allowed`)
+    navigation_allowlist: Option<Vec<String>>,
 }
 
 impl RequestHandler {
@@ -17,7 +25,34 @@ impl RequestHandler {
     }
 }
 
-pub fn allocate() -> *mut RequestHandler {
+/// Cancels main frame navigations to URLs outside the allowlist
+unsafe extern "C" fn on_before_browse(
+    slf: *mut cef_request_handler_t,
+    _browser: *mut cef_browser_t,
+    frame: *mut cef_frame_t,
+    request: *mut cef_request_t,
+    _user_gesture: c_int,
+    _is_redirect: c_int,
+) -> c_int {
+    let handler = slf as *mut RequestHandler;
+    let allowlist = match &(*handler).navigation_allowlist {
+        Some(allowlist) => allowlist,
+        None => return 0,
+    };
+    if (*frame).is_main.expect("is_main is a function")(frame) != 1 {
+        return 0;
+    }
+
+    let url = strings::from_userfree((*request).get_url.expect("get_url is a function")(request));
+    if kiosk::is_allowed(allowlist, &url) {
+        0
+    } else {
+        log::info!("blocked navigation to `{}`: it isn't in the allowlist", url);
+        1
+    }
+}
+
+pub fn allocate(navigation_allowlist: Option<Vec<String>>) -> *mut RequestHandler {
     let handler = RequestHandler {
         request_handler: cef_request_handler_t {
             base: cef_base_ref_counted_t {
@@ -27,7 +62,7 @@ pub fn allocate() -> *mut RequestHandler {
                 has_one_ref: Some(has_one_ref),
                 has_at_least_one_ref: Some(has_at_least_one_ref),
             },
-            on_before_browse: None,
+            on_before_browse: Some(on_before_browse),
             on_open_urlfrom_tab: None,
             get_resource_request_handler: None,
             get_auth_credentials: None,
@@ -39,6 +74,7 @@ pub fn allocate() -> *mut RequestHandler {
             on_render_process_terminated: None,
         },
         ref_count: AtomicUsize::new(1),
+        navigation_allowlist,
     };
 
     Box::into_raw(Box::from(handler))
@@ -1,51 +1,43 @@
-use super::bindings::{
-    _cef_run_file_dialog_callback_t, cef_base_ref_counted_t, cef_string_list_size,
-    cef_string_list_t, cef_string_list_value, cef_string_userfree_t,
-    cef_string_userfree_utf16_alloc, cef_string_userfree_utf16_free,
-};
+use super::bindings::{_cef_run_file_dialog_callback_t, cef_base_ref_counted_t, cef_string_list_t};
+use super::file_dialog::{FileDialogResult, FileFilter};
+use super::strings;
 use std::mem::size_of;
 use std::os::raw::c_int;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[repr(C)]
 pub struct RunFileDialogCallback {
     run_file_dialog_callback: _cef_run_file_dialog_callback_t,
     ref_count: AtomicUsize,
-    on_done: Option<Box<dyn FnMut(Option<std::path::PathBuf>)>>,
+    filters: Vec<FileFilter>,
+    on_done: Option<Box<dyn FnMut(Option<FileDialogResult>)>>,
 }
 
 unsafe extern "C" fn on_file_dialog_dismissed(
     slf: *mut _cef_run_file_dialog_callback_t,
-    _selected_accept_filter: c_int,
+    selected_accept_filter: c_int,
     file_paths: cef_string_list_t,
 ) {
     let callback = slf as *mut RunFileDialogCallback;
     if let Some(on_done) = &mut (*callback).on_done {
-        // if they cancelled, file_paths will be null, so alert as much
-        if file_paths == std::ptr::null_mut() || cef_string_list_size(file_paths) < 1 {
+        // if they cancelled, file_paths will be null (or empty), so alert as much
+        let paths = strings::from_string_list(file_paths);
+        if paths.is_empty() {
             log::debug!("user cancelled file dialog");
             on_done(None);
         } else {
-            // extract the first string from the list (only support a single string for now)
-            let cef_path: cef_string_userfree_t = cef_string_userfree_utf16_alloc();
-            if cef_string_list_value(file_paths, 0, cef_path) != 1 {
-                log::warn!("failed to extract first path from file dialog callback");
-                on_done(None);
-                return;
-            }
-
-            // convert the path into a Rust string
-            let chars: *mut u16 = (*cef_path).str_;
-            let len: usize = (*cef_path).length as usize;
-            let chars = std::slice::from_raw_parts(chars, len);
-            let path = std::char::decode_utf16(chars.iter().cloned())
-                .map(|r| r.unwrap_or(std::char::REPLACEMENT_CHARACTER))
-                .collect::<String>();
-            cef_string_userfree_utf16_free(cef_path);
-
-            // and alert our listener
-            log::debug!("file dialog complete, path: {}", path);
-            on_done(Some(std::path::PathBuf::from(path)));
+            log::debug!(
+                "file dialog complete, paths: {:?}, filter: {}",
+                paths,
+                selected_accept_filter
+            );
+            let filter_index = selected_accept_filter.max(0) as usize;
+            on_done(Some(FileDialogResult {
+                paths: paths.into_iter().map(PathBuf::from).collect(),
+                filter_index,
+                filter: (*callback).filters.get(filter_index).cloned(),
+            }));
         }
     } else {
         log::warn!("no callback registered for run file dialog callback, is this intentional?");
@@ -53,7 +45,8 @@ unsafe extern "C" fn on_file_dialog_dismissed(
 }
 
 pub fn allocate(
-    on_done: Option<Box<dyn FnMut(Option<std::path::PathBuf>)>>,
+    filters: Vec<FileFilter>,
+    on_done: Option<Box<dyn FnMut(Option<FileDialogResult>)>>,
 ) -> *mut RunFileDialogCallback {
     let handler = RunFileDialogCallback {
         run_file_dialog_callback: _cef_run_file_dialog_callback_t {
@@ -67,6 +60,7 @@ pub fn allocate(
             on_file_dialog_dismissed: Some(on_file_dialog_dismissed),
         },
         ref_count: AtomicUsize::new(1),
+        filters,
         on_done,
     };
 
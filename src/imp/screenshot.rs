@@ -0,0 +1,166 @@
+use serde_json::{json, Value};
+
+use super::browser::Browser;
+use super::{devtools_protocol, strings};
+
+/// Which part of the page to capture
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScreenshotArea {
+    /// What's currently visible
+    Viewport,
+    /// The whole page, including what's scrolled out of view
+    FullPage,
+    /// The bounding box of the first element matching a CSS selector, which
+    /// is scrolled into view first
+    Selector(String),
+}
+
+impl Default for ScreenshotArea {
+    fn default() -> ScreenshotArea {
+        ScreenshotArea::Viewport
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ScreenshotOptions {
+    pub area: ScreenshotArea,
+}
+
+/// Called with the PNG's bytes, or why it couldn't be captured
+pub type OnCaptured = Box<dyn FnOnce(Result<Vec<u8>, String>)>;
+
+/// Capture a PNG screenshot of the page using the DevTools protocol, which
+/// works the same for windowed and windowless browsers
+pub fn capture(browser: &Browser, options: &ScreenshotOptions, on_done: Option<OnCaptured>) {
+    let on_captured: OnCaptured = match on_done {
+        Some(on_done) => on_done,
+        None => Box::from(|_: Result<Vec<u8>, String>| {}),
+    };
+
+    match &options.area {
+        ScreenshotArea::Viewport => capture_png(browser, None, on_captured),
+        ScreenshotArea::FullPage => capture_full_page(browser, on_captured),
+        ScreenshotArea::Selector(selector) => capture_selector(browser, selector, on_captured),
+    }
+}
+
+/// Capture what's rendered, clipped to `clip` (in CSS pixels) if it's set
+fn capture_png(browser: &Browser, clip: Option<Value>, on_captured: OnCaptured) {
+    let mut params = json!({ "format": "png" });
+    if let Some(clip) = clip {
+        params["clip"] = clip;
+    }
+    devtools_protocol::execute(
+        browser,
+        "Page.captureScreenshot",
+        params,
+        Box::from(move |result: Result<Value, String>| {
+            on_captured(result.and_then(|result| {
+                let data = result["data"]
+                    .as_str()
+                    .ok_or_else(|| "the screenshot has no data".to_string())?;
+                base64::decode(data).map_err(|e| format!("invalid screenshot data: {}", e))
+            }))
+        }),
+    );
+}
+
+/// Grow the viewport to the size of the page, capture it and then put the
+/// viewport back
+fn capture_full_page(browser: &Browser, on_captured: OnCaptured) {
+    let browser_ = browser.clone();
+    devtools_protocol::execute(
+        browser,
+        "Page.getLayoutMetrics",
+        json!({}),
+        Box::from(move |result: Result<Value, String>| {
+            let metrics = match result {
+                Ok(metrics) => metrics,
+                Err(e) => return on_captured(Err(e)),
+            };
+            let width = metrics["contentSize"]["width"]
+                .as_f64()
+                .unwrap_or(0.0)
+                .ceil();
+            let height = metrics["contentSize"]["height"]
+                .as_f64()
+                .unwrap_or(0.0)
+                .ceil();
+            if width < 1.0 || height < 1.0 {
+                return on_captured(Err("the page has no content".to_string()));
+            }
+
+            let override_metrics = json!({
+                "width": width as i64,
+                "height": height as i64,
+                "deviceScaleFactor": 1,
+                "mobile": false,
+            });
+            let browser = browser_;
+            let captured_browser = browser.clone();
+            devtools_protocol::execute(
+                &browser,
+                "Emulation.setDeviceMetricsOverride",
+                override_metrics,
+                Box::from(move |result: Result<Value, String>| {
+                    if let Err(e) = result {
+                        return on_captured(Err(e));
+                    }
+                    let clip =
+                        json!({ "x": 0, "y": 0, "width": width, "height": height, "scale": 1 });
+                    let browser = captured_browser.clone();
+                    capture_png(
+                        &captured_browser,
+                        Some(clip),
+                        Box::from(move |png: Result<Vec<u8>, String>| {
+                            devtools_protocol::execute(
+                                &browser,
+                                "Emulation.clearDeviceMetricsOverride",
+                                json!({}),
+                                Box::from(move |_| on_captured(png)),
+                            );
+                        }),
+                    );
+                }),
+            );
+        }),
+    );
+}
+
+/// Capture the bounding box of the first element matching `selector`
+fn capture_selector(browser: &Browser, selector: &str, on_captured: OnCaptured) {
+    let expression = format!(
+        r#"(function() {{
+            var element = document.querySelector({});
+            if (!element) return null;
+            element.scrollIntoView({{ block: "nearest", inline: "nearest" }});
+            var rect = element.getBoundingClientRect();
+            return {{ x: rect.left + window.scrollX, y: rect.top + window.scrollY, width: rect.width, height: rect.height }};
+        }})()"#,
+        strings::to_js_string_literal(selector)
+    );
+    let selector = selector.to_string();
+    let browser_ = browser.clone();
+    devtools_protocol::execute(
+        browser,
+        "Runtime.evaluate",
+        json!({ "expression": expression, "returnByValue": true }),
+        Box::from(move |result: Result<Value, String>| {
+            let result = match result {
+                Ok(result) => result,
+                Err(e) => return on_captured(Err(e)),
+            };
+            if let Some(exception) = result.get("exceptionDetails") {
+                return on_captured(Err(format!("failed to find `{}`: {}", selector, exception)));
+            }
+            let rect = &result["result"]["value"];
+            if rect.is_null() {
+                return on_captured(Err(format!("nothing matches `{}`", selector)));
+            }
+
+            let mut clip = rect.clone();
+            clip["scale"] = json!(1);
+            capture_png(&browser_, Some(clip), on_captured);
+        }),
+    );
+}
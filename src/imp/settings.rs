@@ -0,0 +1,194 @@
+use std::path::{Path, PathBuf};
+
+/// The kinds of process CEF runs, for scoping command-line switches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessType {
+    /// The main process, which runs `Cef::run`
+    Browser,
+    Renderer,
+    Gpu,
+    Utility,
+}
+
+impl ProcessType {
+    /// The process type from CEF's `--type` switch, which the browser process
+    /// doesn't have
+    pub(crate) fn from_switch(process_type: &str) -> Option<ProcessType> {
+        match process_type {
+            "" => Some(ProcessType::Browser),
+            "renderer" => Some(ProcessType::Renderer),
+            "gpu-process" => Some(ProcessType::Gpu),
+            "utility" => Some(ProcessType::Utility),
+            _ => None,
+        }
+    }
+}
+
+/// A Chromium command-line switch, such as `disable-gpu` or
+/// `touch-events=enabled`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Switch {
+    /// The switch's name, without the leading `--`
+    pub name: String,
+    pub value: Option<String>,
+    /// The only kind of process to add the switch to, or every process if
+    /// this isn't set
+    pub process: Option<ProcessType>,
+}
+
+impl Switch {
+    pub fn new(name: &str) -> Switch {
+        Switch {
+            name: name.trim_start_matches('-').to_string(),
+            value: None,
+            process: None,
+        }
+    }
+
+    pub fn with_value(name: &str, value: &str) -> Switch {
+        Switch {
+            value: Some(value.to_string()),
+            ..Switch::new(name)
+        }
+    }
+
+    /// Only add the switch to processes of this type
+    pub fn only_in(mut self, process: ProcessType) -> Switch {
+        self.process = Some(process);
+        self
+    }
+
+    pub(crate) fn applies_to(&self, process: Option<ProcessType>) -> bool {
+        self.process.is_none() || self.process == process
+    }
+}
+
+impl std::fmt::Display for Switch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "--{}={}", self.name, value),
+            None => write!(f, "--{}", self.name),
+        }
+    }
+}
+
+/// Ready-made sets of switches for common setups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchPreset {
+    /// Touch screens with an on-screen keyboard: the keyboard shows even when
+    /// a USB keyboard is plugged in, touch events are on and pinch-zooming
+    /// and swiping back and forward are off
+    TouchKiosk,
+    /// Render everything in software, for machines without a (working) GPU
+    SoftwareRendering,
+    /// Software rendering, no audio and no throttling of background pages,
+    /// for running in containers on CI servers. On Linux, CEF still needs an
+    /// X server, such as the one `xvfb-run` starts.
+    HeadlessCi,
+}
+
+impl SwitchPreset {
+    pub fn switches(&self) -> Vec<Switch> {
+        match self {
+            SwitchPreset::TouchKiosk => vec![
+                Switch::new("disable-usb-keyboard-detect").only_in(ProcessType::Browser),
+                Switch::with_value("touch-events", "enabled"),
+                Switch::new("disable-pinch"),
+                Switch::with_value("overscroll-history-navigation", "0"),
+            ],
+            SwitchPreset::SoftwareRendering => vec![
+                Switch::new("disable-gpu"),
+                Switch::new("disable-gpu-compositing"),
+            ],
+            SwitchPreset::HeadlessCi => {
+                let mut switches = SwitchPreset::SoftwareRendering.switches();
+                switches.extend(vec![
+                    Switch::new("mute-audio"),
+                    Switch::new("disable-dev-shm-usage"),
+                    Switch::new("disable-background-timer-throttling"),
+                    Switch::new("disable-renderer-backgrounding"),
+                ]);
+                switches
+            }
+        }
+    }
+}
+
+/// How to initialize CEF, passed to `Cef::initialize_with`
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    debug_port: Option<u16>,
+    disable_command_line_args: bool,
+    switches: Vec<Switch>,
+    cache_path: Option<PathBuf>,
+    windowless_rendering: bool,
+}
+
+impl Settings {
+    pub fn new() -> Settings {
+        Settings::default()
+    }
+
+    /// Serve the DevTools protocol on this port
+    pub fn with_debug_port(mut self, port: u16) -> Settings {
+        self.debug_port = Some(port);
+        self
+    }
+
+    /// Ignore the switches the app was started with
+    pub fn without_command_line_args(mut self) -> Settings {
+        self.disable_command_line_args = true;
+        self
+    }
+
+    /// Keep the browser's cache and profile data in `path` rather than in the
+    /// platform's default location
+    pub fn with_cache_path<P: Into<PathBuf>>(mut self, path: P) -> Settings {
+        self.cache_path = Some(path.into());
+        self
+    }
+
+    /// Allow browsers which render off-screen, as opened with
+    /// `Cef::open_windowless` and used by `PdfRenderer`. Off by default, as it
+    /// changes how CEF composites every browser.
+    pub fn with_windowless_rendering(mut self) -> Settings {
+        self.windowless_rendering = true;
+        self
+    }
+
+    /// Add a switch, replacing any switch with the same name for the same
+    /// processes
+    pub fn with_switch(mut self, switch: Switch) -> Settings {
+        self.switches
+            .retain(|s| s.name != switch.name || s.process != switch.process);
+        self.switches.push(switch);
+        self
+    }
+
+    pub fn with_preset(self, preset: SwitchPreset) -> Settings {
+        preset
+            .switches()
+            .into_iter()
+            .fold(self, |settings, switch| settings.with_switch(switch))
+    }
+
+    pub fn debug_port(&self) -> Option<u16> {
+        self.debug_port
+    }
+
+    pub fn command_line_args_disabled(&self) -> bool {
+        self.disable_command_line_args
+    }
+
+    pub fn switches(&self) -> &[Switch] {
+        &self.switches
+    }
+
+    pub fn cache_path(&self) -> Option<&Path> {
+        self.cache_path.as_deref()
+    }
+
+    pub fn windowless_rendering_enabled(&self) -> bool {
+        self.windowless_rendering
+    }
+}
@@ -0,0 +1,372 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::browser::Browser;
+use super::{devtools, strings};
+
+/// A key combination, such as `Ctrl+Shift+I` or `F11`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    /// The command key on macOS, or the Windows / super key elsewhere
+    pub command: bool,
+    /// The Windows virtual key code, which CEF uses on every platform
+    pub key_code: i32,
+}
+
+const KEY_NAMES: &[(&str, i32)] = &[
+    ("Backspace", 0x08),
+    ("Tab", 0x09),
+    ("Enter", 0x0D),
+    ("Return", 0x0D),
+    ("Escape", 0x1B),
+    ("Esc", 0x1B),
+    ("Space", 0x20),
+    ("PageUp", 0x21),
+    ("PageDown", 0x22),
+    ("End", 0x23),
+    ("Home", 0x24),
+    ("Left", 0x25),
+    ("Up", 0x26),
+    ("Right", 0x27),
+    ("Down", 0x28),
+    ("Insert", 0x2D),
+    ("Delete", 0x2E),
+    ("Del", 0x2E),
+    ("Plus", 0xBB),
+    ("=", 0xBB),
+    (",", 0xBC),
+    ("Comma", 0xBC),
+    ("Minus", 0xBD),
+    ("-", 0xBD),
+    (".", 0xBE),
+    ("Period", 0xBE),
+    ("/", 0xBF),
+];
+
+impl KeyCombo {
+    /// Parse a combination such as `Ctrl+Shift+I`, `Alt+Left` or `F11`.
+    /// Modifiers are `Ctrl`, `Shift`, `Alt`, `Cmd` and `CmdOrCtrl` (the command
+    /// key on macOS and control elsewhere); case doesn't matter.
+    pub fn parse(combo: &str) -> Result<KeyCombo, String> {
+        let mut parsed = KeyCombo {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            command: false,
+            key_code: 0,
+        };
+
+        // split on the `+`s between keys, allowing `Ctrl++` to mean `Ctrl+Plus`
+        let mut parts: Vec<&str> = combo.split('+').collect();
+        if combo.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("Plus");
+        }
+        let (key, modifiers) = match parts.split_last() {
+            Some((key, modifiers)) if !key.trim().is_empty() => (key.trim(), modifiers),
+            _ => return Err(format!("`{}` has no key", combo)),
+        };
+
+        for modifier in modifiers {
+            match modifier.trim().to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => parsed.ctrl = true,
+                "shift" => parsed.shift = true,
+                "alt" | "option" => parsed.alt = true,
+                "cmd" | "command" | "meta" | "super" => parsed.command = true,
+                "cmdorctrl" | "commandorcontrol" => {
+                    if cfg!(target_os = "macos") {
+                        parsed.command = true;
+                    } else {
+                        parsed.ctrl = true;
+                    }
+                }
+                other => return Err(format!("unknown modifier `{}` in `{}`", other, combo)),
+            }
+        }
+
+        parsed.key_code = KeyCombo::key_code(key)
+            .ok_or_else(|| format!("unknown key `{}` in `{}`", key, combo))?;
+        Ok(parsed)
+    }
+
+    fn key_code(key: &str) -> Option<i32> {
+        let upper = key.to_ascii_uppercase();
+        let mut chars = upper.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_ascii_uppercase() || c.is_ascii_digit() {
+                return Some(c as i32);
+            }
+        }
+        if let Some(n) = upper.strip_prefix('F').and_then(|n| n.parse::<i32>().ok()) {
+            if (1..=24).contains(&n) {
+                return Some(0x70 + n - 1);
+            }
+        }
+        KEY_NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|&(_, code)| code)
+    }
+}
+
+impl std::fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (pressed, name) in &[
+            (self.ctrl, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+            (self.command, "Cmd+"),
+        ] {
+            if *pressed {
+                write!(f, "{}", name)?;
+            }
+        }
+        match self.key_code {
+            0x30..=0x39 | 0x41..=0x5A => write!(f, "{}", self.key_code as u8 as char),
+            0x70..=0x87 => write!(f, "F{}", self.key_code - 0x70 + 1),
+            code => match KEY_NAMES.iter().find(|&&(_, c)| c == code) {
+                Some((name, _)) => write!(f, "{}", name),
+                None => write!(f, "0x{:02X}", code),
+            },
+        }
+    }
+}
+
+/// What a shortcut does
+#[derive(Clone)]
+pub enum ShortcutAction {
+    /// Call a Rust closure with the window's browser
+    Run(Rc<dyn Fn(&Browser)>),
+    /// Dispatch a `CustomEvent` with this name on the page's `window`, with the
+    /// key combination in `event.detail.shortcut`
+    JsEvent(String),
+    /// Do nothing, which (if the key is swallowed) disables the key
+    Block,
+}
+
+impl std::fmt::Debug for ShortcutAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShortcutAction::Run(_) => write!(f, "Run"),
+            ShortcutAction::JsEvent(name) => f.debug_tuple("JsEvent").field(name).finish(),
+            ShortcutAction::Block => write!(f, "Block"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Shortcut {
+    pub combo: KeyCombo,
+    pub action: ShortcutAction,
+    /// Whether to keep the key from reaching the page
+    pub swallow: bool,
+}
+
+impl Shortcut {
+    pub fn run(&self, browser: &Browser) {
+        log::debug!("running shortcut {}: {:?}", self.combo, self.action);
+        match &self.action {
+            ShortcutAction::Run(run) => run(browser),
+            ShortcutAction::JsEvent(name) => browser.execute_javascript(&format!(
+                "window.dispatchEvent(new CustomEvent({}, {{ detail: {{ shortcut: {} }} }}));",
+                strings::to_js_string_literal(name),
+                strings::to_js_string_literal(&self.combo.to_string()),
+            )),
+            ShortcutAction::Block => {}
+        }
+    }
+}
+
+/// Maps key combinations to actions. Set app-wide shortcuts with
+/// `Cef::set_shortcuts` and per-window shortcuts with `WindowOptions::shortcuts`;
+/// a window's own shortcuts win over app-wide ones. The methods chain, as in
+/// `shortcuts.on("CmdOrCtrl+P", |b| b.print())?.emit("CmdOrCtrl+S", "save")?`.
+#[derive(Debug, Clone, Default)]
+pub struct Shortcuts {
+    shortcuts: Vec<Shortcut>,
+}
+
+impl Shortcuts {
+    pub fn new() -> Shortcuts {
+        Shortcuts::default()
+    }
+
+    /// Add a shortcut, replacing any existing shortcut for the same keys. If
+    /// `swallow` is false the page sees the key press too.
+    pub fn add(
+        &mut self,
+        combo: &str,
+        action: ShortcutAction,
+        swallow: bool,
+    ) -> Result<&mut Shortcuts, String> {
+        let combo = KeyCombo::parse(combo)?;
+        self.shortcuts.retain(|s| s.combo != combo);
+        self.shortcuts.push(Shortcut {
+            combo,
+            action,
+            swallow,
+        });
+        Ok(self)
+    }
+
+    /// Call `run` when the keys are pressed
+    pub fn on<F: Fn(&Browser) + 'static>(
+        &mut self,
+        combo: &str,
+        run: F,
+    ) -> Result<&mut Shortcuts, String> {
+        self.add(combo, ShortcutAction::Run(Rc::new(run)), true)
+    }
+
+    /// Dispatch the JS event `event` on the page's `window` when the keys are
+    /// pressed
+    pub fn emit(&mut self, combo: &str, event: &str) -> Result<&mut Shortcuts, String> {
+        self.add(combo, ShortcutAction::JsEvent(event.to_string()), true)
+    }
+
+    /// Keep the keys from doing anything
+    pub fn block(&mut self, combo: &str) -> Result<&mut Shortcuts, String> {
+        self.add(combo, ShortcutAction::Block, true)
+    }
+
+    /// Block the browser's own shortcuts for reloading, zooming, navigating
+    /// back and forward, printing and opening the developer tools, such as for
+    /// kiosks
+    pub fn block_browser_shortcuts(&mut self) -> &mut Shortcuts {
+        for combo in &[
+            "F5",
+            "Ctrl+F5",
+            "Shift+F5",
+            "CmdOrCtrl+R",
+            "CmdOrCtrl+Shift+R",
+            "CmdOrCtrl+Plus",
+            "CmdOrCtrl+Shift+Plus",
+            "CmdOrCtrl+Minus",
+            "CmdOrCtrl+0",
+            "Alt+Left",
+            "Alt+Right",
+            "CmdOrCtrl+P",
+            "F12",
+            "CmdOrCtrl+Shift+I",
+            "CmdOrCtrl+Shift+J",
+        ] {
+            self.block(combo).expect("browser shortcuts are valid");
+        }
+        self
+    }
+
+    pub fn find(&self, combo: &KeyCombo) -> Option<&Shortcut> {
+        self.shortcuts.iter().find(|s| s.combo == *combo)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Shortcut> {
+        self.shortcuts.iter()
+    }
+}
+
+thread_local! {
+    static GLOBAL: RefCell<Shortcuts> = RefCell::new(Shortcuts::default());
+}
+
+pub fn set_global(shortcuts: Shortcuts) {
+    GLOBAL.with(|g| *g.borrow_mut() = shortcuts);
+}
+
+pub fn global() -> Shortcuts {
+    GLOBAL.with(|g| g.borrow().clone())
+}
+
+/// The shortcut for `combo`, looking at a window's own shortcuts before the
+/// app-wide ones, and those before the DevTools shortcut
+pub fn find(window_shortcuts: &Shortcuts, combo: &KeyCombo) -> Option<Shortcut> {
+    window_shortcuts
+        .find(combo)
+        .cloned()
+        .or_else(|| GLOBAL.with(|g| g.borrow().find(combo).cloned()))
+        .or_else(|| devtools::shortcut().filter(|s| s.combo == *combo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combo(ctrl: bool, shift: bool, alt: bool, key_code: i32) -> KeyCombo {
+        KeyCombo {
+            ctrl,
+            shift,
+            alt,
+            command: false,
+            key_code,
+        }
+    }
+
+    #[test]
+    fn parses_modifiers_and_keys() {
+        assert_eq!(
+            KeyCombo::parse("Ctrl+Shift+I"),
+            Ok(combo(true, true, false, 'I' as i32))
+        );
+        assert_eq!(
+            KeyCombo::parse("alt + left"),
+            Ok(combo(false, false, true, 0x25))
+        );
+        assert_eq!(KeyCombo::parse("F11"), Ok(combo(false, false, false, 0x7A)));
+        assert_eq!(
+            KeyCombo::parse("Control+1"),
+            Ok(combo(true, false, false, '1' as i32))
+        );
+        let save = KeyCombo::parse("CmdOrCtrl+S").unwrap();
+        assert_eq!(save.command, cfg!(target_os = "macos"));
+        assert_eq!(save.ctrl, !cfg!(target_os = "macos"));
+    }
+
+    #[test]
+    fn parses_plus_and_equals() {
+        let zoom_in = combo(true, false, false, 0xBB);
+        assert_eq!(KeyCombo::parse("Ctrl++"), Ok(zoom_in));
+        assert_eq!(KeyCombo::parse("Ctrl+Plus"), Ok(zoom_in));
+        assert_eq!(
+            KeyCombo::parse("Shift+="),
+            Ok(combo(false, true, false, 0xBB))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_modifiers_and_keys() {
+        assert!(KeyCombo::parse("Hyper+A")
+            .unwrap_err()
+            .contains("unknown modifier"));
+        assert!(KeyCombo::parse("Ctrl+Banana")
+            .unwrap_err()
+            .contains("unknown key"));
+        assert!(KeyCombo::parse("F25").unwrap_err().contains("unknown key"));
+    }
+
+    #[test]
+    fn rejects_an_empty_key() {
+        for combo in &["", " ", "Ctrl+", "Ctrl+Shift+ ", "+"] {
+            assert!(
+                KeyCombo::parse(combo).unwrap_err().contains("has no key"),
+                "`{}` should have no key",
+                combo
+            );
+        }
+    }
+
+    #[test]
+    fn displays_combos_which_parse_back() {
+        for combo in &[
+            "Ctrl+Shift+I",
+            "Alt+Left",
+            "F11",
+            "Ctrl+Plus",
+            "Shift+Comma",
+        ] {
+            let parsed = KeyCombo::parse(combo).unwrap();
+            assert_eq!(KeyCombo::parse(&parsed.to_string()), Ok(parsed));
+        }
+    }
+}
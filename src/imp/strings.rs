@@ -0,0 +1,97 @@
+use std::ffi::CString;
+
+use super::bindings::{
+    cef_string_list_alloc, cef_string_list_append, cef_string_list_size, cef_string_list_t,
+    cef_string_list_value, cef_string_t, cef_string_userfree_t, cef_string_userfree_utf16_free,
+    cef_string_utf8_to_utf16,
+};
+
+/// Convert a Rust string into a (CEF-owned) UTF-16 cef string
+pub fn to_cef_string(s: &str) -> cef_string_t {
+    let s = CString::new(s.as_bytes()).unwrap_or_default();
+    let mut cef_s = cef_string_t::default();
+    unsafe {
+        cef_string_utf8_to_utf16(s.as_ptr(), s.to_bytes().len() as u64, &mut cef_s);
+    }
+    cef_s
+}
+
+/// Convert a borrowed cef string into a Rust string. Null pointers are
+/// treated as empty strings.
+pub unsafe fn from_cef_string(s: *const cef_string_t) -> String {
+    if s.is_null() || (*s).str_.is_null() {
+        return String::new();
+    }
+    let chars: *mut u16 = (*s).str_;
+    let len: usize = (*s).length as usize;
+    let chars = std::slice::from_raw_parts(chars, len);
+    std::char::decode_utf16(chars.iter().cloned())
+        .map(|r| r.unwrap_or(std::char::REPLACEMENT_CHARACTER))
+        .collect::<String>()
+}
+
+/// Convert a userfree cef string into a Rust string, freeing the cef string
+/// in the process. Null pointers are treated as empty strings.
+pub unsafe fn from_userfree(s: cef_string_userfree_t) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    let string = from_cef_string(s);
+    cef_string_userfree_utf16_free(s);
+    string
+}
+
+/// Copy the contents of a cef string list into Rust strings. Null lists are
+/// treated as empty.
+pub unsafe fn from_string_list(list: cef_string_list_t) -> Vec<String> {
+    if list.is_null() {
+        return Vec::new();
+    }
+    (0..cef_string_list_size(list))
+        .filter_map(|i| {
+            let mut value = cef_string_t::default();
+            if cef_string_list_value(list, i, &mut value) == 1 {
+                let s = from_cef_string(&value);
+                if let Some(dtor) = value.dtor {
+                    dtor(value.str_);
+                }
+                Some(s)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Build a cef string list out of Rust strings. The caller is responsible for
+/// freeing the list with `cef_string_list_free`.
+pub unsafe fn to_string_list(strings: &[String]) -> cef_string_list_t {
+    let list = cef_string_list_alloc();
+    for s in strings.iter() {
+        let cef_s = to_cef_string(s);
+        cef_string_list_append(list, &cef_s);
+    }
+    list
+}
+
+/// Quote and escape `s` so it can be embedded in JavaScript code as a string
+/// literal
+pub fn to_js_string_literal(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            // these end lines in JS (and `<` keeps `</script>` from ending a script tag)
+            '\u{2028}' | '\u{2029}' | '<' => literal.push_str(&format!("\\u{:04x}", c as u32)),
+            c if (c as u32) < 0x20 => literal.push_str(&format!("\\u{:04x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
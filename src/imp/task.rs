@@ -0,0 +1,94 @@
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_post_delayed_task, cef_post_task, cef_task_t,
+    cef_thread_id_t_TID_UI,
+};
+
+#[repr(C)]
+pub struct Task {
+    task: cef_task_t,
+    ref_count: AtomicUsize,
+    callback: Option<Box<dyn FnOnce()>>,
+}
+
+unsafe extern "C" fn execute(slf: *mut cef_task_t) {
+    let task = slf as *mut Task;
+    if let Some(callback) = (*task).callback.take() {
+        callback();
+    }
+}
+
+fn allocate(callback: Box<dyn FnOnce()>) -> *mut Task {
+    let task = Task {
+        task: cef_task_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<Task>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            execute: Some(execute),
+        },
+        ref_count: AtomicUsize::new(1),
+        callback: Some(callback),
+    };
+
+    Box::into_raw(Box::from(task))
+}
+
+/// Run `callback` on the browser process' UI thread as soon as possible
+pub fn post_task<F: FnOnce() + 'static>(callback: F) -> bool {
+    let task = allocate(Box::from(callback));
+    unsafe { cef_post_task(cef_thread_id_t_TID_UI, task as *mut cef_task_t) == 1 }
+}
+
+/// Run `callback` on the browser process' UI thread after `delay_ms` milliseconds
+pub fn post_delayed_task<F: FnOnce() + 'static>(callback: F, delay_ms: i64) -> bool {
+    let task = allocate(Box::from(callback));
+    unsafe { cef_post_delayed_task(cef_thread_id_t_TID_UI, task as *mut cef_task_t, delay_ms) == 1 }
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let task = base as *mut Task;
+    unsafe {
+        (*task).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let task = base as *mut Task;
+    let count = unsafe { (*task).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(task);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let task = base as *mut Task;
+    let count = unsafe { (*task).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let task = base as *mut Task;
+    let count = unsafe { (*task).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,358 @@
+//! A headless harness for integration-testing pages which use the `cef.*`
+//! extensions, without a GPU and without showing any windows.
+//!
+//! Only available with the `testing` feature.
+//!
+//! CEF runs its renderer and GPU processes by starting the current executable
+//! again with extra switches, which libtest doesn't understand, so test
+//! targets using this need `harness = false` and a `main` which calls `run`
+//! first thing:
+//!
+//! ```toml
+//! [[test]]
+//! name = "pages"
+//! harness = false
+//! ```
+//!
+//! This doesn't remove the need for a display: CEF still needs an X server on
+//! Linux, even when nothing is shown, and `Harness::initialize` fails without
+//! one. On a CI box without a display, run the tests under `xvfb-run`.
+
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use super::bindings::{cef_do_message_loop_work, cef_shutdown};
+use super::browser::Browser;
+use super::client::ClientHooks;
+use super::devtools_protocol;
+use super::dialog_handler::AutomatedDialogs;
+use super::js_dialog_handler::LogOnly;
+use super::load_handler::OnLoaded;
+use super::offscreen::{self, Frame, FrameSink, WindowlessOptions};
+use super::settings::{Settings, SwitchPreset};
+
+pub use super::display_handler::ConsoleMessage;
+
+/// A test, which fails by panicking
+pub type Test = fn(&Harness);
+
+/// Run `tests`, libtest-style, and exit with a non-zero code if any of them
+/// failed. The first argument which isn't a switch filters the tests by name.
+pub fn run(tests: &[(&str, Test)]) -> ! {
+    let harness = match Harness::initialize() {
+        Ok(harness) => harness,
+        Err(e) => {
+            eprintln!("failed to initialize CEF: {}", e);
+            std::process::exit(101);
+        }
+    };
+
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    let tests: Vec<&(&str, Test)> = tests
+        .iter()
+        .filter(|(name, _)| filter.as_ref().map_or(true, |f| name.contains(f.as_str())))
+        .collect();
+
+    println!("\nrunning {} tests", tests.len());
+    let mut failed = Vec::new();
+    for (name, test) in tests.iter() {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| test(&harness)));
+        if result.is_ok() {
+            println!("test {} ... ok", name);
+        } else {
+            println!("test {} ... FAILED", name);
+            failed.push(*name);
+        }
+    }
+    let passed = tests.len() - failed.len();
+    harness.finish();
+
+    if failed.is_empty() {
+        println!("\ntest result: ok. {} passed; 0 failed\n", passed);
+        std::process::exit(0);
+    }
+    println!("\nfailures:");
+    for name in failed.iter() {
+        println!("    {}", name);
+    }
+    println!(
+        "\ntest result: FAILED. {} passed; {} failed\n",
+        passed,
+        failed.len()
+    );
+    std::process::exit(101);
+}
+
+/// CEF, initialized for running tests: software rendering and a throwaway
+/// cache directory, which `finish` removes again
+pub struct Harness {
+    cache_dir: PathBuf,
+    timeout: Duration,
+    open_pages: Rc<Cell<usize>>,
+    next_page: Cell<usize>,
+}
+
+impl Harness {
+    /// Initialize CEF. Like `Cef::initialize_with`, this is where CEF's other
+    /// processes run and exit, so call it before anything else.
+    pub fn initialize() -> Result<Harness, Box<dyn std::error::Error>> {
+        // CEF talks to X11 on Linux even when every browser is windowless, and
+        // crashes without a display
+        if cfg!(target_os = "linux") && std::env::var_os("DISPLAY").is_none() {
+            return Err("CEF needs an X server; run the tests under `xvfb-run`".into());
+        }
+        let cache_dir =
+            std::env::temp_dir().join(format!("cef-simple-test-{}", std::process::id()));
+        crate::Cef::initialize_with(
+            Settings::new()
+                .with_preset(SwitchPreset::HeadlessCi)
+                .with_windowless_rendering()
+                .with_cache_path(&cache_dir),
+        )?;
+        std::fs::create_dir_all(&cache_dir)?;
+
+        Ok(Harness {
+            cache_dir,
+            timeout: Duration::from_secs(30),
+            open_pages: Rc::new(Cell::new(0)),
+            next_page: Cell::new(0),
+        })
+    }
+
+    /// How long to wait for pages to load and for `eval` and the other
+    /// `wait_for_*` methods, 30 seconds by default
+    pub fn with_timeout(mut self, timeout: Duration) -> Harness {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Load `url` into a new windowless browser, waiting for it to load
+    pub fn load_url(&self, url: &str) -> Result<TestPage, String> {
+        let page_id = self.next_page.get();
+        self.next_page.set(page_id + 1);
+        let pdf_directory = self.cache_dir.join(format!("pdfs-{}", page_id));
+        std::fs::create_dir_all(&pdf_directory)
+            .map_err(|e| format!("failed to create `{}`: {}", pdf_directory.display(), e))?;
+
+        let state = Rc::new(PageState::default());
+        let on_loaded: OnLoaded = {
+            let state = state.clone();
+            Box::from(move |_browser: Browser, result: Result<(), String>| {
+                state.loads.set(state.loads.get() + 1);
+                *state.load_result.borrow_mut() = Some(result);
+            })
+        };
+        let on_console_message = {
+            let state = state.clone();
+            Rc::new(move |message: &ConsoleMessage| {
+                state.console.borrow_mut().push(message.clone())
+            })
+        };
+        let on_closed = {
+            let open_pages = self.open_pages.clone();
+            Box::from(move || open_pages.set(open_pages.get() - 1))
+        };
+
+        let dialogs = Rc::new(AutomatedDialogs::new());
+        let mut options = WindowlessOptions::new(url, Rc::new(NullSink));
+        options.dialog_handler = Some(dialogs.clone());
+        options.js_dialog_handler = Some(Rc::new(LogOnly));
+        let browser = offscreen::create_browser_with(
+            options,
+            Some(on_loaded),
+            ClientHooks {
+                on_console_message: Some(on_console_message),
+                on_closed: Some(on_closed),
+                pdf_directory: Some(pdf_directory.clone()),
+                ..ClientHooks::default()
+            },
+        )
+        .ok_or_else(|| "failed to create windowless browser".to_string())?;
+        self.open_pages.set(self.open_pages.get() + 1);
+
+        let page = TestPage {
+            browser,
+            state,
+            dialogs,
+            pdf_directory,
+            timeout: self.timeout,
+        };
+        page.wait_for_load(0)?;
+        Ok(page)
+    }
+
+    /// Load an HTML document into a new windowless browser, waiting for it
+    /// to load
+    pub fn load_html(&self, html: &str) -> Result<TestPage, String> {
+        self.load_url(&format!("data:text/html;base64,{}", base64::encode(html)))
+    }
+
+    /// Wait for the pages to close, shut CEF down and remove the cache
+    /// directory
+    pub fn finish(self) {
+        let open_pages = self.open_pages.clone();
+        if pump_until(self.timeout, "pages to close", || {
+            if open_pages.get() == 0 {
+                Some(())
+            } else {
+                None
+            }
+        })
+        .is_err()
+        {
+            log::warn!("{} test pages didn't close", open_pages.get());
+        }
+
+        unsafe { cef_shutdown() };
+        if let Err(e) = std::fs::remove_dir_all(&self.cache_dir) {
+            log::warn!("failed to remove `{}`: {}", self.cache_dir.display(), e);
+        }
+    }
+}
+
+#[derive(Default)]
+struct PageState {
+    loads: Cell<usize>,
+    load_result: RefCell<Option<Result<(), String>>>,
+    console: RefCell<Vec<ConsoleMessage>>,
+}
+
+/// A page loaded by a `Harness`, which closes when it's dropped
+pub struct TestPage {
+    browser: Browser,
+    state: Rc<PageState>,
+    dialogs: Rc<AutomatedDialogs>,
+    pdf_directory: PathBuf,
+    timeout: Duration,
+}
+
+impl TestPage {
+    pub fn browser(&self) -> &Browser {
+        &self.browser
+    }
+
+    /// Navigate to `url`, waiting for it to load
+    pub fn navigate(&self, url: &str) -> Result<(), String> {
+        let loads = self.state.loads.get();
+        self.browser.load_url(url);
+        self.wait_for_load(loads)
+    }
+
+    /// Evaluate a JavaScript expression in the page and return its value as
+    /// JSON, waiting for it to resolve if it's a promise. Thrown exceptions
+    /// and rejections are returned as errors.
+    pub fn eval(&self, expression: &str) -> Result<Value, String> {
+        let result: Rc<RefCell<Option<Result<Value, String>>>> = Rc::default();
+        let on_result = result.clone();
+        devtools_protocol::execute(
+            &self.browser,
+            "Runtime.evaluate",
+            json!({ "expression": expression, "returnByValue": true, "awaitPromise": true }),
+            Box::from(move |r: Result<Value, String>| *on_result.borrow_mut() = Some(r)),
+        );
+        let response = pump_until(self.timeout, "`eval` to finish", || {
+            result.borrow_mut().take()
+        })??;
+
+        if let Some(exception) = response.get("exceptionDetails") {
+            let description = exception["exception"]["description"]
+                .as_str()
+                .or_else(|| exception["text"].as_str())
+                .unwrap_or("uncaught exception");
+            return Err(description.to_string());
+        }
+        Ok(response["result"]["value"].clone())
+    }
+
+    /// Everything the page has logged to the console so far
+    pub fn console(&self) -> Vec<ConsoleMessage> {
+        self.state.console.borrow().clone()
+    }
+
+    pub fn clear_console(&self) {
+        self.state.console.borrow_mut().clear();
+    }
+
+    /// Wait for the page to log a console message containing `text`
+    pub fn wait_for_console(&self, text: &str) -> Result<ConsoleMessage, String> {
+        pump_until(self.timeout, &format!("`{}` in the console", text), || {
+            self.state
+                .console
+                .borrow()
+                .iter()
+                .find(|m| m.message.contains(text))
+                .cloned()
+        })
+    }
+
+    /// Answers the page's file dialogs; queue answers with
+    /// `AutomatedDialogs::answer` before the page opens a dialog. Dialogs
+    /// without an answer are cancelled.
+    pub fn file_dialogs(&self) -> &AutomatedDialogs {
+        &self.dialogs
+    }
+
+    /// Where PDFs printed with `cef.printToPDF` end up. Only the file name
+    /// the page asks for is kept, so pages can't write anywhere else.
+    pub fn pdf_directory(&self) -> &Path {
+        &self.pdf_directory
+    }
+
+    /// The PDF the page printed with `cef.printToPDF` to `file_name`, if any
+    pub fn printed_pdf(&self, file_name: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.pdf_directory.join(file_name)).ok()
+    }
+
+    /// Wait until the page has finished more than `loads` loads
+    fn wait_for_load(&self, loads: usize) -> Result<(), String> {
+        pump_until(self.timeout, "the page to load", || {
+            if self.state.loads.get() > loads {
+                self.state.load_result.borrow_mut().take()
+            } else {
+                None
+            }
+        })?
+    }
+}
+
+impl Drop for TestPage {
+    fn drop(&mut self) {
+        self.browser.close(true);
+    }
+}
+
+/// Throws away everything a test page paints
+struct NullSink;
+
+impl FrameSink for NullSink {
+    fn view_size(&self) -> (i32, i32) {
+        (1280, 720)
+    }
+
+    fn on_frame(&self, _frame: &Frame) {}
+}
+
+/// Run CEF's message loop until `poll` returns something, or give up after
+/// `timeout`
+fn pump_until<T, F: FnMut() -> Option<T>>(
+    timeout: Duration,
+    what: &str,
+    mut poll: F,
+) -> Result<T, String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(value) = poll() {
+            return Ok(value);
+        }
+        if Instant::now() >= deadline {
+            return Err(format!("timed out waiting for {}", what));
+        }
+        unsafe { cef_do_message_loop_work() };
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,154 @@
+use std::cell::Cell;
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_begin_tracing, cef_end_tracing, cef_end_tracing_callback_t,
+    cef_string_t,
+};
+use super::strings;
+
+/// Called with the path of the trace file once it has been written, or `None`
+/// if tracing failed
+pub type OnTraceWritten = Box<dyn FnMut(Option<PathBuf>)>;
+
+thread_local! {
+    static TRACING: Cell<bool> = Cell::new(false);
+}
+
+pub fn is_tracing() -> bool {
+    TRACING.with(|t| t.get())
+}
+
+/// Start recording a trace of `categories` in every process. An empty list
+/// records CEF's default categories; categories can be excluded with a `-`
+/// prefix, and `disabled-by-default-*` ones must be named explicitly.
+pub fn begin(categories: &[&str]) -> Result<(), String> {
+    if is_tracing() {
+        return Err("already tracing".to_string());
+    }
+    let categories = strings::to_cef_string(&categories.join(","));
+    if unsafe { cef_begin_tracing(&categories, std::ptr::null_mut()) } != 1 {
+        return Err("failed to begin tracing".to_string());
+    }
+    TRACING.with(|t| t.set(true));
+    log::debug!("began tracing");
+    Ok(())
+}
+
+/// Stop tracing and write the trace to `path` as Chrome trace event JSON,
+/// which `chrome://tracing` and Perfetto open. Without a path, CEF writes it
+/// to a new temporary file.
+pub fn end(path: Option<PathBuf>, mut on_done: Option<OnTraceWritten>) {
+    if !is_tracing() {
+        log::warn!("can't end tracing, it hasn't begun");
+        if let Some(on_done) = on_done.as_mut() {
+            on_done(None);
+        }
+        return;
+    }
+
+    let cef_path = path
+        .as_ref()
+        .map(|path| strings::to_cef_string(&path.display().to_string()))
+        .unwrap_or_default();
+    let callback = allocate(on_done);
+    unsafe {
+        // CEF takes one reference to the callback; keep ours until we know
+        // whether it will be called
+        add_ref(callback as *mut cef_base_ref_counted_t);
+        let ended = cef_end_tracing(&cef_path, callback as *mut cef_end_tracing_callback_t);
+        if ended == 1 {
+            TRACING.with(|t| t.set(false));
+            log::debug!("ending tracing");
+        } else {
+            log::error!("failed to end tracing");
+            // the callback is never called, so report the failure ourselves
+            if let Some(on_done) = (*callback).on_done.as_mut() {
+                on_done(None);
+            }
+        }
+        release(callback as *mut cef_base_ref_counted_t);
+    }
+}
+
+#[repr(C)]
+struct EndTracingCallback {
+    callback: cef_end_tracing_callback_t,
+    ref_count: AtomicUsize,
+    on_done: Option<OnTraceWritten>,
+}
+
+unsafe extern "C" fn on_end_tracing_complete(
+    slf: *mut cef_end_tracing_callback_t,
+    tracing_file: *const cef_string_t,
+) {
+    let callback = slf as *mut EndTracingCallback;
+    let path = PathBuf::from(strings::from_cef_string(tracing_file));
+    log::info!("wrote trace to `{}`", path.display());
+    if let Some(on_done) = (*callback).on_done.as_mut() {
+        on_done(Some(path));
+    }
+}
+
+fn allocate(on_done: Option<OnTraceWritten>) -> *mut EndTracingCallback {
+    let callback = EndTracingCallback {
+        callback: cef_end_tracing_callback_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<EndTracingCallback>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            on_end_tracing_complete: Some(on_end_tracing_complete),
+        },
+        ref_count: AtomicUsize::new(1),
+        on_done,
+    };
+
+    Box::into_raw(Box::from(callback))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let callback = base as *mut EndTracingCallback;
+    unsafe {
+        (*callback).ref_count.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut EndTracingCallback;
+    let count = unsafe { (*callback).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            Box::from_raw(callback);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut EndTracingCallback;
+    let count = unsafe { (*callback).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let callback = base as *mut EndTracingCallback;
+    let count = unsafe { (*callback).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
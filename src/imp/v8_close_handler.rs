@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_process_message_t, cef_string_t,
+    cef_v8context_get_current_context, cef_v8context_t, cef_v8handler_t, cef_v8value_t, size_t,
+};
+use super::{strings, v8_values};
+
+#[repr(C)]
+pub struct V8CloseHandler {
+    v8_handler: cef_v8handler_t,
+    ref_count: AtomicUsize,
+    pub browser: Option<*mut cef_browser_t>,
+    /// The wrapped `cef.onCloseRequested` handler of each browser (by id), and
+    /// the context it belongs to. Popups share their opener's renderer process,
+    /// so there can be several.
+    close_requested: BTreeMap<c_int, (*mut cef_v8context_t, *mut cef_v8value_t)>,
+}
+
+const CODE: &str = r#"
+    var cef;
+    if(!cef) cef = {};
+    (function() {
+        native function setCloseRequestedHandler(handler);
+        native function closeRequestedDone(allow);
+        cef.onCloseRequested = function(handler) {
+            if(typeof handler !== "function") {
+                setCloseRequestedHandler(null);
+                return;
+            }
+            setCloseRequestedHandler(() => {
+                Promise.resolve()
+                    .then(() => handler())
+                    .then((allow) => closeRequestedDone(allow !== false), (error) => {
+                        // a broken handler shouldn't trap the user in the window
+                        console.error("onCloseRequested handler failed, closing anyway:", error);
+                        closeRequestedDone(true);
+                    });
+            });
+        };
+        console.info("registered onCloseRequested CEF extension");
+    })();
+"#;
+
+pub unsafe fn register_extension(extension: *mut V8CloseHandler) {
+    use super::bindings::cef_register_extension;
+    let cef_code = strings::to_cef_string(CODE);
+    let cef_extension_name = strings::to_cef_string("CEF Close Requests");
+
+    cef_register_extension(
+        &cef_extension_name,
+        &cef_code,
+        extension as *mut cef_v8handler_t,
+    );
+    log::debug!("registered close requests extension");
+}
+
+pub unsafe fn process_message(
+    slf: *mut V8CloseHandler,
+    browser: *mut cef_browser_t,
+    message_name: &str,
+    _message: *mut cef_process_message_t,
+) -> bool {
+    if message_name != "close_requested" {
+        return false;
+    }
+
+    match (*slf).close_requested.get(&browser_id(browser)).cloned() {
+        Some((context, handler))
+            if ((*context).is_valid.expect("is_valid is a function"))(context) == 1 =>
+        {
+            log::debug!("asking the page whether it can close");
+            ((*context).enter.expect("enter is a function"))(context);
+            ((*handler)
+                .execute_function
+                .expect("execute_function is a function"))(
+                handler,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+            );
+            ((*context).exit.expect("exit is a function"))(context);
+        }
+        _ => {
+            // the page which registered the handler has gone away
+            log::debug!("no close handler in the page, allowing close");
+            send_to_browser(Some(browser), "close_requested_done", true);
+        }
+    }
+
+    true
+}
+
+/// Forget the close handler registered in `context`, which is going away
+pub unsafe fn context_released(
+    slf: *mut V8CloseHandler,
+    browser: *mut cef_browser_t,
+    context: *mut cef_v8context_t,
+) {
+    let browser_id = browser_id(browser);
+    let registered_here = match (*slf).close_requested.get(&browser_id) {
+        Some(&(handler_context, _)) => {
+            ((*handler_context).is_same.expect("is_same is a function"))(handler_context, context)
+                == 1
+        }
+        None => false,
+    };
+    if registered_here {
+        set_close_requested(slf, browser_id, None);
+    }
+}
+
+/// Forget the close handler of a browser which has been destroyed
+pub unsafe fn browser_destroyed(slf: *mut V8CloseHandler, browser: *mut cef_browser_t) {
+    set_close_requested(slf, browser_id(browser), None);
+}
+
+unsafe fn browser_id(browser: *mut cef_browser_t) -> c_int {
+    (*browser)
+        .get_identifier
+        .expect("get_identifier is a function")(browser)
+}
+
+/// The browser whose page is calling into the extension
+unsafe fn calling_browser(slf: *mut V8CloseHandler) -> Option<*mut cef_browser_t> {
+    v8_values::current_browser().or((*slf).browser)
+}
+
+unsafe fn send_to_browser(browser: Option<*mut cef_browser_t>, message_name: &str, value: bool) {
+    if let Some(browser) = browser {
+        let frame = (*browser)
+            .get_main_frame
+            .expect("get_main_frame is a function")(browser);
+
+        let cef_message_name = strings::to_cef_string(message_name);
+        let message = super::bindings::cef_process_message_create(&cef_message_name);
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        ((*args).set_size.expect("set_size is a function"))(args, 1);
+        ((*args).set_bool.expect("set_bool is a function"))(args, 0, value as i32);
+
+        ((*frame)
+            .send_process_message
+            .expect("send_process_message is a function"))(
+            frame,
+            super::bindings::cef_process_id_t_PID_BROWSER,
+            message,
+        );
+    } else {
+        log::error!("browser isn't set!");
+    }
+}
+
+unsafe fn set_close_requested(
+    slf: *mut V8CloseHandler,
+    browser_id: c_int,
+    close_requested: Option<(*mut cef_v8context_t, *mut cef_v8value_t)>,
+) {
+    // hold on to the handler for as long as it's registered
+    if let Some((context, handler)) = close_requested {
+        (*context).base.add_ref.expect("add_ref is a function")(
+            context as *mut cef_base_ref_counted_t,
+        );
+        (*handler).base.add_ref.expect("add_ref is a function")(
+            handler as *mut cef_base_ref_counted_t,
+        );
+    }
+    if let Some((context, handler)) = (*slf).close_requested.remove(&browser_id) {
+        (*context).base.release.expect("release is a function")(
+            context as *mut cef_base_ref_counted_t,
+        );
+        (*handler).base.release.expect("release is a function")(
+            handler as *mut cef_base_ref_counted_t,
+        );
+    }
+    if let Some(close_requested) = close_requested {
+        (*slf).close_requested.insert(browser_id, close_requested);
+    }
+}
+
+unsafe extern "C" fn execute(
+    slf: *mut cef_v8handler_t,
+    name: *const cef_string_t,
+    _object: *mut cef_v8value_t,
+    arguments_count: size_t,
+    arguments: *const *mut cef_v8value_t,
+    _retval: *mut *mut cef_v8value_t,
+    _exception: *mut cef_string_t,
+) -> c_int {
+    let name = strings::from_cef_string(name);
+    log::debug!(
+        "native call to function: {} with {} arguments",
+        name,
+        arguments_count
+    );
+
+    let _self = slf as *mut V8CloseHandler;
+    let browser = calling_browser(_self);
+    if name == "setCloseRequestedHandler" && arguments_count == 1 {
+        let browser_id = match browser {
+            Some(browser) => browser_id(browser),
+            None => {
+                log::error!("browser isn't set!");
+                return 1;
+            }
+        };
+        let handler: *mut cef_v8value_t = *arguments;
+        if ((*handler).is_function.expect("is_function is a function"))(handler) == 1 {
+            set_close_requested(
+                _self,
+                browser_id,
+                Some((cef_v8context_get_current_context(), handler)),
+            );
+            send_to_browser(browser, "close_requested_listener", true);
+        } else {
+            set_close_requested(_self, browser_id, None);
+            send_to_browser(browser, "close_requested_listener", false);
+        }
+        1
+    } else if name == "closeRequestedDone" && arguments_count == 1 {
+        let allow: *mut cef_v8value_t = *arguments;
+        let allow = ((*allow)
+            .get_bool_value
+            .expect("get_bool_value is a function"))(allow)
+            == 1;
+        log::debug!("page answered close request: {}", allow);
+        send_to_browser(browser, "close_requested_done", allow);
+        1
+    } else {
+        log::warn!(
+            "unrecognized function: `{}` with {} args, skipping",
+            name,
+            arguments_count
+        );
+        0
+    }
+}
+
+pub fn allocate() -> *mut V8CloseHandler {
+    let handler = V8CloseHandler {
+        v8_handler: cef_v8handler_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<V8CloseHandler>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            execute: Some(execute),
+        },
+        ref_count: AtomicUsize::new(1),
+        browser: None,
+        close_requested: BTreeMap::new(),
+    };
+
+    Box::into_raw(Box::from(handler))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let v8_handler = base as *mut V8CloseHandler;
+    unsafe { (*v8_handler).ref_count.fetch_add(1, Ordering::SeqCst) };
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let v8_handler = base as *mut V8CloseHandler;
+    let count = unsafe { (*v8_handler).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            log::debug!("dropping close v8 handler!");
+            let browser_ids: Vec<c_int> = (*v8_handler).close_requested.keys().cloned().collect();
+            for browser_id in browser_ids {
+                set_close_requested(v8_handler, browser_id, None);
+            }
+            Box::from_raw(v8_handler);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let v8_handler = base as *mut V8CloseHandler;
+    let count = unsafe { (*v8_handler).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let v8_handler = base as *mut V8CloseHandler;
+    let count = unsafe { (*v8_handler).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
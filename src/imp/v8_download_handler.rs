@@ -0,0 +1,187 @@
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_string_t, cef_v8handler_t, cef_v8value_t, size_t,
+};
+use super::{strings, v8_values};
+
+#[repr(C)]
+pub struct V8DownloadHandler {
+    v8_handler: cef_v8handler_t,
+    ref_count: AtomicUsize,
+    pub browser: Option<*mut cef_browser_t>,
+}
+
+const CODE: &str = r#"
+    var cef;
+    if(!cef) cef = {};
+    (function() {
+        native function downloadCommand(command, id);
+        // every update to the downloads this page starts, as "updated"
+        // events whose `detail` is the download, for showing a download tray
+        var downloads = new EventTarget();
+        var latest = {};
+        downloads.all = function() {
+            return Object.keys(latest).map(function(id) { return latest[id]; });
+        };
+        downloads.cancel = function(id) { downloadCommand("cancel", String(id)); };
+        downloads.pause = function(id) { downloadCommand("pause", String(id)); };
+        downloads.resume = function(id) { downloadCommand("resume", String(id)); };
+        // called by the browser process
+        downloads._update = function(download) {
+            latest[download.id] = download;
+            downloads.dispatchEvent(new CustomEvent("updated", { detail: download }));
+        };
+        cef.downloads = downloads;
+        console.info("registered downloads CEF extension");
+    })();
+"#;
+
+pub unsafe fn register_extension(extension: *mut V8DownloadHandler) {
+    use super::bindings::cef_register_extension;
+    let cef_code = strings::to_cef_string(CODE);
+    let cef_extension_name = strings::to_cef_string("CEF Downloads");
+
+    cef_register_extension(
+        &cef_extension_name,
+        &cef_code,
+        extension as *mut cef_v8handler_t,
+    );
+    log::debug!("registered downloads extension");
+}
+
+unsafe extern "C" fn execute(
+    slf: *mut cef_v8handler_t,
+    name: *const cef_string_t,
+    _object: *mut cef_v8value_t,
+    arguments_count: size_t,
+    arguments: *const *mut cef_v8value_t,
+    _retval: *mut *mut cef_v8value_t,
+    _exception: *mut cef_string_t,
+) -> c_int {
+    let name = strings::from_cef_string(name);
+    log::debug!(
+        "native call to function: {} with {} arguments",
+        name,
+        arguments_count
+    );
+
+    let command = if name == "downloadCommand" && arguments_count == 2 {
+        match (
+            v8_values::as_string(*arguments),
+            v8_values::as_string(*arguments.add(1)),
+        ) {
+            (Some(command), Some(id)) => Some((command, id)),
+            _ => None,
+        }
+    } else {
+        None
+    };
+    let (command, id) = match command {
+        Some(command) => command,
+        None => {
+            log::warn!(
+                "unrecognized function: `{}` with {} args, skipping",
+                name,
+                arguments_count
+            );
+            return 0;
+        }
+    };
+
+    let _self = slf as *mut V8DownloadHandler;
+    if let Some(browser) = v8_values::current_browser().or((*_self).browser) {
+        let frame = (*browser)
+            .get_main_frame
+            .expect("get_main_frame is a function")(browser);
+
+        let cef_message_name = strings::to_cef_string("download_command");
+        let message = super::bindings::cef_process_message_create(&cef_message_name);
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        ((*args).set_size.expect("set_size is a function"))(args, 2);
+        ((*args).set_string.expect("set_string is a function"))(
+            args,
+            0,
+            &strings::to_cef_string(&command),
+        );
+        ((*args).set_string.expect("set_string is a function"))(
+            args,
+            1,
+            &strings::to_cef_string(&id),
+        );
+
+        ((*frame)
+            .send_process_message
+            .expect("send_process_message is a function"))(
+            frame,
+            super::bindings::cef_process_id_t_PID_BROWSER,
+            message,
+        );
+    } else {
+        log::error!("browser isn't set!");
+    }
+    1
+}
+
+pub fn allocate() -> *mut V8DownloadHandler {
+    let handler = V8DownloadHandler {
+        v8_handler: cef_v8handler_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<V8DownloadHandler>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            execute: Some(execute),
+        },
+        ref_count: AtomicUsize::new(1),
+        browser: None,
+    };
+
+    Box::into_raw(Box::from(handler))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let v8_handler = base as *mut V8DownloadHandler;
+    unsafe { (*v8_handler).ref_count.fetch_add(1, Ordering::SeqCst) };
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let v8_handler = base as *mut V8DownloadHandler;
+    let count = unsafe { (*v8_handler).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            log::debug!("dropping downloads v8 handler!");
+            Box::from_raw(v8_handler);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let v8_handler = base as *mut V8DownloadHandler;
+    let count = unsafe { (*v8_handler).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let v8_handler = base as *mut V8DownloadHandler;
+    let count = unsafe { (*v8_handler).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
@@ -4,15 +4,11 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::bindings::{
     cef_base_ref_counted_t, cef_browser_t, cef_process_message_t, cef_string_t,
-    cef_string_userfree_t, cef_string_userfree_utf16_free, cef_v8context_get_current_context,
-    cef_v8context_t, cef_v8handler_t, cef_v8value_create_string, cef_v8value_t, size_t,
+    cef_v8context_get_current_context, cef_v8context_t, cef_v8handler_t, cef_v8value_create_int,
+    cef_v8value_create_string, cef_v8value_t, size_t,
 };
-
-#[derive(Debug)]
-pub enum FileDialogMode {
-    Open,
-    Save,
-}
+use super::file_dialog::FileDialogOptions;
+use super::{strings, v8_values};
 
 #[repr(C)]
 pub struct V8FileDialogHandler {
@@ -26,21 +22,43 @@ const CODE: &str = r#"
     var cef;
     if(!cef) cef = {};
     (function() {
-        cef.saveFileDialog = function(title, defaultFileName, filter) {
-            native function saveFileDialog(title, defaultFileName, filter, onDone, onError);
+        cef.runFileDialog = function(options) {
+            native function runFileDialog(options, onDone, onCancel);
+            options = options || {};
+            var filters = typeof options.filters === "string" ? [options.filters] : (options.filters || []);
             return new Promise((resolve, reject) => {
-                console.debug("running native saveFileDialog...");
-                saveFileDialog(title, defaultFileName, filter, resolve, reject);
+                console.debug("running native file dialog...");
+                runFileDialog(options, (filterIndex, ...paths) => {
+                    resolve({ paths: paths, path: paths[0], filterIndex: filterIndex, filter: filters[filterIndex] });
+                }, () => {
+                    // cancelling rejects with an AbortError, so it can be told apart from other errors
+                    reject(new DOMException("the file dialog was cancelled", "AbortError"));
+                });
             });
         };
-        cef.openFileDialog = function(title, defaultFileName, filter) {
-            native function openFileDialog(title, defaultFileName, filter, onDone, onError);
-            return new Promise((resolve, reject) => {
-                console.debug("running native openFileDialog...");
-                openFileDialog(title, defaultFileName, filter, resolve, reject);
-            });
+        cef.isCancelled = function(error) {
+            return error instanceof DOMException && error.name === "AbortError";
         };
-        console.info("registered saveFileDialog and openFileDialog CEF extensions");
+        cef.saveFileDialog = function(title, defaultFileName, filters, options) {
+            options = options || {};
+            return cef.runFileDialog({
+                mode: "save",
+                title: title,
+                defaultFileName: defaultFileName,
+                filters: filters,
+                overwritePrompt: !!options.overwritePrompt,
+            }).then((result) => result.path);
+        };
+        cef.openFileDialog = function(title, defaultFileName, filters, options) {
+            options = options || {};
+            return cef.runFileDialog({
+                mode: options.folder ? "openFolder" : (options.multiple ? "openMultiple" : "open"),
+                title: title,
+                defaultFileName: defaultFileName,
+                filters: filters,
+            }).then((result) => result.paths);
+        };
+        console.info("registered runFileDialog, saveFileDialog and openFileDialog CEF extensions");
     })();
 "#;
 
@@ -84,43 +102,51 @@ pub unsafe fn process_message(
         .get_argument_list
         .expect("get_argument_list is a function"))(message);
     let size = (*args).get_size.expect("get_size is a function")(args);
-    if size < 1 {
+    if size < 2 {
         on_file_dialog_done(slf, None);
     } else {
-        let cef_path: cef_string_userfree_t =
-            (*args).get_string.expect("get_string is a function")(args, 0);
-        on_file_dialog_done(slf, Some(cef_path));
-        cef_string_userfree_utf16_free(cef_path);
+        // [filter index, paths...]
+        let filter_index = (*args).get_int.expect("get_int is a function")(args, 0);
+        let paths: Vec<String> = (1..size)
+            .map(|i| {
+                strings::from_userfree((*args).get_string.expect("get_string is a function")(
+                    args, i,
+                ))
+            })
+            .collect();
+        on_file_dialog_done(slf, Some((filter_index, paths)));
     }
 
     true
 }
 
-unsafe fn on_file_dialog_done(slf: *mut V8FileDialogHandler, path: Option<*const cef_string_t>) {
+unsafe fn on_file_dialog_done(slf: *mut V8FileDialogHandler, result: Option<(i32, Vec<String>)>) {
     log::debug!("file dialog done");
-    if let Some((context, on_success, on_error)) = (*slf).done_callback {
+    if let Some((context, on_success, on_cancel)) = (*slf).done_callback {
         ((*context).enter.expect("enter is a function"))(context);
 
-        if let Some(path) = path {
-            // store the path as a string in v8
-            let v8_path = cef_v8value_create_string(path);
-            // and call success, with a single argument that is the path!
+        if let Some((filter_index, paths)) = result {
+            // call success with the index of the selected filter, followed by the paths
+            let mut v8_args = vec![cef_v8value_create_int(filter_index)];
+            for path in paths.iter() {
+                v8_args.push(cef_v8value_create_string(&strings::to_cef_string(path)));
+            }
             log::debug!("successfully ran file dialog!");
             ((*on_success)
                 .execute_function
                 .expect("execute_function is a function"))(
                 on_success,
                 std::ptr::null_mut(),
-                1,
-                &v8_path,
+                v8_args.len() as size_t,
+                v8_args.as_ptr(),
             );
         } else {
-            // if the path was None, the user cancelled; report that as an error to JS
+            // if there was no result, the user cancelled
             log::debug!("user cancelled file dialog!");
-            ((*on_error)
+            ((*on_cancel)
                 .execute_function
                 .expect("execute_function is a function"))(
-                on_error,
+                on_cancel,
                 std::ptr::null_mut(),
                 0,
                 std::ptr::null_mut(),
@@ -141,7 +167,7 @@ unsafe extern "C" fn execute(
     arguments_count: size_t,
     arguments: *const *mut cef_v8value_t,
     _retval: *mut *mut cef_v8value_t,
-    _exception: *mut cef_string_t,
+    exception: *mut cef_string_t,
 ) -> c_int {
     // get the name of the function
     let chars: *mut u16 = (*name).str_;
@@ -156,114 +182,46 @@ unsafe extern "C" fn execute(
         name,
         arguments_count
     );
-    if (name == "saveFileDialog" || name == "openFileDialog") && arguments_count == 5 {
-        // get the title argument
-        let arg_title: *mut cef_v8value_t = *arguments.offset(0);
-        let is_string = ((*arg_title).is_string.expect("is_string is a function"))(arg_title) == 1;
-        if !is_string {
-            log::warn!("title argument isn't a string!");
-            return 0;
-        }
-
-        // get the file name argument
-        let arg_file_name: *mut cef_v8value_t = *arguments.offset(1);
-        let is_string: bool =
-            ((*arg_file_name).is_string.expect("is_string is a function"))(arg_file_name) == 1;
-        let arg_file_name_is_null: bool =
-            ((*arg_file_name).is_null.expect("is_null is a function"))(arg_file_name) == 1;
-        if !arg_file_name_is_null && !is_string {
-            log::error!("file name argument isn't a string, nor is it null!");
-            return 0;
-        }
-
-        // get the filter argument
-        let arg_filter: *mut cef_v8value_t = *arguments.offset(2);
-        let is_string =
-            ((*arg_filter).is_string.expect("is_string is a function"))(arg_filter) == 1;
-        if !is_string {
-            log::warn!("filter argument isn't a string!");
-            return 0;
-        }
-
-        // get the onDone argument
-        let arg_on_done: *mut cef_v8value_t = *arguments.offset(3);
-        let is_function = ((*arg_on_done)
-            .is_function
-            .expect("is_function is a function"))(arg_on_done)
-            == 1;
-        if !is_function {
-            log::warn!("onDone argument isn't a function!");
-            return 0;
-        }
+    if name == "runFileDialog" && arguments_count == 3 {
+        let options = match FileDialogOptions::from_v8(*arguments) {
+            Ok(options) => options,
+            Err(e) => {
+                log::warn!("invalid file dialog options: {}", e);
+                // throwing from inside the promise executor rejects the promise
+                *exception = strings::to_cef_string(&format!("invalid file dialog options: {}", e));
+                return 1;
+            }
+        };
 
-        // get the onError argument
-        let arg_on_error: *mut cef_v8value_t = *arguments.offset(4);
-        let is_function = ((*arg_on_error)
-            .is_function
-            .expect("is_function is a function"))(arg_on_error)
-            == 1;
-        if !is_function {
-            log::warn!("onError argument isn't a function!");
+        // get the onDone and onCancel arguments
+        let arg_on_done: *mut cef_v8value_t = *arguments.offset(1);
+        let arg_on_cancel: *mut cef_v8value_t = *arguments.offset(2);
+        let is_function = |value: *mut cef_v8value_t| {
+            ((*value).is_function.expect("is_function is a function"))(value) == 1
+        };
+        if !is_function(arg_on_done) || !is_function(arg_on_cancel) {
+            log::warn!("onDone / onCancel arguments aren't functions!");
             return 0;
         }
 
-        // get the v8 strings as cef strings
-        let cef_title: cef_string_userfree_t =
-            ((*arg_title)
-                .get_string_value
-                .expect("get_string_value is a function"))(arg_title);
-        let cef_file_name: cef_string_userfree_t = if arg_file_name_is_null {
-            super::bindings::cef_string_userfree_utf16_alloc()
-        } else {
-            ((*arg_file_name)
-                .get_string_value
-                .expect("get_string_value is a function"))(arg_file_name)
-        };
-        let cef_filter: cef_string_userfree_t =
-            ((*arg_filter)
-                .get_string_value
-                .expect("get_string_value is a function"))(arg_filter);
-
-        log::debug!("extracted arguments...");
-
         // now send an IPC message to the frame process telling it to open the file dialog
         let _self = slf as *mut V8FileDialogHandler;
-        log::debug!("{} called from JS", name);
-        if let Some(browser) = (*_self).browser {
+        if let Some(browser) = v8_values::current_browser().or((*_self).browser) {
             let frame = (*browser)
                 .get_main_frame
                 .expect("get_main_frame is a function")(browser);
 
-            // convert the message name to a CEF string
-            let mut cef_message_name = cef_string_t::default();
-            let message_name = match name.as_ref() {
-                "openFileDialog" => "open_file_dialog".as_bytes(),
-                "saveFileDialog" => "save_file_dialog".as_bytes(),
-                _ => unreachable!(),
-            };
-            let message_name = std::ffi::CString::new(message_name).unwrap();
-            super::bindings::cef_string_utf8_to_utf16(
-                message_name.as_ptr(),
-                message_name.to_bytes().len() as u64,
-                &mut cef_message_name,
-            );
-            log::debug!("generated message name");
-
-            // store our callback to onDone
+            // store our callbacks
             let context = cef_v8context_get_current_context();
-            (*_self).done_callback = Some((context, arg_on_done, arg_on_error));
-            log::debug!("stored done callback");
+            (*_self).done_callback = Some((context, arg_on_done, arg_on_cancel));
 
             // build the message
+            let cef_message_name = strings::to_cef_string("run_file_dialog");
             let message = super::bindings::cef_process_message_create(&cef_message_name);
             let args = ((*message)
                 .get_argument_list
                 .expect("get_argument_list is a function"))(message);
-            ((*args).set_size.expect("set_size is a function"))(args, 3);
-            ((*args).set_string.expect("set_string is a function"))(args, 0, cef_title);
-            ((*args).set_string.expect("set_string is a function"))(args, 1, cef_file_name);
-            ((*args).set_string.expect("set_string is a function"))(args, 2, cef_filter);
-            log::debug!("built IPC message");
+            options.write_to_list(args, 0);
 
             // send the message
             ((*frame)
@@ -276,11 +234,8 @@ unsafe extern "C" fn execute(
             log::debug!("sent IPC message");
         } else {
             log::error!("browser isn't set!");
+            *exception = strings::to_cef_string("can't run a file dialog: browser isn't set");
         }
-
-        cef_string_userfree_utf16_free(cef_title);
-        cef_string_userfree_utf16_free(cef_file_name);
-        cef_string_userfree_utf16_free(cef_filter);
         1
     } else {
         log::warn!(
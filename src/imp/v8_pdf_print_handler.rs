@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::mem::size_of;
 use std::os::raw::c_int;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -5,8 +6,11 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use super::bindings::{
     cef_base_ref_counted_t, cef_browser_t, cef_process_message_t, cef_string_t,
     cef_string_userfree_t, cef_string_userfree_utf16_free, cef_v8context_get_current_context,
-    cef_v8context_t, cef_v8handler_t, cef_v8value_t, size_t,
+    cef_v8context_t, cef_v8handler_t, cef_v8value_create_string, cef_v8value_t, size_t,
 };
+use super::pdf_options::{self, PdfOptions};
+use super::print_settings::{self, PrintSettings};
+use super::{strings, v8_values};
 
 #[repr(C)]
 pub struct V8PDFPrintHandler {
@@ -14,18 +18,97 @@ pub struct V8PDFPrintHandler {
     ref_count: AtomicUsize,
     pub browser: Option<*mut cef_browser_t>,
     pub done_callback: Option<(*mut cef_v8context_t, *mut cef_v8value_t, *mut cef_v8value_t)>,
+    /// The `printToPDFData` calls waiting for their PDF, by request id
+    data_callbacks: BTreeMap<c_int, PendingCallback>,
+    next_data_request: c_int,
+}
+
+/// A promise's resolve and reject functions, and the context they belong to,
+/// which are referenced for as long as they're pending
+struct PendingCallback {
+    context: *mut cef_v8context_t,
+    on_success: *mut cef_v8value_t,
+    on_error: *mut cef_v8value_t,
+}
+
+impl PendingCallback {
+    unsafe fn new(
+        context: *mut cef_v8context_t,
+        on_success: *mut cef_v8value_t,
+        on_error: *mut cef_v8value_t,
+    ) -> PendingCallback {
+        (*context).base.add_ref.expect("add_ref is a function")(
+            context as *mut cef_base_ref_counted_t,
+        );
+        for value in &[on_success, on_error] {
+            (**value).base.add_ref.expect("add_ref is a function")(
+                *value as *mut cef_base_ref_counted_t,
+            );
+        }
+        PendingCallback {
+            context,
+            on_success,
+            on_error,
+        }
+    }
+}
+
+impl Drop for PendingCallback {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.context).base.release.expect("release is a function")(
+                self.context as *mut cef_base_ref_counted_t,
+            );
+            for value in &[self.on_success, self.on_error] {
+                (**value).base.release.expect("release is a function")(
+                    *value as *mut cef_base_ref_counted_t,
+                );
+            }
+        }
+    }
 }
 
 const CODE: &str = r#"
     var cef;
     if(!cef) cef = {};
     (function() {
-        cef.printToPDF = function(path) {
-            native function printToPDF(path, onDone, onError);
+        cef.printToPDF = function(path, options) {
+            native function printToPDF(path, options, onDone, onError);
+            return new Promise((resolve, reject) => {
+                printToPDF(path, options || {}, resolve, reject);
+            });
+        };
+        cef.signalReady = function() {
+            native function signalReady();
+            signalReady();
+        };
+        cef.printToPDFBase64 = function(options) {
+            native function printToPDFData(options, onDone, onError);
             return new Promise((resolve, reject) => {
-                printToPDF(path, resolve, reject);
+                printToPDFData(options || {}, resolve, reject);
             });
         };
+        cef.printToPDFData = function(options) {
+            return cef.printToPDFBase64(options).then((data) => {
+                var binary = atob(data);
+                var bytes = new Uint8Array(binary.length);
+                for(var i = 0; i < binary.length; i++) {
+                    bytes[i] = binary.charCodeAt(i);
+                }
+                return bytes.buffer;
+            });
+        };
+        cef.print = function(options) {
+            native function print(options);
+            options = Object.assign({}, options || {});
+            if(Array.isArray(options.pageRanges)) {
+                // accept [[1, 3], 5] as well as "1-3, 5"
+                options.pageRanges = options.pageRanges.map((r) => {
+                    return Array.isArray(r) ? r[0] + "-" + r[1] : String(r);
+                }).join(",");
+            }
+            print(options);
+        };
     })();
 "#;
 
@@ -60,6 +143,32 @@ pub unsafe fn process_message(
     message_name: &str,
     message: *mut cef_process_message_t,
 ) -> bool {
+    if message_name == "print_to_pdf_data_done" {
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        let request_id = ((*args).get_int.expect("get_int is a function"))(args, 0);
+        let ok: bool = ((*args).get_bool.expect("get_bool is a function"))(args, 1) == 1;
+        let data = if ok {
+            let binary = ((*args).get_binary.expect("get_binary is a function"))(args, 2);
+            let size = ((*binary).get_size.expect("get_size is a function"))(binary);
+            let mut data: Vec<u8> = vec![0; size as usize];
+            ((*binary).get_data.expect("get_data is a function"))(
+                binary,
+                data.as_mut_ptr() as *mut _,
+                size,
+                0,
+            );
+            (*binary).base.release.expect("release is a function")(
+                binary as *mut cef_base_ref_counted_t,
+            );
+            Some(data)
+        } else {
+            None
+        };
+        on_pdf_print_data_done(slf, request_id, data);
+        return true;
+    }
     if message_name != "print_to_pdf_done" {
         return false;
     }
@@ -72,6 +181,60 @@ pub unsafe fn process_message(
     true
 }
 
+/// Forget the `printToPDFData` calls made from `context`, which is going away
+pub unsafe fn context_released(slf: *mut V8PDFPrintHandler, context: *mut cef_v8context_t) {
+    (*slf).data_callbacks.retain(|_, callback| {
+        ((*callback.context).is_same.expect("is_same is a function"))(callback.context, context)
+            != 1
+    });
+}
+
+unsafe fn on_pdf_print_data_done(
+    slf: *mut V8PDFPrintHandler,
+    request_id: c_int,
+    data: Option<Vec<u8>>,
+) {
+    if let Some(callback) = (*slf).data_callbacks.remove(&request_id) {
+        let (context, on_success, on_error) =
+            (callback.context, callback.on_success, callback.on_error);
+        if ((*context).is_valid.expect("is_valid is a function"))(context) != 1 {
+            log::debug!("pdf data print is done but its page has gone away");
+            return;
+        }
+        ((*context).enter.expect("enter is a function"))(context);
+
+        if let Some(data) = data {
+            // hand the PDF to JS as base64, the JS side turns it into an ArrayBuffer
+            let cef_data = strings::to_cef_string(&base64::encode(&data));
+            let v8_data = cef_v8value_create_string(&cef_data);
+            ((*on_success)
+                .execute_function
+                .expect("execute_function is a function"))(
+                on_success,
+                std::ptr::null_mut(),
+                1,
+                &v8_data,
+            );
+        } else {
+            ((*on_error)
+                .execute_function
+                .expect("execute_function is a function"))(
+                on_error,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+            );
+        }
+
+        ((*context).exit.expect("exit is a function"))(context);
+    } else {
+        log::warn!(
+            "pdf data print {} is done but its callback wasn't set?!",
+            request_id
+        );
+    }
+}
+
 unsafe fn on_pdf_print_done(slf: *mut V8PDFPrintHandler, ok: bool) {
     if let Some((context, on_success, on_error)) = (*slf).done_callback {
         ((*context).enter.expect("enter is a function"))(context);
@@ -111,7 +274,7 @@ unsafe extern "C" fn execute(
     arguments_count: size_t,
     arguments: *const *mut cef_v8value_t,
     _retval: *mut *mut cef_v8value_t,
-    _exception: *mut cef_string_t,
+    exception: *mut cef_string_t,
 ) -> c_int {
     // get the name of the function
     let chars: *mut u16 = (*name).str_;
@@ -121,7 +284,7 @@ unsafe extern "C" fn execute(
         .map(|r| r.unwrap_or(std::char::REPLACEMENT_CHARACTER))
         .collect::<String>();
 
-    if name == "printToPDF" && arguments_count == 3 {
+    if name == "printToPDF" && arguments_count == 4 {
         log::debug!("printing!");
 
         // get the path argument
@@ -132,8 +295,20 @@ unsafe extern "C" fn execute(
             return 0;
         }
 
+        // get the options argument
+        let arg_options: *mut cef_v8value_t = *(arguments.offset(1));
+        let options = match PdfOptions::from_v8(arg_options) {
+            Ok(options) => options,
+            Err(e) => {
+                log::warn!("invalid PDF options: {}", e);
+                // throwing from inside the promise executor rejects the promise
+                *exception = strings::to_cef_string(&format!("invalid PDF options: {}", e));
+                return 1;
+            }
+        };
+
         // get the onDone argument
-        let arg_on_done: *mut cef_v8value_t = *(arguments.offset(1));
+        let arg_on_done: *mut cef_v8value_t = *(arguments.offset(2));
         let is_function = ((*arg_on_done)
             .is_function
             .expect("is_function is a function"))(arg_on_done)
@@ -144,7 +319,7 @@ unsafe extern "C" fn execute(
         }
 
         // get the onError argument
-        let arg_on_error: *mut cef_v8value_t = *(arguments.offset(2));
+        let arg_on_error: *mut cef_v8value_t = *(arguments.offset(3));
         let is_function = ((*arg_on_error)
             .is_function
             .expect("is_function is a function"))(arg_on_error)
@@ -162,7 +337,7 @@ unsafe extern "C" fn execute(
 
         // now send an IPC message to the frame process telling it to print
         let _self = slf as *mut V8PDFPrintHandler;
-        if let Some(browser) = (*_self).browser {
+        if let Some(browser) = v8_values::current_browser().or((*_self).browser) {
             let frame = (*browser)
                 .get_main_frame
                 .expect("get_main_frame is a function")(browser);
@@ -186,8 +361,12 @@ unsafe extern "C" fn execute(
             let args = ((*message)
                 .get_argument_list
                 .expect("get_argument_list is a function"))(message);
-            ((*args).set_size.expect("set_size is a function"))(args, 1);
+            ((*args).set_size.expect("set_size is a function"))(
+                args,
+                1 + pdf_options::LIST_LEN as u64,
+            );
             ((*args).set_string.expect("set_string is a function"))(args, 0, cef_path);
+            options.write_to_list(args, 1);
 
             // send the message
             ((*frame)
@@ -203,6 +382,121 @@ unsafe extern "C" fn execute(
 
         cef_string_userfree_utf16_free(cef_path);
         1
+    } else if name == "signalReady" {
+        // let the browser process know the page considers itself ready (to print)
+        let _self = slf as *mut V8PDFPrintHandler;
+        if let Some(browser) = v8_values::current_browser().or((*_self).browser) {
+            let frame = (*browser)
+                .get_main_frame
+                .expect("get_main_frame is a function")(browser);
+            let cef_message_name = strings::to_cef_string("page_ready");
+            let message = super::bindings::cef_process_message_create(&cef_message_name);
+            ((*frame)
+                .send_process_message
+                .expect("send_process_message is a function"))(
+                frame,
+                super::bindings::cef_process_id_t_PID_BROWSER,
+                message,
+            );
+        } else {
+            log::error!("browser isn't set!");
+        }
+        1
+    } else if name == "printToPDFData" && arguments_count == 3 {
+        let options = match PdfOptions::from_v8(*arguments) {
+            Ok(options) => options,
+            Err(e) => {
+                log::warn!("invalid PDF options: {}", e);
+                *exception = strings::to_cef_string(&format!("invalid PDF options: {}", e));
+                return 1;
+            }
+        };
+
+        let arg_on_done: *mut cef_v8value_t = *(arguments.offset(1));
+        let arg_on_error: *mut cef_v8value_t = *(arguments.offset(2));
+        let is_function = |value: *mut cef_v8value_t| {
+            ((*value).is_function.expect("is_function is a function"))(value) == 1
+        };
+        if !is_function(arg_on_done) || !is_function(arg_on_error) {
+            log::warn!("onDone / onError arguments aren't functions!");
+            return 0;
+        }
+
+        let _self = slf as *mut V8PDFPrintHandler;
+        if let Some(browser) = v8_values::current_browser().or((*_self).browser) {
+            let frame = (*browser)
+                .get_main_frame
+                .expect("get_main_frame is a function")(browser);
+
+            let request_id = (*_self).next_data_request;
+            (*_self).next_data_request = request_id.wrapping_add(1);
+            let context = cef_v8context_get_current_context();
+            (*_self).data_callbacks.insert(
+                request_id,
+                PendingCallback::new(context, arg_on_done, arg_on_error),
+            );
+
+            let cef_message_name = strings::to_cef_string("print_to_pdf_data");
+            let message = super::bindings::cef_process_message_create(&cef_message_name);
+            let args = ((*message)
+                .get_argument_list
+                .expect("get_argument_list is a function"))(message);
+            ((*args).set_size.expect("set_size is a function"))(
+                args,
+                1 + pdf_options::LIST_LEN as u64,
+            );
+            ((*args).set_int.expect("set_int is a function"))(args, 0, request_id);
+            options.write_to_list(args, 1);
+
+            ((*frame)
+                .send_process_message
+                .expect("send_process_message is a function"))(
+                frame,
+                super::bindings::cef_process_id_t_PID_BROWSER,
+                message,
+            );
+        } else {
+            log::error!("browser isn't set!");
+        }
+        1
+    } else if name == "print" && arguments_count == 1 {
+        let settings = match PrintSettings::from_v8(*arguments) {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::warn!("invalid print options: {}", e);
+                *exception = strings::to_cef_string(&format!("invalid print options: {}", e));
+                return 1;
+            }
+        };
+
+        let _self = slf as *mut V8PDFPrintHandler;
+        if let Some(browser) = v8_values::current_browser().or((*_self).browser) {
+            let frame = (*browser)
+                .get_main_frame
+                .expect("get_main_frame is a function")(browser);
+
+            let cef_message_name = strings::to_cef_string("print");
+            let message = super::bindings::cef_process_message_create(&cef_message_name);
+            let args = ((*message)
+                .get_argument_list
+                .expect("get_argument_list is a function"))(message);
+            ((*args).set_size.expect("set_size is a function"))(
+                args,
+                print_settings::LIST_LEN as u64,
+            );
+            settings.write_to_list(args, 0);
+
+            ((*frame)
+                .send_process_message
+                .expect("send_process_message is a function"))(
+                frame,
+                super::bindings::cef_process_id_t_PID_BROWSER,
+                message,
+            );
+        } else {
+            log::error!("browser isn't set!");
+        }
+        1
     } else {
         log::warn!(
             "unrecognized function: `{}` with {} args, skipping",
@@ -228,6 +522,8 @@ pub fn allocate() -> *mut V8PDFPrintHandler {
         ref_count: AtomicUsize::new(1),
         browser: None,
         done_callback: None,
+        data_callbacks: BTreeMap::new(),
+        next_data_request: 0,
     };
 
     Box::into_raw(Box::from(handler))
@@ -0,0 +1,106 @@
+use super::bindings::{cef_browser_t, cef_v8context_get_current_context, cef_v8value_t};
+use super::strings;
+
+/// The browser whose JS is currently running. Extensions are shared by every
+/// browser in a render process (such as a page and its popups), so replies
+/// have to go to the browser which called them.
+pub unsafe fn current_browser() -> Option<*mut cef_browser_t> {
+    let context = cef_v8context_get_current_context();
+    if context.is_null() || ((*context).is_valid.expect("is_valid is a function"))(context) != 1 {
+        return None;
+    }
+    let browser = ((*context).get_browser.expect("get_browser is a function"))(context);
+    if browser.is_null() {
+        None
+    } else {
+        Some(browser)
+    }
+}
+
+/// Look up `key` on a v8 object, returning `None` if the key doesn't exist or
+/// holds `undefined` / `null`
+pub unsafe fn get(object: *mut cef_v8value_t, key: &str) -> Option<*mut cef_v8value_t> {
+    if object.is_null() || ((*object).is_object.expect("is_object is a function"))(object) != 1 {
+        return None;
+    }
+
+    let cef_key = strings::to_cef_string(key);
+    if ((*object)
+        .has_value_bykey
+        .expect("has_value_bykey is a function"))(object, &cef_key)
+        != 1
+    {
+        return None;
+    }
+    let value = ((*object)
+        .get_value_bykey
+        .expect("get_value_bykey is a function"))(object, &cef_key);
+    if is_nullish(value) {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+pub unsafe fn is_nullish(value: *mut cef_v8value_t) -> bool {
+    value.is_null()
+        || ((*value).is_undefined.expect("is_undefined is a function"))(value) == 1
+        || ((*value).is_null.expect("is_null is a function"))(value) == 1
+}
+
+pub unsafe fn as_string(value: *mut cef_v8value_t) -> Option<String> {
+    if value.is_null() || ((*value).is_string.expect("is_string is a function"))(value) != 1 {
+        return None;
+    }
+    Some(strings::from_userfree(((*value)
+        .get_string_value
+        .expect("get_string_value is a function"))(
+        value
+    )))
+}
+
+pub unsafe fn as_bool(value: *mut cef_v8value_t) -> Option<bool> {
+    if value.is_null() || ((*value).is_bool.expect("is_bool is a function"))(value) != 1 {
+        return None;
+    }
+    Some(
+        ((*value)
+            .get_bool_value
+            .expect("get_bool_value is a function"))(value)
+            == 1,
+    )
+}
+
+pub unsafe fn as_number(value: *mut cef_v8value_t) -> Option<f64> {
+    if value.is_null() {
+        return None;
+    }
+    let is_number = ((*value).is_int.expect("is_int is a function"))(value) == 1
+        || ((*value).is_uint.expect("is_uint is a function"))(value) == 1
+        || ((*value).is_double.expect("is_double is a function"))(value) == 1;
+    if !is_number {
+        return None;
+    }
+    Some(((*value)
+        .get_double_value
+        .expect("get_double_value is a function"))(value))
+}
+
+/// The elements of a v8 array, or `None` if `value` isn't an array
+pub unsafe fn as_array(value: *mut cef_v8value_t) -> Option<Vec<*mut cef_v8value_t>> {
+    if value.is_null() || ((*value).is_array.expect("is_array is a function"))(value) != 1 {
+        return None;
+    }
+    let length = ((*value)
+        .get_array_length
+        .expect("get_array_length is a function"))(value);
+    Some(
+        (0..length)
+            .map(|i| {
+                ((*value)
+                    .get_value_byindex
+                    .expect("get_value_byindex is a function"))(value, i)
+            })
+            .collect(),
+    )
+}
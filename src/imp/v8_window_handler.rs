@@ -0,0 +1,241 @@
+use std::collections::BTreeSet;
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_browser_t, cef_dictionary_value_t, cef_frame_t, cef_string_t,
+    cef_v8handler_t, cef_v8value_t, size_t,
+};
+use super::{strings, v8_values};
+
+/// Set in a browser's extra info when the browser process wants to know
+/// whether the user is still around, for a kiosk's idle timer
+pub const WATCH_ACTIVITY: &str = "watch_activity";
+
+#[repr(C)]
+pub struct V8WindowHandler {
+    v8_handler: cef_v8handler_t,
+    ref_count: AtomicUsize,
+    pub browser: Option<*mut cef_browser_t>,
+    /// The browsers (by id) whose pages should report activity
+    watching_activity: BTreeSet<c_int>,
+}
+
+const CODE: &str = r#"
+    var cef;
+    if(!cef) cef = {};
+    (function() {
+        native function windowCommand(command);
+        cef.window = {
+            minimize: function() { windowCommand("minimize"); },
+            maximize: function() { windowCommand("maximize"); },
+            restore: function() { windowCommand("restore"); },
+            close: function() { windowCommand("close"); },
+            // call from a mousedown handler to move the window with the mouse
+            startDrag: function() { windowCommand("startDrag"); },
+        };
+        // only does anything if `Cef::set_dev_tools` lets pages open DevTools
+        cef.devTools = {
+            show: function() { windowCommand("showDevTools"); },
+            close: function() { windowCommand("closeDevTools"); },
+        };
+        // called by the renderer in kiosks with an idle timer, to tell the
+        // browser the user is still around
+        cef.window._watchActivity = function() {
+            var lastActivity = 0;
+            ["pointerdown", "pointermove", "keydown", "wheel", "touchstart"].forEach(function(type) {
+                window.addEventListener(type, function() {
+                    var now = Date.now();
+                    if (now - lastActivity > 5000) {
+                        lastActivity = now;
+                        windowCommand("activity");
+                    }
+                }, { capture: true, passive: true });
+            });
+            delete cef.window._watchActivity;
+        };
+        console.info("registered window controls CEF extension");
+    })();
+"#;
+
+pub unsafe fn register_extension(extension: *mut V8WindowHandler) {
+    use super::bindings::cef_register_extension;
+    let cef_code = strings::to_cef_string(CODE);
+    let cef_extension_name = strings::to_cef_string("CEF Window Controls");
+
+    cef_register_extension(
+        &cef_extension_name,
+        &cef_code,
+        extension as *mut cef_v8handler_t,
+    );
+    log::debug!("registered window controls extension");
+}
+
+pub unsafe fn browser_created(
+    slf: *mut V8WindowHandler,
+    browser: *mut cef_browser_t,
+    extra_info: *mut cef_dictionary_value_t,
+) {
+    if extra_info.is_null() {
+        return;
+    }
+    let key = strings::to_cef_string(WATCH_ACTIVITY);
+    if ((*extra_info).get_bool.expect("get_bool is a function"))(extra_info, &key) == 1 {
+        (*slf).watching_activity.insert(browser_id(browser));
+    }
+}
+
+pub unsafe fn browser_destroyed(slf: *mut V8WindowHandler, browser: *mut cef_browser_t) {
+    (*slf).watching_activity.remove(&browser_id(browser));
+}
+
+/// Start watching for activity in a new page, if its browser wants to know
+pub unsafe fn context_created(
+    slf: *mut V8WindowHandler,
+    browser: *mut cef_browser_t,
+    frame: *mut cef_frame_t,
+) {
+    if !(*slf).watching_activity.contains(&browser_id(browser))
+        || ((*frame).is_main.expect("is_main is a function"))(frame) != 1
+    {
+        return;
+    }
+    let cef_code = strings::to_cef_string("cef.window._watchActivity();");
+    let cef_url = strings::to_cef_string("");
+    ((*frame)
+        .execute_java_script
+        .expect("execute_java_script is a function"))(frame, &cef_code, &cef_url, 0);
+}
+
+unsafe fn browser_id(browser: *mut cef_browser_t) -> c_int {
+    (*browser)
+        .get_identifier
+        .expect("get_identifier is a function")(browser)
+}
+
+unsafe extern "C" fn execute(
+    slf: *mut cef_v8handler_t,
+    name: *const cef_string_t,
+    _object: *mut cef_v8value_t,
+    arguments_count: size_t,
+    arguments: *const *mut cef_v8value_t,
+    _retval: *mut *mut cef_v8value_t,
+    _exception: *mut cef_string_t,
+) -> c_int {
+    let name = strings::from_cef_string(name);
+    log::debug!(
+        "native call to function: {} with {} arguments",
+        name,
+        arguments_count
+    );
+
+    let string_arg = |i: usize| v8_values::as_string(*arguments.add(i));
+    let message = match (name.as_str(), arguments_count) {
+        ("windowCommand", 1) => string_arg(0).map(|command| ("window_command", vec![command])),
+        _ => None,
+    };
+    let (message_name, message_args) = match message {
+        Some(message) => message,
+        _ => {
+            log::warn!(
+                "unrecognized function: `{}` with {} args, skipping",
+                name,
+                arguments_count
+            );
+            return 0;
+        }
+    };
+
+    let _self = slf as *mut V8WindowHandler;
+    if let Some(browser) = v8_values::current_browser().or((*_self).browser) {
+        let frame = (*browser)
+            .get_main_frame
+            .expect("get_main_frame is a function")(browser);
+
+        let cef_message_name = strings::to_cef_string(message_name);
+        let message = super::bindings::cef_process_message_create(&cef_message_name);
+        let args = ((*message)
+            .get_argument_list
+            .expect("get_argument_list is a function"))(message);
+        ((*args).set_size.expect("set_size is a function"))(args, message_args.len() as size_t);
+        for (i, arg) in message_args.iter().enumerate() {
+            ((*args).set_string.expect("set_string is a function"))(
+                args,
+                i as size_t,
+                &strings::to_cef_string(arg),
+            );
+        }
+
+        ((*frame)
+            .send_process_message
+            .expect("send_process_message is a function"))(
+            frame,
+            super::bindings::cef_process_id_t_PID_BROWSER,
+            message,
+        );
+    } else {
+        log::error!("browser isn't set!");
+    }
+    1
+}
+
+pub fn allocate() -> *mut V8WindowHandler {
+    let handler = V8WindowHandler {
+        v8_handler: cef_v8handler_t {
+            base: cef_base_ref_counted_t {
+                size: size_of::<V8WindowHandler>() as u64,
+                add_ref: Some(add_ref),
+                release: Some(release),
+                has_one_ref: Some(has_one_ref),
+                has_at_least_one_ref: Some(has_at_least_one_ref),
+            },
+            execute: Some(execute),
+        },
+        ref_count: AtomicUsize::new(1),
+        browser: None,
+        watching_activity: BTreeSet::new(),
+    };
+
+    Box::into_raw(Box::from(handler))
+}
+
+extern "C" fn add_ref(base: *mut cef_base_ref_counted_t) {
+    let v8_handler = base as *mut V8WindowHandler;
+    unsafe { (*v8_handler).ref_count.fetch_add(1, Ordering::SeqCst) };
+}
+
+extern "C" fn release(base: *mut cef_base_ref_counted_t) -> c_int {
+    let v8_handler = base as *mut V8WindowHandler;
+    let count = unsafe { (*v8_handler).ref_count.fetch_sub(1, Ordering::SeqCst) - 1 };
+
+    if count == 0 {
+        unsafe {
+            log::debug!("dropping window controls v8 handler!");
+            Box::from_raw(v8_handler);
+        }
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let v8_handler = base as *mut V8WindowHandler;
+    let count = unsafe { (*v8_handler).ref_count.load(Ordering::SeqCst) };
+    if count == 1 {
+        1
+    } else {
+        0
+    }
+}
+
+extern "C" fn has_at_least_one_ref(base: *mut cef_base_ref_counted_t) -> c_int {
+    let v8_handler = base as *mut V8WindowHandler;
+    let count = unsafe { (*v8_handler).ref_count.load(Ordering::SeqCst) };
+    if count >= 1 {
+        1
+    } else {
+        0
+    }
+}
@@ -1,26 +1,107 @@
 use std::ffi::CString;
 use std::mem::size_of;
 use std::os::raw::c_int;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::bindings::{
     cef_base_ref_counted_t, cef_browser_settings_t, cef_browser_view_create,
-    cef_browser_view_delegate_t, cef_client_t, cef_dictionary_value_create, cef_image_create,
-    cef_image_t, cef_panel_delegate_t, cef_panel_t, cef_request_context_get_global_context,
-    cef_size_t, cef_state_t_STATE_DISABLED, cef_state_t_STATE_ENABLED, cef_string_t,
-    cef_string_utf8_to_utf16, cef_view_delegate_t, cef_view_t, cef_window_delegate_t, cef_window_t,
+    cef_browser_view_delegate_t, cef_browser_view_t, cef_client_t, cef_dictionary_value_create,
+    cef_image_create, cef_image_t, cef_panel_delegate_t, cef_panel_t, cef_point_t, cef_rect_t,
+    cef_request_context_get_global_context, cef_size_t, cef_state_t_STATE_DISABLED,
+    cef_state_t_STATE_ENABLED, cef_string_t, cef_string_utf8_to_utf16, cef_view_delegate_t,
+    cef_view_t, cef_window_delegate_t, cef_window_t,
 };
-use super::{browser_view_delegate, client};
+use super::browser::Browser;
+use super::client::ClientHooks;
+use super::dialog_handler::DialogHandler;
+use super::download_handler::DownloadHandler;
+use super::js_dialog_handler::JsDialogHandler;
+use super::kiosk::{self, KioskOptions};
+use super::popups::PopupPolicy;
+use super::shortcuts::{self, KeyCombo, Shortcuts};
+use super::window_state::{self, WindowState};
+use super::windows::{self, CloseDecision, CloseRequest, CloseRequested, WindowId};
+use super::{browser_view_delegate, client, devtools, strings, v8_window_handler};
 
-#[derive(Debug)]
 pub struct WindowOptions {
     pub url: String,
     pub title: Option<String>,
     pub maximized: bool,
     pub fullscreen: bool,
     pub size: Option<(i32, i32)>,
+    pub min_size: Option<(i32, i32)>,
+    pub max_size: Option<(i32, i32)>,
+    /// Where to put the window's top-left corner on screen, rather than
+    /// centering it
+    pub position: Option<(i32, i32)>,
+    /// Save the window's bounds, maximized and fullscreen state under this key
+    /// in the store set with `Cef::set_window_state_store` when it closes, and
+    /// restore them the next time a window with this key opens
+    pub state_key: Option<String>,
+    /// Leave out the platform's titlebar and borders, so the page can draw its
+    /// own titlebar. Mark the parts of the page which move the window with
+    /// `-webkit-app-region: drag`, and use `cef.window.minimize()`,
+    /// `maximize()`, `restore()`, `close()` and `startDrag()` for its buttons.
+    pub frameless: bool,
+    pub resizable: bool,
+    pub minimizable: bool,
+    pub maximizable: bool,
+    /// Keep the window above other windows
+    pub always_on_top: bool,
     pub window_icon: Option<&'static [u8]>,
     pub window_app_icon: Option<&'static [u8]>,
+    /// Called once the window's browser has been created, so that it can be
+    /// controlled from Rust
+    pub on_browser_created: Option<Box<dyn FnMut(Browser)>>,
+    /// Intercepts file dialogs instead of showing the platform's dialog
+    pub dialog_handler: Option<Rc<dyn DialogHandler>>,
+    /// Handles `alert()`, `confirm()`, `prompt()` and `beforeunload` dialogs
+    /// instead of showing CEF's default dialogs
+    pub js_dialog_handler: Option<Rc<dyn JsDialogHandler>>,
+    /// Decides where downloads go and follows their progress; nothing is
+    /// downloaded without one
+    pub download_handler: Option<Rc<dyn DownloadHandler>>,
+    /// Decides whether the window can close when the user tries to close it
+    pub on_close_requested: Option<CloseRequested>,
+    /// What to do when the page opens a popup
+    pub popup_policy: PopupPolicy,
+    /// Shortcuts for this window only, on top of those set with
+    /// `Cef::set_shortcuts`
+    pub shortcuts: Shortcuts,
+    /// Lock the window down as a kiosk; see `WindowOptions::kiosk`
+    pub kiosk: Option<KioskOptions>,
+}
+
+impl std::fmt::Debug for WindowOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WindowOptions")
+            .field("url", &self.url)
+            .field("title", &self.title)
+            .field("maximized", &self.maximized)
+            .field("fullscreen", &self.fullscreen)
+            .field("size", &self.size)
+            .field("min_size", &self.min_size)
+            .field("max_size", &self.max_size)
+            .field("position", &self.position)
+            .field("state_key", &self.state_key)
+            .field("frameless", &self.frameless)
+            .field("resizable", &self.resizable)
+            .field("minimizable", &self.minimizable)
+            .field("maximizable", &self.maximizable)
+            .field("always_on_top", &self.always_on_top)
+            .field("window_icon", &self.window_icon.is_some())
+            .field("window_app_icon", &self.window_app_icon.is_some())
+            .field("on_browser_created", &self.on_browser_created.is_some())
+            .field("dialog_handler", &self.dialog_handler.is_some())
+            .field("js_dialog_handler", &self.js_dialog_handler.is_some())
+            .field("download_handler", &self.download_handler.is_some())
+            .field("on_close_requested", &self.on_close_requested.is_some())
+            .field("popup_policy", &self.popup_policy)
+            .field("shortcuts", &self.shortcuts)
+            .field("kiosk", &self.kiosk)
+            .finish()
+    }
 }
 
 impl Default for WindowOptions {
@@ -31,8 +112,103 @@ impl Default for WindowOptions {
             maximized: false,
             fullscreen: false,
             size: Some((1280, 720)),
+            min_size: None,
+            max_size: None,
+            position: None,
+            state_key: None,
+            frameless: false,
+            resizable: true,
+            minimizable: true,
+            maximizable: true,
+            always_on_top: false,
             window_icon: None,
             window_app_icon: None,
+            on_browser_created: None,
+            dialog_handler: None,
+            js_dialog_handler: None,
+            download_handler: None,
+            on_close_requested: None,
+            popup_policy: PopupPolicy::default(),
+            shortcuts: Shortcuts::default(),
+            kiosk: None,
+        }
+    }
+}
+
+impl WindowOptions {
+    /// Options for a kiosk showing `url`, its home URL: a frameless,
+    /// always-on-top window which stays fullscreen, can't be minimized or
+    /// resized, only navigates to the home URL and `kiosk.allowed_urls`,
+    /// denies popups and blocks the browser's reload, zoom, navigation, print
+    /// and developer tools shortcuts. The context menu is disabled, as in
+    /// every window. If `kiosk.idle_timeout` is set, the window goes back to
+    /// the home URL once nobody has touched it for that long.
+    pub fn kiosk(url: &str, kiosk: KioskOptions) -> WindowOptions {
+        let mut shortcuts = Shortcuts::new();
+        shortcuts.block_browser_shortcuts();
+        WindowOptions {
+            url: url.to_string(),
+            fullscreen: true,
+            size: None,
+            frameless: true,
+            resizable: false,
+            minimizable: false,
+            maximizable: false,
+            always_on_top: true,
+            popup_policy: PopupPolicy::Deny,
+            shortcuts,
+            kiosk: Some(kiosk),
+            ..WindowOptions::default()
+        }
+    }
+
+    /// Options for a popup opened from a window with these options: the popup
+    /// shares the window's title, icons, size limits, frame, dialog and
+    /// download handlers, popup policy and shortcuts, but not its position,
+    /// saved state or callbacks, and is never maximized, fullscreen or a kiosk.
+    pub fn popup(&self, url: &str) -> WindowOptions {
+        WindowOptions {
+            url: url.to_string(),
+            title: self.title.clone(),
+            maximized: false,
+            fullscreen: false,
+            size: self.size,
+            min_size: self.min_size,
+            max_size: self.max_size,
+            position: None,
+            state_key: None,
+            frameless: self.frameless,
+            resizable: self.resizable,
+            minimizable: self.minimizable,
+            maximizable: self.maximizable,
+            always_on_top: self.always_on_top,
+            window_icon: self.window_icon,
+            window_app_icon: self.window_app_icon,
+            on_browser_created: None,
+            dialog_handler: self.dialog_handler.clone(),
+            js_dialog_handler: self.js_dialog_handler.clone(),
+            download_handler: self.download_handler.clone(),
+            on_close_requested: None,
+            popup_policy: self.popup_policy.clone(),
+            shortcuts: self.shortcuts.clone(),
+            kiosk: None,
+        }
+    }
+
+    pub(crate) fn client_hooks(&self) -> ClientHooks {
+        ClientHooks {
+            dialog_handler: self.dialog_handler.clone(),
+            js_dialog_handler: self.js_dialog_handler.clone(),
+            download_handler: self.download_handler.clone(),
+            popup_options: Some(self.popup("")),
+            shortcuts: self.shortcuts.clone(),
+            navigation_allowlist: self
+                .kiosk
+                .as_ref()
+                .map(|kiosk| kiosk.navigation_allowlist(&self.url)),
+            lock_fullscreen: self.kiosk.is_some(),
+            dev_tools_menu: self.kiosk.is_none(),
+            ..ClientHooks::default()
         }
     }
 }
@@ -43,6 +219,11 @@ pub struct WindowDelegate {
     pub window_delegate: cef_window_delegate_t,
     pub ref_count: AtomicUsize,
     pub options: WindowOptions,
+    pub window_id: WindowId,
+    /// The browser view CEF already created, if this window shows a popup
+    pub popup_browser_view: Option<*mut cef_browser_view_t>,
+    /// The shortcuts registered as window accelerators, indexed by command id
+    pub accelerators: Vec<KeyCombo>,
     pub window_icon: Option<*mut cef_image_t>,
     pub window_app_icon: Option<*mut cef_image_t>,
 }
@@ -53,30 +234,136 @@ impl WindowDelegate {
     }
 }
 
-extern "C" fn is_frameless(_: *mut cef_window_delegate_t, _: *mut cef_window_t) -> c_int {
-    0
+fn to_cef_size(size: Option<(i32, i32)>) -> cef_size_t {
+    // an empty size means no preference / no limit
+    let (width, height) = size.unwrap_or((0, 0));
+    cef_size_t { width, height }
 }
 
-extern "C" fn can_resize(_: *mut cef_window_delegate_t, _: *mut cef_window_t) -> c_int {
-    1
+unsafe extern "C" fn get_preferred_size(
+    slf: *mut cef_view_delegate_t,
+    _: *mut cef_view_t,
+) -> cef_size_t {
+    let window_delegate = slf as *mut WindowDelegate;
+    to_cef_size((*window_delegate).options.size)
 }
 
-extern "C" fn can_maximize(_: *mut cef_window_delegate_t, _: *mut cef_window_t) -> c_int {
-    1
+unsafe extern "C" fn get_minimum_size(
+    slf: *mut cef_view_delegate_t,
+    _: *mut cef_view_t,
+) -> cef_size_t {
+    let window_delegate = slf as *mut WindowDelegate;
+    to_cef_size((*window_delegate).options.min_size)
 }
 
-extern "C" fn can_minimize(_: *mut cef_window_delegate_t, _: *mut cef_window_t) -> c_int {
-    1
+unsafe extern "C" fn get_maximum_size(
+    slf: *mut cef_view_delegate_t,
+    _: *mut cef_view_t,
+) -> cef_size_t {
+    let window_delegate = slf as *mut WindowDelegate;
+    to_cef_size((*window_delegate).options.max_size)
 }
 
-extern "C" fn can_close(_: *mut cef_window_delegate_t, _: *mut cef_window_t) -> c_int {
-    1
+unsafe extern "C" fn is_frameless(slf: *mut cef_window_delegate_t, _: *mut cef_window_t) -> c_int {
+    let window_delegate = slf as *mut WindowDelegate;
+    (*window_delegate).options.frameless as c_int
 }
 
-extern "C" fn window_delegate_created(slf: *mut cef_window_delegate_t, window: *mut cef_window_t) {
-    log::debug!("window delegate created!");
+unsafe extern "C" fn can_resize(slf: *mut cef_window_delegate_t, _: *mut cef_window_t) -> c_int {
+    let window_delegate = slf as *mut WindowDelegate;
+    (*window_delegate).options.resizable as c_int
+}
+
+unsafe extern "C" fn can_maximize(slf: *mut cef_window_delegate_t, _: *mut cef_window_t) -> c_int {
+    let window_delegate = slf as *mut WindowDelegate;
+    (*window_delegate).options.maximizable as c_int
+}
+
+unsafe extern "C" fn can_minimize(slf: *mut cef_window_delegate_t, _: *mut cef_window_t) -> c_int {
+    let window_delegate = slf as *mut WindowDelegate;
+    (*window_delegate).options.minimizable as c_int
+}
+
+/// Save the window's state, if it has a state key
+unsafe fn save_state(window_delegate: *mut WindowDelegate, window: *mut cef_window_t) {
+    let key = match &(*window_delegate).options.state_key {
+        Some(key) => key,
+        None => return,
+    };
+
+    let view = window as *mut cef_view_t;
+    let bounds = (*view).get_bounds.expect("get_bounds is a function")(view);
+    let maximized = (*window).is_maximized.expect("is_maximized is a function")(window) == 1;
+    let fullscreen = (*window)
+        .is_fullscreen
+        .expect("is_fullscreen is a function")(window)
+        == 1;
+    let minimized = (*window).is_minimized.expect("is_minimized is a function")(window) == 1;
+
+    let mut state = WindowState {
+        x: bounds.x,
+        y: bounds.y,
+        width: bounds.width,
+        height: bounds.height,
+        maximized,
+        fullscreen,
+        display_id: window_state::display_id(&bounds),
+    };
+    if maximized || fullscreen || minimized {
+        // CEF can't tell us the restored bounds, so keep the last ones we saw
+        if let Some(saved) = window_state::load(key) {
+            state.x = saved.x;
+            state.y = saved.y;
+            state.width = saved.width;
+            state.height = saved.height;
+            state.display_id = saved.display_id;
+        }
+    }
+    log::debug!("saving window state `{}`: {:?}", key, state);
+    window_state::save(key, state);
+}
 
+unsafe extern "C" fn can_close(
+    slf: *mut cef_window_delegate_t,
+    window: *mut cef_window_t,
+) -> c_int {
     let window_delegate = slf as *mut WindowDelegate;
+    let window_id = (*window_delegate).window_id;
+    let browser = match windows::browser(window_id) {
+        Some(browser) => browser,
+        None => {
+            save_state(window_delegate, window);
+            return 1;
+        }
+    };
+
+    if !windows::close_approved(window_id) {
+        let request = CloseRequest::new(window_id);
+        let decision = match &mut (*window_delegate).options.on_close_requested {
+            Some(on_close_requested) => on_close_requested(&browser, request),
+            None => CloseDecision::Allow,
+        };
+        log::debug!("close requested for {}: {:?}", window_id, decision);
+        if decision != CloseDecision::Allow || !windows::allow_close(window_id) {
+            return 0;
+        }
+    }
+
+    // runs the page's beforeunload handlers, after which CEF closes the
+    // window again if they let it
+    if browser.try_close() {
+        save_state(window_delegate, window);
+        1
+    } else {
+        windows::unload_pending(window_id);
+        0
+    }
+}
+
+fn create_browser_view(
+    window_delegate: *mut WindowDelegate,
+    window: *mut cef_window_t,
+) -> *mut cef_browser_view_t {
     let mut cef_url = cef_string_t::default();
     unsafe {
         let url = (*window_delegate).options.url.as_bytes();
@@ -89,20 +376,102 @@ extern "C" fn window_delegate_created(slf: *mut cef_window_delegate_t, window: *
     browser_settings.local_storage = cef_state_t_STATE_ENABLED;
     browser_settings.application_cache = cef_state_t_STATE_DISABLED;
 
-    let client = client::allocate(window);
-    let browser_view_delegate = browser_view_delegate::allocate();
+    let hooks = unsafe { (*window_delegate).options.client_hooks() };
+    let window_id = unsafe { (*window_delegate).window_id };
+    let client = client::allocate(window, window_id, hooks);
+    let on_browser_created = unsafe { (*window_delegate).options.on_browser_created.take() };
+    let browser_view_delegate = browser_view_delegate::allocate(window_id, on_browser_created);
+
+    unsafe {
+        // only kiosks with an idle timer need pages to report the user's activity
+        let extra_info = cef_dictionary_value_create();
+        let watch_activity = (*window_delegate)
+            .options
+            .kiosk
+            .as_ref()
+            .map_or(false, |kiosk| kiosk.idle_timeout.is_some());
+        if watch_activity {
+            let key = strings::to_cef_string(v8_window_handler::WATCH_ACTIVITY);
+            ((*extra_info).set_bool.expect("set_bool is a function"))(extra_info, &key, 1);
+        }
 
-    let browser_view = unsafe {
         (*client).inc_ref();
         (*browser_view_delegate).inc_ref();
         cef_browser_view_create(
             client as *mut cef_client_t,
             &cef_url,
             &browser_settings,
-            cef_dictionary_value_create(),
+            extra_info,
             cef_request_context_get_global_context(),
             browser_view_delegate as *mut cef_browser_view_delegate_t,
         )
+    }
+}
+
+/// Register the shortcuts which keep their keys from the page as window
+/// accelerators too, so they work wherever the focus is in the window.
+/// Accelerators can't use the command key, so those are left to the
+/// keyboard handler.
+unsafe fn register_accelerators(window_delegate: *mut WindowDelegate, window: *mut cef_window_t) {
+    let global = shortcuts::global();
+    let mut accelerators: Vec<KeyCombo> = Vec::new();
+    let dev_tools = devtools::shortcut();
+    for shortcut in (*window_delegate)
+        .options
+        .shortcuts
+        .iter()
+        .chain(global.iter())
+        .chain(dev_tools.iter())
+    {
+        if shortcut.swallow && !shortcut.combo.command && !accelerators.contains(&shortcut.combo) {
+            accelerators.push(shortcut.combo);
+        }
+    }
+
+    for (command_id, combo) in accelerators.iter().enumerate() {
+        (*window)
+            .set_accelerator
+            .expect("set_accelerator is a function")(
+            window,
+            command_id as c_int,
+            combo.key_code,
+            combo.shift as c_int,
+            combo.ctrl as c_int,
+            combo.alt as c_int,
+        );
+    }
+    (*window_delegate).accelerators = accelerators;
+}
+
+unsafe extern "C" fn on_accelerator(
+    slf: *mut cef_window_delegate_t,
+    _window: *mut cef_window_t,
+    command_id: c_int,
+) -> c_int {
+    let window_delegate = slf as *mut WindowDelegate;
+    let combo = match (*window_delegate).accelerators.get(command_id as usize) {
+        Some(combo) => *combo,
+        None => return 0,
+    };
+    let shortcut = shortcuts::find(&(*window_delegate).options.shortcuts, &combo);
+    let browser = windows::browser((*window_delegate).window_id);
+    match (shortcut, browser) {
+        (Some(shortcut), Some(browser)) => {
+            shortcut.run(&browser);
+            1
+        }
+        _ => 0,
+    }
+}
+
+extern "C" fn window_delegate_created(slf: *mut cef_window_delegate_t, window: *mut cef_window_t) {
+    log::debug!("window delegate created!");
+
+    let window_delegate = slf as *mut WindowDelegate;
+    windows::set_window(unsafe { (*window_delegate).window_id }, window);
+    let browser_view = match unsafe { (*window_delegate).popup_browser_view } {
+        Some(browser_view) => browser_view,
+        None => create_browser_view(window_delegate, window),
     };
 
     unsafe {
@@ -112,8 +481,13 @@ extern "C" fn window_delegate_created(slf: *mut cef_window_delegate_t, window: *
             browser_view as *mut cef_view_t,
         );
 
+        register_accelerators(window_delegate, window);
         (*window).show.unwrap()(window);
 
+        if (*window_delegate).options.always_on_top {
+            (*window).set_always_on_top.unwrap()(window, 1);
+        }
+
         if let Some(title) = &(*window_delegate).options.title {
             let mut cef_title = cef_string_t::default();
             let title = title.as_bytes();
@@ -135,25 +509,56 @@ extern "C" fn window_delegate_created(slf: *mut cef_window_delegate_t, window: *
             (*window).set_window_app_icon.unwrap()(window, icon);
         }
 
-        if let Some(size) = (*window_delegate).options.size {
-            let size: cef_size_t = cef_size_t {
-                width: size.0,
-                height: size.1,
-            };
-            (*window).center_window.unwrap()(window, &size);
+        let options = &(*window_delegate).options;
+        let saved = options
+            .state_key
+            .as_ref()
+            .and_then(|key| window_state::load(key));
+        let view = window as *mut cef_view_t;
+        if let Some(saved) = saved {
+            log::debug!("restoring window state {:?}", saved);
+            let bounds = window_state::visible_bounds(&saved);
+            (*view).set_bounds.unwrap()(view, &bounds);
+        } else {
+            match (options.position, options.size) {
+                (Some((x, y)), Some((width, height))) => {
+                    let bounds = cef_rect_t {
+                        x,
+                        y,
+                        width,
+                        height,
+                    };
+                    (*view).set_bounds.unwrap()(view, &bounds);
+                }
+                (Some((x, y)), None) => {
+                    (*view).set_position.unwrap()(view, &cef_point_t { x, y });
+                }
+                (None, Some(size)) => {
+                    let size: cef_size_t = cef_size_t {
+                        width: size.0,
+                        height: size.1,
+                    };
+                    (*window).center_window.unwrap()(window, &size);
+                }
+                (None, None) => {}
+            }
         }
 
-        if (*window_delegate).options.maximized {
+        if saved.map_or(options.maximized, |saved| saved.maximized) {
             (*window).maximize.unwrap()(window);
         }
 
-        if (*window_delegate).options.fullscreen {
+        if saved.map_or(options.fullscreen, |saved| saved.fullscreen) {
             (*window).set_fullscreen.unwrap()(window, 1);
         }
+
+        if let Some(kiosk) = &options.kiosk {
+            kiosk::start((*window_delegate).window_id, &options.url, kiosk);
+        }
     }
 }
 
-pub unsafe fn allocate(options: WindowOptions) -> *mut WindowDelegate {
+pub unsafe fn allocate(options: WindowOptions, window_id: WindowId) -> *mut WindowDelegate {
     let window_icon = if let Some(data) = options.window_icon {
         let image = cef_image_create();
         (*image).add_png.unwrap()(image, 1.0, data.as_ptr() as *const _, data.len() as u64);
@@ -181,9 +586,9 @@ pub unsafe fn allocate(options: WindowOptions) -> *mut WindowDelegate {
                         has_one_ref: Some(has_one_ref),
                         has_at_least_one_ref: Some(has_at_least_one_ref),
                     },
-                    get_preferred_size: None,
-                    get_minimum_size: None,
-                    get_maximum_size: None,
+                    get_preferred_size: Some(get_preferred_size),
+                    get_minimum_size: Some(get_minimum_size),
+                    get_maximum_size: Some(get_maximum_size),
                     get_height_for_width: None,
                     on_parent_view_changed: None,
                     on_child_view_changed: None,
@@ -199,11 +604,14 @@ pub unsafe fn allocate(options: WindowOptions) -> *mut WindowDelegate {
             can_maximize: Some(can_maximize),
             can_minimize: Some(can_minimize),
             can_close: Some(can_close),
-            on_accelerator: None,
+            on_accelerator: Some(on_accelerator),
             on_key_event: None,
         },
         ref_count: AtomicUsize::new(1),
         options,
+        window_id,
+        popup_browser_view: None,
+        accelerators: Vec::new(),
         window_icon,
         window_app_icon,
     };
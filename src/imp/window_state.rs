@@ -0,0 +1,220 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::bindings::{
+    cef_base_ref_counted_t, cef_display_get_alls, cef_display_get_count,
+    cef_display_get_matching_bounds, cef_display_t, cef_rect_t,
+};
+
+/// Where a window was and how it was shown when it was last closed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub maximized: bool,
+    pub fullscreen: bool,
+    /// The display the window was on
+    pub display_id: Option<i64>,
+}
+
+/// Remembers the state of windows between runs, keyed by
+/// `WindowOptions::state_key`, in a JSON file. Windows are restored to their
+/// saved state when they're opened, and their state is saved as they close.
+#[derive(Debug)]
+pub struct WindowStateStore {
+    path: PathBuf,
+    states: BTreeMap<String, WindowState>,
+}
+
+impl WindowStateStore {
+    /// Use the store at `path`, loading any states already saved there. A
+    /// missing or unreadable file starts an empty store.
+    pub fn new<P: Into<PathBuf>>(path: P) -> WindowStateStore {
+        let path = path.into();
+        let states = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::warn!(
+                    "ignoring invalid window state in `{}`: {}",
+                    path.display(),
+                    e
+                );
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        WindowStateStore { path, states }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, key: &str) -> Option<WindowState> {
+        self.states.get(key).copied()
+    }
+
+    /// Remember the state of the window with `key` and write the store out
+    pub fn set(&mut self, key: &str, state: WindowState) -> std::io::Result<()> {
+        self.states.insert(key.to_string(), state);
+        self.save()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&self.states)?;
+        std::fs::write(&self.path, json)
+    }
+}
+
+thread_local! {
+    static STORE: RefCell<Option<WindowStateStore>> = RefCell::new(None);
+}
+
+pub fn set_store(store: WindowStateStore) {
+    STORE.with(|s| *s.borrow_mut() = Some(store));
+}
+
+/// The saved state of the window with `key`, if there is a store
+pub fn load(key: &str) -> Option<WindowState> {
+    STORE.with(|s| s.borrow().as_ref().and_then(|store| store.get(key)))
+}
+
+/// Save the state of the window with `key`, if there is a store
+pub fn save(key: &str, state: WindowState) {
+    STORE.with(|s| {
+        if let Some(store) = s.borrow_mut().as_mut() {
+            if let Err(e) = store.set(key, state) {
+                log::warn!(
+                    "failed to save window state to `{}`: {}",
+                    store.path().display(),
+                    e
+                );
+            }
+        }
+    });
+}
+
+/// The bounds to restore a window to, moved (and shrunk if need be) so that
+/// the window is fully visible: on the display it was saved on if that's
+/// still connected, otherwise on the nearest display
+pub unsafe fn visible_bounds(state: &WindowState) -> cef_rect_t {
+    let bounds = cef_rect_t {
+        x: state.x,
+        y: state.y,
+        width: state.width,
+        height: state.height,
+    };
+
+    let display = state
+        .display_id
+        .and_then(|id| find_display(id))
+        .unwrap_or_else(|| cef_display_get_matching_bounds(&bounds, 0));
+    if display.is_null() {
+        return bounds;
+    }
+    let work_area = (*display)
+        .get_work_area
+        .expect("get_work_area is a function")(display);
+    release(display);
+    clamp(bounds, work_area)
+}
+
+/// The connected display with `id`, which the caller must release
+unsafe fn find_display(id: i64) -> Option<*mut cef_display_t> {
+    let mut count = cef_display_get_count();
+    let mut displays = vec![std::ptr::null_mut(); count as usize];
+    cef_display_get_alls(&mut count, displays.as_mut_ptr());
+    displays.truncate(count as usize);
+
+    let mut found = None;
+    for display in displays {
+        if found.is_none() && (*display).get_id.expect("get_id is a function")(display) == id {
+            found = Some(display);
+        } else {
+            release(display);
+        }
+    }
+    found
+}
+
+/// The id of the display `bounds` are (mostly) on
+pub unsafe fn display_id(bounds: &cef_rect_t) -> Option<i64> {
+    let display = cef_display_get_matching_bounds(bounds, 0);
+    if display.is_null() {
+        return None;
+    }
+    let id = (*display).get_id.expect("get_id is a function")(display);
+    release(display);
+    Some(id)
+}
+
+unsafe fn release(display: *mut cef_display_t) {
+    (*display).base.release.expect("release is a function")(display as *mut cef_base_ref_counted_t);
+}
+
+fn clamp(bounds: cef_rect_t, area: cef_rect_t) -> cef_rect_t {
+    let width = bounds.width.min(area.width);
+    let height = bounds.height.min(area.height);
+    cef_rect_t {
+        x: bounds.x.max(area.x).min(area.x + area.width - width),
+        y: bounds.y.max(area.y).min(area.y + area.height - height),
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> cef_rect_t {
+        cef_rect_t {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn parts(rect: cef_rect_t) -> (i32, i32, i32, i32) {
+        (rect.x, rect.y, rect.width, rect.height)
+    }
+
+    const WORK_AREA: (i32, i32, i32, i32) = (0, 40, 1920, 1040);
+
+    fn clamped(bounds: cef_rect_t) -> (i32, i32, i32, i32) {
+        let (x, y, width, height) = WORK_AREA;
+        parts(clamp(bounds, rect(x, y, width, height)))
+    }
+
+    #[test]
+    fn leaves_visible_windows_alone() {
+        assert_eq!(clamped(rect(100, 100, 800, 600)), (100, 100, 800, 600));
+    }
+
+    #[test]
+    fn moves_windows_which_are_fully_off_screen() {
+        assert_eq!(clamped(rect(3000, 100, 800, 600)), (1120, 100, 800, 600));
+        assert_eq!(clamped(rect(-2000, -2000, 800, 600)), (0, 40, 800, 600));
+        assert_eq!(clamped(rect(100, 5000, 800, 600)), (100, 480, 800, 600));
+    }
+
+    #[test]
+    fn moves_windows_which_are_partly_off_screen() {
+        assert_eq!(clamped(rect(1500, 100, 800, 600)), (1120, 100, 800, 600));
+        assert_eq!(clamped(rect(-300, 0, 800, 600)), (0, 40, 800, 600));
+    }
+
+    #[test]
+    fn shrinks_windows_larger_than_the_work_area() {
+        assert_eq!(clamped(rect(-10, -10, 2560, 1440)), WORK_AREA);
+        assert_eq!(clamped(rect(500, 200, 800, 2000)), (500, 40, 800, 1040));
+    }
+}
@@ -0,0 +1,377 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use super::bindings::{
+    cef_process_id_t_PID_RENDERER, cef_process_message_create, cef_quit_message_loop, cef_window_t,
+};
+use super::browser::Browser;
+use super::window_delegate::WindowOptions;
+use super::{devtools, kiosk, modal_overlay, strings};
+
+/// Identifies a window opened with `Cef::open_window`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowId(u64);
+
+impl std::fmt::Display for WindowId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "window #{}", self.0)
+    }
+}
+
+/// When to stop the message loop (causing `Cef::run` to return) as windows
+/// close
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuitPolicy {
+    /// Quit once every window has closed
+    LastWindowClosed,
+    /// Quit once the main window (the first window opened) has closed, even
+    /// if other windows are still open
+    MainWindowClosed,
+    /// Never quit automatically; call `Cef::quit` yourself
+    Never,
+}
+
+impl Default for QuitPolicy {
+    fn default() -> QuitPolicy {
+        QuitPolicy::LastWindowClosed
+    }
+}
+
+/// What to do when a window is asked to close, returned from a
+/// `CloseRequested` hook
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CloseDecision {
+    /// Close the window (after asking the page, see `CloseRequested`)
+    Allow,
+    /// Keep the window open
+    Deny,
+    /// Decide later by calling `CloseRequest::allow` or `CloseRequest::deny`,
+    /// for example once your own "you have unsaved changes" prompt has been
+    /// answered
+    Defer,
+}
+
+/// Called when the user tries to close a window, with a `CloseRequest` that
+/// only needs answering if `CloseDecision::Defer` is returned.
+///
+/// Once Rust allows the close, the page is asked next if it registered
+/// `cef.onCloseRequested(async () => boolean)`, and finally the page's
+/// `beforeunload` handlers run.
+pub type CloseRequested = Box<dyn FnMut(&Browser, CloseRequest) -> CloseDecision>;
+
+/// A pending request to close a window
+#[derive(Debug)]
+pub struct CloseRequest {
+    window_id: WindowId,
+}
+
+impl CloseRequest {
+    pub(crate) fn new(window_id: WindowId) -> CloseRequest {
+        CloseRequest { window_id }
+    }
+
+    pub fn window_id(&self) -> WindowId {
+        self.window_id
+    }
+
+    /// Carry on closing the window
+    pub fn allow(self) {
+        if allow_close(self.window_id) {
+            if let Some(browser) = browser(self.window_id) {
+                browser.close(false);
+            }
+        }
+    }
+
+    /// Keep the window open
+    pub fn deny(self) {
+        log::debug!("kept {} open", self.window_id);
+    }
+}
+
+/// The state of an open window
+#[derive(Default)]
+struct OpenWindow {
+    window: Option<*mut cef_window_t>,
+    browser: Option<Browser>,
+    /// Set once both Rust and the page have agreed to close the window, after
+    /// which only `beforeunload` can stop it
+    close_approved: bool,
+    /// Set once `beforeunload` has let the page go too, so the window is
+    /// closing for good
+    closing: bool,
+    /// Whether the page has registered `cef.onCloseRequested`
+    page_handles_close: bool,
+}
+
+thread_local! {
+    static NEXT_ID: Cell<u64> = Cell::new(1);
+    static QUIT_POLICY: Cell<QuitPolicy> = Cell::new(QuitPolicy::default());
+    static MAIN_WINDOW: Cell<Option<WindowId>> = Cell::new(None);
+    static OPEN_WINDOWS: RefCell<BTreeMap<WindowId, OpenWindow>> = RefCell::new(BTreeMap::new());
+    static PENDING_POPUPS: RefCell<BTreeMap<WindowId, WindowOptions>> = RefCell::new(BTreeMap::new());
+    static ON_WINDOW_CLOSED: RefCell<Option<Box<dyn FnMut(WindowId)>>> = RefCell::new(None);
+}
+
+pub fn set_quit_policy(policy: QuitPolicy) {
+    QUIT_POLICY.with(|p| p.set(policy));
+}
+
+pub fn set_on_window_closed(on_window_closed: Box<dyn FnMut(WindowId)>) {
+    ON_WINDOW_CLOSED.with(|c| *c.borrow_mut() = Some(on_window_closed));
+}
+
+/// Track a new window, returning its id. The first window registered becomes
+/// the main window.
+pub fn register() -> WindowId {
+    let id = WindowId(NEXT_ID.with(|n| n.replace(n.get() + 1)));
+    OPEN_WINDOWS.with(|w| w.borrow_mut().insert(id, OpenWindow::default()));
+    MAIN_WINDOW.with(|m| {
+        if m.get().is_none() {
+            m.set(Some(id));
+        }
+    });
+    log::debug!("opened {}", id);
+    id
+}
+
+/// Hold on to the options for a popup's window until CEF has created the
+/// popup's browser view
+pub fn set_pending_popup(id: WindowId, options: WindowOptions) {
+    PENDING_POPUPS.with(|p| p.borrow_mut().insert(id, options));
+}
+
+pub fn take_pending_popup(id: WindowId) -> Option<WindowOptions> {
+    PENDING_POPUPS.with(|p| p.borrow_mut().remove(&id))
+}
+
+/// Remember the CEF window, once it has been created
+pub fn set_window(id: WindowId, window: *mut cef_window_t) {
+    OPEN_WINDOWS.with(|w| {
+        if let Some(open_window) = w.borrow_mut().get_mut(&id) {
+            open_window.window = Some(window);
+        }
+    });
+}
+
+pub fn window(id: WindowId) -> Option<*mut cef_window_t> {
+    OPEN_WINDOWS.with(|w| w.borrow().get(&id).and_then(|w| w.window))
+}
+
+/// Run a `cef.window` command from the page on its window
+pub fn window_command(id: WindowId, command: &str) {
+    if command == "activity" {
+        kiosk::activity(id);
+        return;
+    }
+    if kiosk::is_kiosk(id) && command != "close" {
+        log::debug!("ignoring `{}` for kiosk {}", command, id);
+        return;
+    }
+    if command == "showDevTools" || command == "closeDevTools" {
+        match browser(id) {
+            Some(browser) => devtools::page_command(&browser, command == "showDevTools"),
+            None => log::warn!("can't {} in {}: it has no browser yet", command, id),
+        }
+        return;
+    }
+
+    let window = match window(id) {
+        Some(window) => window,
+        None => {
+            log::warn!("can't {} {}: it has no window yet", command, id);
+            return;
+        }
+    };
+
+    log::debug!("{} {}", command, id);
+    unsafe {
+        match command {
+            "minimize" => (*window).minimize.expect("minimize is a function")(window),
+            "maximize" => (*window).maximize.expect("maximize is a function")(window),
+            "restore" => (*window).restore.expect("restore is a function")(window),
+            // goes through `can_close`, just like the user closing the window
+            "close" => (*window).close.expect("close is a function")(window),
+            "startDrag" => start_drag(window),
+            _ => log::warn!("unknown window command `{}`", command),
+        }
+    }
+}
+
+/// Start moving the window with the mouse, as though the user had pressed the
+/// mouse on its titlebar
+#[cfg(windows)]
+unsafe fn start_drag(window: *mut cef_window_t) {
+    use winapi::um::winuser::{ReleaseCapture, SendMessageW, HTCAPTION, WM_NCLBUTTONDOWN};
+
+    let hwnd = (*window)
+        .get_window_handle
+        .expect("get_window_handle is a function")(window);
+    ReleaseCapture();
+    SendMessageW(hwnd as _, WM_NCLBUTTONDOWN, HTCAPTION as _, 0);
+}
+
+#[cfg(not(windows))]
+unsafe fn start_drag(_window: *mut cef_window_t) {
+    log::warn!(
+        "startDrag isn't supported on this platform; use `-webkit-app-region: drag` instead"
+    );
+}
+
+/// Remember the browser shown in a window, once it has been created
+pub fn set_browser(id: WindowId, browser: Browser) {
+    OPEN_WINDOWS.with(|w| {
+        if let Some(window) = w.borrow_mut().get_mut(&id) {
+            window.browser = Some(browser);
+        }
+    });
+}
+
+pub fn browser(id: WindowId) -> Option<Browser> {
+    OPEN_WINDOWS.with(|w| w.borrow().get(&id).and_then(|w| w.browser.clone()))
+}
+
+/// The window showing `browser`, if it's one of ours
+pub fn window_of(browser: &Browser) -> Option<WindowId> {
+    OPEN_WINDOWS.with(|w| {
+        w.borrow()
+            .iter()
+            .find(|(_, w)| w.browser.as_ref().map_or(false, |b| b.is_same(browser)))
+            .map(|(id, _)| *id)
+    })
+}
+
+pub fn open_windows() -> Vec<WindowId> {
+    OPEN_WINDOWS.with(|w| w.borrow().keys().cloned().collect())
+}
+
+pub fn main_window() -> Option<WindowId> {
+    MAIN_WINDOW.with(|m| m.get())
+}
+
+/// Remember whether the page in a window has registered `cef.onCloseRequested`
+pub fn set_page_handles_close(id: WindowId, page_handles_close: bool) {
+    OPEN_WINDOWS.with(|w| {
+        if let Some(window) = w.borrow_mut().get_mut(&id) {
+            window.page_handles_close = page_handles_close;
+        }
+    });
+}
+
+pub fn close_approved(id: WindowId) -> bool {
+    OPEN_WINDOWS.with(|w| {
+        w.borrow()
+            .get(&id)
+            .map_or(false, |w| w.close_approved || w.closing)
+    })
+}
+
+/// Called while the page's `beforeunload` handlers decide whether the window
+/// can close. If they keep it open, the next close asks Rust and the page
+/// again.
+pub fn unload_pending(id: WindowId) {
+    OPEN_WINDOWS.with(|w| {
+        if let Some(window) = w.borrow_mut().get_mut(&id) {
+            window.close_approved = false;
+        }
+    });
+}
+
+/// Called once the page's `beforeunload` handlers have let it close
+pub fn closing(id: WindowId) {
+    OPEN_WINDOWS.with(|w| {
+        if let Some(window) = w.borrow_mut().get_mut(&id) {
+            window.closing = true;
+        }
+    });
+}
+
+/// Continue closing a window once Rust has allowed it to close. If the page
+/// has registered `cef.onCloseRequested` it gets asked next, and this returns
+/// false; otherwise the close is approved and this returns true.
+pub fn allow_close(id: WindowId) -> bool {
+    let (browser, page_handles_close) = OPEN_WINDOWS.with(|w| match w.borrow().get(&id) {
+        Some(window) => (window.browser.clone(), window.page_handles_close),
+        None => (None, false),
+    });
+
+    match browser {
+        Some(browser) if page_handles_close => {
+            log::debug!("asking the page whether {} can close", id);
+            unsafe {
+                let browser = browser.as_raw();
+                let frame = (*browser)
+                    .get_main_frame
+                    .expect("get_main_frame is a function")(browser);
+                let cef_message_name = strings::to_cef_string("close_requested");
+                let message = cef_process_message_create(&cef_message_name);
+                ((*frame)
+                    .send_process_message
+                    .expect("send_process_message is a function"))(
+                    frame,
+                    cef_process_id_t_PID_RENDERER,
+                    message,
+                );
+            }
+            false
+        }
+        _ => {
+            OPEN_WINDOWS.with(|w| {
+                if let Some(window) = w.borrow_mut().get_mut(&id) {
+                    window.close_approved = true;
+                }
+            });
+            true
+        }
+    }
+}
+
+/// Called with the page's answer to `cef.onCloseRequested`
+pub fn page_answered_close(id: WindowId, allow: bool) {
+    if !allow {
+        log::debug!("the page kept {} open", id);
+        return;
+    }
+
+    OPEN_WINDOWS.with(|w| {
+        if let Some(window) = w.borrow_mut().get_mut(&id) {
+            window.close_approved = true;
+        }
+    });
+    if let Some(browser) = browser(id) {
+        browser.close(false);
+    }
+}
+
+/// Stop tracking a window which has closed, quitting if the quit policy says so
+pub fn closed(id: WindowId) {
+    PENDING_POPUPS.with(|p| p.borrow_mut().remove(&id));
+    kiosk::stop(id);
+    modal_overlay::dismiss(id);
+    let removed = OPEN_WINDOWS.with(|w| w.borrow_mut().remove(&id));
+    if removed.is_none() {
+        return;
+    }
+    log::debug!("closed {}", id);
+
+    let on_window_closed = ON_WINDOW_CLOSED.with(|c| c.borrow_mut().take());
+    if let Some(mut on_window_closed) = on_window_closed {
+        on_window_closed(id);
+        ON_WINDOW_CLOSED.with(|c| {
+            let mut c = c.borrow_mut();
+            if c.is_none() {
+                *c = Some(on_window_closed);
+            }
+        });
+    }
+
+    let quit = match QUIT_POLICY.with(|p| p.get()) {
+        QuitPolicy::LastWindowClosed => OPEN_WINDOWS.with(|w| w.borrow().is_empty()),
+        QuitPolicy::MainWindowClosed => main_window() == Some(id),
+        QuitPolicy::Never => false,
+    };
+    if quit {
+        log::debug!("quitting as {} closed", id);
+        unsafe { cef_quit_message_loop() };
+    }
+}
@@ -1,23 +1,102 @@
 use std::mem::size_of;
+use std::path::PathBuf;
 use std::ptr::null_mut;
 
 mod imp;
 use imp::bindings::{
     cef_app_t, cef_execute_process, cef_initialize, cef_log_severity_t_LOGSEVERITY_ERROR,
-    cef_log_severity_t_LOGSEVERITY_INFO, cef_main_args_t, cef_run_message_loop, cef_settings_t,
-    cef_shutdown, cef_window_create_top_level, cef_window_delegate_t,
+    cef_log_severity_t_LOGSEVERITY_INFO, cef_main_args_t, cef_quit_message_loop,
+    cef_run_message_loop, cef_settings_t, cef_shutdown, cef_window_create_top_level,
+    cef_window_delegate_t,
 };
+pub use imp::browser::Browser;
+pub use imp::cookie_manager::{Cookie, CookieManager, CookiePriority, SameSite};
+pub use imp::devtools::{DevTools, DevToolsOptions};
+pub use imp::devtools_protocol::{DevToolsClient, OnEvent, SubscriptionId};
+pub use imp::dialog_handler::{
+    AutomatedDialogs, DialogHandler, DirectoryChooser, FileDialogCallback, SandboxedDirectory,
+};
+pub use imp::display_handler::ConsoleMessage;
+pub use imp::download_handler::{
+    DenyDownloads, Download, DownloadDestination, DownloadHandler, DownloadState,
+    PromptForDownloads, SaveToDirectory,
+};
+pub use imp::file_dialog::{FileDialogMode, FileDialogOptions, FileDialogResult, FileFilter};
+pub use imp::input::{BrowserInput, Modifiers, MouseButton, TouchPhase};
+pub use imp::js_dialog_handler::{
+    AutoAccept, DenyAll, InPageModal, JsDialog, JsDialogCallback, JsDialogHandler, JsDialogKind,
+    LogOnly,
+};
+pub use imp::kiosk::KioskOptions;
+pub use imp::offscreen::{Cursor, Frame, FrameSink, PaintElement, Rect, WindowlessOptions};
+pub use imp::pdf_options::{HeaderFooter, Margins, Orientation, PaperSize, PdfOptions};
+pub use imp::pdf_renderer::{PdfJob, PdfRenderer, PdfSource};
+pub use imp::pending::Pending;
+pub use imp::popups::{Popup, PopupAction, PopupPolicy};
+pub use imp::print_backend::{
+    PrintBackend, PrintDialogCallback, PrintJobCallback, PrintToFileBackend,
+};
+pub use imp::print_settings::{ColorMode, Duplex, PageRange, PrintSettings};
+pub use imp::screenshot::{OnCaptured, ScreenshotArea, ScreenshotOptions};
+pub use imp::settings::{ProcessType, Settings, Switch, SwitchPreset};
+pub use imp::shortcuts::{KeyCombo, Shortcut, ShortcutAction, Shortcuts};
+#[cfg(feature = "testing")]
+pub use imp::testing;
+pub use imp::tracing::OnTraceWritten;
 pub use imp::window_delegate::WindowOptions;
-use imp::{app, window_delegate};
+pub use imp::window_state::{WindowState, WindowStateStore};
+pub use imp::windows::{CloseDecision, CloseRequest, CloseRequested, QuitPolicy, WindowId};
+use imp::{
+    app, devtools, download_handler, offscreen, pending, print_handler, shortcuts, strings,
+    tracing, window_delegate, window_state, windows,
+};
 
 pub struct Cef {}
 
 impl Cef {
-    #[cfg(unix)]
     pub fn initialize(
         debug_port: Option<u16>,
         disable_command_line_args: bool,
     ) -> Result<Cef, Box<dyn std::error::Error>> {
+        Cef::initialize_with(Cef::settings(debug_port, disable_command_line_args))
+    }
+
+    /// Initialize CEF for running without any visible UI, for example to
+    /// render PDFs with a `PdfRenderer` on a machine without a GPU. GPU
+    /// acceleration is disabled so everything is rendered in software, and
+    /// windowless rendering is enabled. This doesn't remove the need for a
+    /// display: on Linux, CEF still needs an X server (e.g. `xvfb-run`).
+    pub fn initialize_headless(
+        debug_port: Option<u16>,
+        disable_command_line_args: bool,
+    ) -> Result<Cef, Box<dyn std::error::Error>> {
+        Cef::initialize_with(
+            Cef::settings(debug_port, disable_command_line_args)
+                .with_preset(SwitchPreset::SoftwareRendering)
+                .with_windowless_rendering(),
+        )
+    }
+
+    fn settings(debug_port: Option<u16>, disable_command_line_args: bool) -> Settings {
+        // keep the on-screen keyboard working when a USB keyboard is plugged
+        // in, as `initialize` always has
+        let mut settings = Settings::new()
+            .with_switch(Switch::new("disable-usb-keyboard-detect").only_in(ProcessType::Browser));
+        if let Some(port) = debug_port {
+            settings = settings.with_debug_port(port);
+        }
+        if disable_command_line_args {
+            settings = settings.without_command_line_args();
+        }
+        settings
+    }
+
+    /// Initialize CEF with `settings`. This runs CEF's other processes too
+    /// (the renderer, GPU process and so on), which exit here instead of
+    /// returning, so call it first thing in `main` with the same settings
+    /// every time.
+    #[cfg(unix)]
+    pub fn initialize_with(settings: Settings) -> Result<Cef, Box<dyn std::error::Error>> {
         use std::ffi::CString;
         use std::os::raw::{c_char, c_int};
         let args: Vec<CString> = std::env::args().map(|x| CString::new(x).unwrap()).collect();
@@ -28,7 +107,11 @@ impl Cef {
         };
 
         log::debug!("preparing app");
-        let app = app::allocate();
+        let debug_port = settings.debug_port();
+        let disable_command_line_args = settings.command_line_args_disabled();
+        let cache_path = settings.cache_path().map(|path| path.display().to_string());
+        let windowless_rendering = settings.windowless_rendering_enabled();
+        let app = app::allocate(settings.switches().to_vec());
 
         log::debug!("executing process");
         let exit_code = unsafe {
@@ -42,10 +125,15 @@ impl Cef {
         let mut settings = cef_settings_t::default();
         settings.size = size_of::<cef_settings_t>() as u64;
         settings.no_sandbox = 1;
+        settings.windowless_rendering_enabled = if windowless_rendering { 1 } else { 0 };
         if let Some(port) = debug_port {
             settings.remote_debugging_port = port as i32;
         }
         settings.command_line_args_disabled = if disable_command_line_args { 1 } else { 0 };
+        if let Some(path) = &cache_path {
+            settings.cache_path = strings::to_cef_string(path);
+            settings.root_cache_path = strings::to_cef_string(path);
+        }
         if cfg!(debug_assertions) {
             settings.log_severity = cef_log_severity_t_LOGSEVERITY_INFO;
         } else {
@@ -59,15 +147,17 @@ impl Cef {
                 return Err(Box::from("failed to initialize"));
             }
         }
+        offscreen::set_enabled(windowless_rendering);
 
         Ok(Cef {})
     }
 
+    /// Initialize CEF with `settings`. This runs CEF's other processes too
+    /// (the renderer, GPU process and so on), which exit here instead of
+    /// returning, so call it first thing in `main` with the same settings
+    /// every time.
     #[cfg(windows)]
-    pub fn initialize(
-        debug_port: Option<u16>,
-        disable_command_line_args: bool,
-    ) -> Result<Cef, Box<dyn std::error::Error>> {
+    pub fn initialize_with(settings: Settings) -> Result<Cef, Box<dyn std::error::Error>> {
         let main_args = unsafe {
             cef_main_args_t {
                 instance: winapi::um::libloaderapi::GetModuleHandleA(null_mut())
@@ -76,7 +166,11 @@ impl Cef {
         };
 
         log::debug!("preparing app");
-        let app = app::allocate();
+        let debug_port = settings.debug_port();
+        let disable_command_line_args = settings.command_line_args_disabled();
+        let cache_path = settings.cache_path().map(|path| path.display().to_string());
+        let windowless_rendering = settings.windowless_rendering_enabled();
+        let app = app::allocate(settings.switches().to_vec());
 
         log::debug!("executing process");
         let exit_code = unsafe {
@@ -90,10 +184,15 @@ impl Cef {
         let mut settings = cef_settings_t::default();
         settings.size = size_of::<cef_settings_t>() as u64;
         settings.no_sandbox = 1;
+        settings.windowless_rendering_enabled = if windowless_rendering { 1 } else { 0 };
         if let Some(port) = debug_port {
             settings.remote_debugging_port = port as i32;
         }
         settings.command_line_args_disabled = if disable_command_line_args { 1 } else { 0 };
+        if let Some(path) = &cache_path {
+            settings.cache_path = strings::to_cef_string(path);
+            settings.root_cache_path = strings::to_cef_string(path);
+        }
         if cfg!(debug_assertions) {
             settings.log_severity = cef_log_severity_t_LOGSEVERITY_INFO;
         } else {
@@ -107,19 +206,160 @@ impl Cef {
                 return Err(Box::from("failed to initialize"));
             }
         }
+        offscreen::set_enabled(windowless_rendering);
 
         Ok(Cef {})
     }
 
-    pub fn open_window(&self, options: WindowOptions) -> Result<(), Box<dyn std::error::Error>> {
-        let window_delegate = unsafe { window_delegate::allocate(options) };
+    /// Open a new top-level window. The first window opened is the main
+    /// window (see `QuitPolicy::MainWindowClosed`).
+    pub fn open_window(
+        &self,
+        options: WindowOptions,
+    ) -> Result<WindowId, Box<dyn std::error::Error>> {
+        let window_id = windows::register();
+        let window_delegate = unsafe { window_delegate::allocate(options, window_id) };
         log::debug!("creating window");
         let _window = unsafe {
             (*window_delegate).inc_ref();
             cef_window_create_top_level(window_delegate as *mut cef_window_delegate_t)
         };
 
-        Ok(())
+        Ok(window_id)
+    }
+
+    /// Create a browser which renders off-screen into `options.frame_sink`
+    /// instead of into a window, for drawing the page yourself. It lives
+    /// until it's closed with `Browser::close`. CEF must have been initialized
+    /// with `Settings::with_windowless_rendering`.
+    pub fn open_windowless(
+        &self,
+        options: WindowlessOptions,
+    ) -> Result<Browser, Box<dyn std::error::Error>> {
+        offscreen::check_enabled()?;
+        offscreen::create_browser(options)
+            .ok_or_else(|| Box::from("failed to create windowless browser"))
+    }
+
+    /// Decide when closing windows stops the message loop. Defaults to
+    /// `QuitPolicy::LastWindowClosed`.
+    pub fn set_quit_policy(&self, policy: QuitPolicy) {
+        windows::set_quit_policy(policy);
+    }
+
+    /// Called whenever a window opened with `open_window` has closed
+    pub fn on_window_closed(&self, on_window_closed: Box<dyn FnMut(WindowId)>) {
+        windows::set_on_window_closed(on_window_closed);
+    }
+
+    /// Set the shortcuts which work in every window. Set these before opening
+    /// windows, so they can be registered as window accelerators.
+    pub fn set_shortcuts(&self, shortcuts: Shortcuts) {
+        shortcuts::set_global(shortcuts);
+    }
+
+    /// Choose where the user can open DevTools from: a shortcut, the context
+    /// menu and the page. Set this before opening windows, so the shortcut can
+    /// be registered as a window accelerator.
+    pub fn set_dev_tools(&self, dev_tools: DevTools) {
+        devtools::set_dev_tools(dev_tools);
+    }
+
+    /// Save and restore the state of windows which have a
+    /// `WindowOptions::state_key` using `store`
+    pub fn set_window_state_store(&self, store: WindowStateStore) {
+        window_state::set_store(store);
+    }
+
+    /// The windows which are currently open
+    pub fn open_windows(&self) -> Vec<WindowId> {
+        windows::open_windows()
+    }
+
+    /// The browser shown in a window, once it has been created
+    pub fn browser(&self, window_id: WindowId) -> Option<Browser> {
+        windows::browser(window_id)
+    }
+
+    /// Create a renderer which prints pages to PDF using windowless browsers,
+    /// rendering at most `max_concurrent` pages at once. CEF must have been
+    /// initialized with `Settings::with_windowless_rendering`.
+    pub fn pdf_renderer(
+        &self,
+        max_concurrent: usize,
+    ) -> Result<PdfRenderer, Box<dyn std::error::Error>> {
+        offscreen::check_enabled()?;
+        Ok(PdfRenderer::new(max_concurrent))
+    }
+
+    /// Set the backend used for native printing (`window.print()` and
+    /// `cef.print(options)`). Without one, printing is cancelled.
+    pub fn set_print_backend(&self, backend: Box<dyn PrintBackend>) {
+        print_handler::set_backend(backend);
+    }
+
+    /// The command line the browser process ended up with, after the switches
+    /// from `Settings` were added, for diagnostics. Other processes log
+    /// theirs at debug level.
+    pub fn command_line(&self) -> Option<String> {
+        app::command_line()
+    }
+
+    /// The cookie manager of the global request context, which every browser
+    /// uses. Use `Browser::cookie_manager` for a browser's own.
+    pub fn cookie_manager(&self) -> Option<CookieManager> {
+        CookieManager::global()
+    }
+
+    /// Cancel a download by its `Download::id`. Returns false if there's no
+    /// such download, or it has already finished.
+    pub fn cancel_download(&self, id: u32) -> bool {
+        download_handler::cancel(id)
+    }
+
+    pub fn pause_download(&self, id: u32) -> bool {
+        download_handler::pause(id)
+    }
+
+    pub fn resume_download(&self, id: u32) -> bool {
+        download_handler::resume(id)
+    }
+
+    /// Start recording a performance trace of every process, for
+    /// `chrome://tracing` or Perfetto. An empty list of `categories` records
+    /// CEF's defaults; `disabled-by-default-*` categories must be named. In
+    /// debug builds, pages can also start and stop tracing with
+    /// `cef.trace.start(categories)` and `cef.trace.stop(path)`.
+    pub fn begin_tracing(&self, categories: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(tracing::begin(categories)?)
+    }
+
+    /// Stop tracing and write the trace as Chrome trace event JSON to `path`,
+    /// or to a new temporary file. `on_done` is called with the path once the
+    /// other processes have sent their traces and it has been written, or with
+    /// `None` if tracing wasn't running.
+    pub fn end_tracing(&self, path: Option<PathBuf>, on_done: Option<OnTraceWritten>) {
+        tracing::end(path, on_done);
+    }
+
+    /// Stop tracing like `end_tracing`, as a `Future` of the trace's path
+    pub fn end_tracing_async(&self, path: Option<PathBuf>) -> Pending<PathBuf> {
+        let (pending, resolver) = pending::pending();
+        let mut resolver = Some(resolver);
+        tracing::end(
+            path,
+            Some(Box::from(move |path: Option<PathBuf>| {
+                if let Some(resolver) = resolver.take() {
+                    resolver.resolve(path.ok_or_else(|| "failed to write the trace".to_string()));
+                }
+            })),
+        );
+        pending
+    }
+
+    /// Stop the message loop, causing `run` to return
+    pub fn quit() {
+        unsafe { cef_quit_message_loop() };
     }
 
     pub fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
@@ -0,0 +1,79 @@
+//! End-to-end tests of the `cef.*` extensions in a headless browser. They need
+//! the CEF runtime next to the test binary, so they only build with the
+//! `testing` feature: `cargo test --features testing --test pages`. On Linux
+//! without a display, run that under `xvfb-run`.
+
+use cef_simple::testing::{self, Harness};
+use serde_json::json;
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<body>
+    <h1>Hello, tests!</h1>
+    <script>console.log("page loaded");</script>
+</body>
+</html>"#;
+
+fn evaluates_javascript(harness: &Harness) {
+    let page = harness.load_html(PAGE).unwrap();
+    assert_eq!(page.eval("1 + 2").unwrap(), json!(3));
+    assert_eq!(
+        page.eval("document.querySelector('h1').textContent")
+            .unwrap(),
+        json!("Hello, tests!")
+    );
+    assert!(page.eval("Promise.reject(new Error('nope'))").is_err());
+}
+
+fn captures_console_output(harness: &Harness) {
+    let page = harness.load_html(PAGE).unwrap();
+    page.wait_for_console("page loaded").unwrap();
+
+    page.eval("console.warn('careful')").unwrap();
+    let message = page.wait_for_console("careful").unwrap();
+    assert_eq!(message.level, log::Level::Warn);
+}
+
+fn prints_to_pdf(harness: &Harness) {
+    let page = harness.load_html(PAGE).unwrap();
+    // the path is redirected into the page's PDF directory
+    assert_eq!(
+        page.eval("cef.printToPDF('/somewhere/else/out.pdf').then(() => true)")
+            .unwrap(),
+        json!(true)
+    );
+    let pdf = page.printed_pdf("out.pdf").expect("the PDF was printed");
+    assert!(pdf.starts_with(b"%PDF"));
+}
+
+fn answers_file_dialogs(harness: &Harness) {
+    let page = harness.load_html(PAGE).unwrap();
+    let path = page.pdf_directory().join("data.csv");
+    page.file_dialogs().answer(vec![path.clone()]);
+    assert_eq!(
+        page.eval("cef.openFileDialog('Open data', null, 'CSV Files|.csv')")
+            .unwrap(),
+        json!([path.display().to_string()])
+    );
+    assert_eq!(page.file_dialogs().requests()[0].title, "Open data");
+
+    // dialogs without an answer are cancelled
+    assert_eq!(
+        page.eval("cef.openFileDialog('Open').catch((e) => cef.isCancelled(e))")
+            .unwrap(),
+        json!(true)
+    );
+}
+
+fn main() {
+    if cfg!(target_os = "linux") && std::env::var_os("DISPLAY").is_none() {
+        println!("skipping the page tests: there's no display to run CEF on");
+        return;
+    }
+    testing::run(&[
+        ("evaluates_javascript", evaluates_javascript),
+        ("captures_console_output", captures_console_output),
+        ("prints_to_pdf", prints_to_pdf),
+        ("answers_file_dialogs", answers_file_dialogs),
+    ]);
+}